@@ -34,6 +34,7 @@ mod render;
 ///
 /// - `table_name` overrides the value returned by `Model::table_name` implementation
 /// - `foreign_key` overrides the value returned by `Model::foreign_key` implementation
+/// - `cursor_column` sets the column returned by `Model::cursor_column`, enabling keyset pagination
 /// - `belongs_to` annotates the struct with a "belongs to" relationship to anoter model
 /// - `has_many` annotates the struct with a "has many" relationship to another model
 ///
@@ -127,7 +128,7 @@ mod render;
 /// }
 /// ```
 ///
-#[proc_macro_derive(Model, attributes(belongs_to, has_many, table_name, foreign_key))]
+#[proc_macro_derive(Model, attributes(belongs_to, has_many, table_name, foreign_key, cursor_column))]
 pub fn derive_model(input: TokenStream) -> TokenStream {
     model::impl_derive_model(input)
 }
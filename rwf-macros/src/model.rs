@@ -82,6 +82,8 @@ pub fn impl_derive_model(input: TokenStream) -> TokenStream {
                 &input.attrs,
             );
 
+            let cursor_column = handle_override("cursor_column", quote! {}, &input.attrs);
+
             quote! {
                 #[automatically_derived]
                 impl rwf::model::FromRow for #ident {
@@ -96,6 +98,7 @@ pub fn impl_derive_model(input: TokenStream) -> TokenStream {
                 impl rwf::model::Model for #ident {
                     #table_name
                     #foreign_key
+                    #cursor_column
 
                     fn column_names() -> &'static[&'static str] {
                         &[
@@ -161,6 +164,14 @@ fn handle_override(
                             }
                         }
 
+                        "cursor_column" => {
+                            quote! {
+                                fn cursor_column() -> Option<&'static str> {
+                                    Some(#tokens)
+                                }
+                            }
+                        }
+
                         _ => panic!("unexpected attribute: {}", name),
                     }
                 } else {
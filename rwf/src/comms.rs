@@ -6,6 +6,7 @@
 //!
 //! * Send messages between clients connected to different Rwf servers
 //! * ORM-triggered events, e.g. callbacks
+use crate::config::{get_config, SendQueuePolicy};
 use crate::controller::auth::SessionId;
 use crate::http::websocket::Message;
 use crate::http::ToMessage;
@@ -13,11 +14,16 @@ use crate::model::{Model, Value};
 
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use thiserror::Error;
-use tokio::sync::broadcast::{channel, error::SendError, Receiver, Sender};
+use tokio::sync::broadcast::{
+    channel,
+    error::{RecvError, SendError},
+    Receiver, Sender,
+};
 use tracing::debug;
 
 /// Error returned by comms.
@@ -26,6 +32,20 @@ pub enum Error {
     /// Error sending message through Tokio channel.
     #[error("{0}")]
     SendError(#[from] SendError<Message>),
+
+    /// Error receiving message from Tokio channel.
+    #[error("{0}")]
+    RecvError(#[from] RecvError),
+
+    /// The send queue is full and the client hasn't caught up yet. Only returned
+    /// with [`SendQueuePolicy::Reject`]; the message wasn't sent.
+    #[error("websocket send queue is full")]
+    QueueFull,
+
+    /// Message couldn't be converted to or from JSON. The channel
+    /// is still usable.
+    #[error("json: {0}")]
+    Json(#[from] serde_json::Error),
 }
 
 static MESSAGES: Lazy<Messages> = Lazy::new(|| Messages::new());
@@ -35,38 +55,39 @@ fn get_comms() -> &'static Messages {
     &MESSAGES
 }
 
+#[derive(Clone)]
 struct Websocket {
     sender: Sender<Message>,
-    receiver: Receiver<Message>,
-    topic: String,
-}
-
-impl Clone for Websocket {
-    fn clone(&self) -> Self {
-        Websocket {
-            sender: self.sender.clone(),
-            receiver: self.receiver.resubscribe(),
-            topic: self.topic.clone(),
-        }
-    }
+    capacity: usize,
+    policy: SendQueuePolicy,
 }
 
 impl Websocket {
     fn new() -> Self {
-        let (sender, receiver) = channel(1024);
+        let config = &get_config().websocket;
+        Self::with_capacity(config.send_queue_capacity, config.send_queue_policy)
+    }
+
+    fn with_capacity(capacity: usize, policy: SendQueuePolicy) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, _) = channel(capacity);
         Self {
             sender,
-            receiver,
-            topic: DEFAULT_TOPIC.to_string(),
+            capacity,
+            policy,
         }
     }
 
     fn receiver(&self) -> Receiver<Message> {
-        self.receiver.resubscribe()
+        self.sender.subscribe()
     }
 
-    fn sender(&self) -> Sender<Message> {
-        self.sender.clone()
+    fn sender(&self) -> WebsocketSender {
+        WebsocketSender {
+            sender: self.sender.clone(),
+            capacity: self.capacity,
+            policy: self.policy,
+        }
     }
 }
 
@@ -102,8 +123,9 @@ impl Messages {
 
         WebsocketReceiver {
             receiver: Some(entry.receiver()),
-            sender: entry.sender(),
+            sender: entry.sender.clone(),
             session_id: session_id.clone(),
+            policy: entry.policy,
         }
     }
 
@@ -114,9 +136,7 @@ impl Messages {
         let entry = guard
             .entry(session_id.clone())
             .or_insert_with(Websocket::new);
-        WebsocketSender {
-            sender: entry.sender(),
-        }
+        entry.sender()
     }
 
     /// Get a websocket message sender that will send messages to all _other_ sessions.
@@ -125,7 +145,7 @@ impl Messages {
         let entries = guard
             .iter()
             .filter(|(id, _)| *session_id != **id)
-            .map(|(_, websocket)| websocket.clone())
+            .map(|(_, websocket)| websocket.sender())
             .collect::<Vec<_>>();
 
         Broadcast { everyone: entries }
@@ -136,7 +156,7 @@ impl Messages {
         let guard = self.websocket.lock();
         let entries = guard
             .iter()
-            .map(|(_, websocket)| websocket.clone())
+            .map(|(_, websocket)| websocket.sender())
             .collect::<Vec<_>>();
 
         Broadcast { everyone: entries }
@@ -144,16 +164,40 @@ impl Messages {
 }
 
 /// WebSocket message sender.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct WebsocketSender {
     sender: Sender<Message>,
+    capacity: usize,
+    policy: SendQueuePolicy,
 }
 
 impl WebsocketSender {
-    /// Send a message via WebSocket connection.
+    /// Send a message via WebSocket connection. Returns the number
+    /// of connections the message will be delivered to.
+    ///
+    /// If the send queue is full, the configured [`SendQueuePolicy`] is applied.
     pub fn send(&self, message: impl ToMessage) -> Result<usize, Error> {
+        if self.full() {
+            return Err(Error::QueueFull);
+        }
+
+        if self.sender.receiver_count() == 0 {
+            return Ok(0);
+        }
+
         Ok(self.sender.send(message.to_message())?)
     }
+
+    /// The queue is full and messages will be rejected until the client catches up.
+    /// Always `false` unless the policy is [`SendQueuePolicy::Reject`].
+    pub fn full(&self) -> bool {
+        self.policy == SendQueuePolicy::Reject && self.sender.len() >= self.capacity
+    }
+
+    /// Serialize the value to JSON and send it as a text message.
+    pub fn send_json(&self, value: &impl Serialize) -> Result<usize, Error> {
+        self.send(Message::from_json(value)?)
+    }
 }
 
 impl std::ops::Deref for WebsocketSender {
@@ -173,6 +217,7 @@ pub struct WebsocketReceiver {
     receiver: Option<Receiver<Message>>,
     sender: Sender<Message>,
     session_id: SessionId,
+    policy: SendQueuePolicy,
 }
 
 impl WebsocketReceiver {
@@ -180,6 +225,35 @@ impl WebsocketReceiver {
     pub fn session_id(&self) -> &SessionId {
         &self.session_id
     }
+
+    /// Receive the next message.
+    ///
+    /// If this receiver fell behind and messages were dropped, the configured [`SendQueuePolicy`]
+    /// is applied: messages are skipped or, with [`SendQueuePolicy::Disconnect`],
+    /// [`RecvError::Lagged`] is returned and the connection should be closed.
+    pub async fn next(&mut self) -> Result<Message, Error> {
+        loop {
+            match self.recv().await {
+                Err(RecvError::Lagged(skipped)) if self.policy != SendQueuePolicy::Disconnect => {
+                    debug!(
+                        "websocket session \"{:?}\" lagging, skipped {} messages",
+                        self.session_id, skipped
+                    );
+                    continue;
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+
+    /// Receive the next message and deserialize it from JSON.
+    ///
+    /// If the message isn't valid JSON for `T`, [`Error::Json`] is returned and the
+    /// message is skipped. The receiver can continue to be used.
+    pub async fn recv_json<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+        let message = self.next().await?;
+        Ok(message.to_json()?)
+    }
 }
 
 impl std::ops::Deref for WebsocketReceiver {
@@ -199,7 +273,7 @@ impl std::ops::DerefMut for WebsocketReceiver {
 impl Drop for WebsocketReceiver {
     fn drop(&mut self) {
         drop(self.receiver.take());
-        if self.sender.receiver_count() == 1 {
+        if self.sender.receiver_count() == 0 {
             get_comms().websocket_disconnect(&self.session_id);
         }
     }
@@ -208,18 +282,30 @@ impl Drop for WebsocketReceiver {
 /// Send messages to every single connected
 /// WebSocket session.
 pub struct Broadcast {
-    everyone: Vec<Websocket>,
+    everyone: Vec<WebsocketSender>,
 }
 
 impl Broadcast {
     /// Send a message to all connected sessions.
+    ///
+    /// With [`SendQueuePolicy::Reject`], if any of the sessions' queue is full,
+    /// the message isn't sent to anyone and [`Error::QueueFull`] is returned.
     pub fn send(&self, message: impl ToMessage) -> Result<(), Error> {
+        if self.everyone.iter().any(|socket| socket.full()) {
+            return Err(Error::QueueFull);
+        }
+
         for socket in &self.everyone {
-            socket.sender.send(message.clone().to_message())?;
+            socket.send(message.clone().to_message())?;
         }
 
         Ok(())
     }
+
+    /// Serialize the value to JSON and send it to all connected sessions.
+    pub fn send_json(&self, value: &impl Serialize) -> Result<(), Error> {
+        self.send(Message::from_json(value)?)
+    }
 }
 
 /// Convert an object into a session.
@@ -351,4 +437,98 @@ mod test {
         let websocket = Comms::websocket(&user);
         websocket.send(Message::Text("test2".into())).unwrap();
     }
+
+    #[tokio::test]
+    async fn test_json_messages() {
+        #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Chat {
+            from: String,
+            body: String,
+        }
+
+        let session = SessionId::Authenticated(227);
+        let mut receiver = Comms::receiver(&session);
+        let websocket = Comms::websocket(&session);
+
+        let chat = Chat {
+            from: "alice".into(),
+            body: "hello".into(),
+        };
+        websocket.send_json(&chat).unwrap();
+        assert_eq!(receiver.recv_json::<Chat>().await.unwrap(), chat);
+
+        // Malformed message doesn't close the channel.
+        websocket.send("not json").unwrap();
+        websocket.send_json(&chat).unwrap();
+        assert!(matches!(
+            receiver.recv_json::<Chat>().await,
+            Err(Error::Json(_))
+        ));
+        assert_eq!(receiver.recv_json::<Chat>().await.unwrap(), chat);
+    }
+
+    fn socket(policy: SendQueuePolicy) -> (Websocket, WebsocketReceiver) {
+        let websocket = Websocket::with_capacity(2, policy);
+        let receiver = WebsocketReceiver {
+            receiver: Some(websocket.receiver()),
+            sender: websocket.sender.clone(),
+            session_id: SessionId::default(),
+            policy,
+        };
+
+        (websocket, receiver)
+    }
+
+    #[tokio::test]
+    async fn test_queue_drop_messages() {
+        let (websocket, mut receiver) = socket(SendQueuePolicy::DropMessages);
+        let sender = websocket.sender();
+
+        for n in 0..5 {
+            sender.send(n.to_string()).unwrap();
+        }
+        assert!(!sender.full());
+
+        // Only the most recent messages are kept.
+        assert!(matches!(receiver.next().await.unwrap(), Message::Text(n) if n == "3"));
+        assert!(matches!(receiver.next().await.unwrap(), Message::Text(n) if n == "4"));
+    }
+
+    #[tokio::test]
+    async fn test_queue_disconnect() {
+        let (websocket, mut receiver) = socket(SendQueuePolicy::Disconnect);
+        let sender = websocket.sender();
+
+        for n in 0..5 {
+            sender.send(n.to_string()).unwrap();
+        }
+
+        assert!(matches!(
+            receiver.next().await,
+            Err(Error::RecvError(RecvError::Lagged(3)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_queue_reject() {
+        let (websocket, mut receiver) = socket(SendQueuePolicy::Reject);
+        let sender = websocket.sender();
+        let broadcast = Broadcast {
+            everyone: vec![websocket.sender()],
+        };
+
+        sender.send("0").unwrap();
+        broadcast.send("1").unwrap();
+        assert!(sender.full());
+        assert!(matches!(sender.send("2"), Err(Error::QueueFull)));
+        assert!(matches!(broadcast.send("2"), Err(Error::QueueFull)));
+
+        // Client catches up.
+        assert!(matches!(receiver.next().await.unwrap(), Message::Text(n) if n == "0"));
+        assert!(!sender.full());
+        sender.send("2").unwrap();
+
+        assert!(matches!(receiver.next().await.unwrap(), Message::Text(n) if n == "1"));
+        assert!(matches!(receiver.next().await.unwrap(), Message::Text(n) if n == "2"));
+    }
 }
@@ -39,6 +39,10 @@ pub enum Error {
     #[error("secret key is incorrect length")]
     SecretKey,
 
+    /// Secret key is not set, and generating a random one is not allowed.
+    #[error("secret key is required, set it with general.secret_key or RWF_SECRET_KEY")]
+    MissingSecretKey,
+
     /// Configuration is already loaded.
     #[error("config is already loaded")]
     ConfigLoaded,
@@ -46,6 +50,18 @@ pub enum Error {
     /// Configuration was not loaded.
     #[error("config not found")]
     NoConfig,
+
+    /// TLS certificate or private key is not valid.
+    #[error("tls: {0}")]
+    Tls(#[from] tokio_rustls::rustls::Error),
+
+    /// TLS certificate or private key file is not valid PEM.
+    #[error("tls: {0}")]
+    Pem(#[from] tokio_rustls::rustls::pki_types::pem::Error),
+
+    /// Setting has an invalid value.
+    #[error("invalid value for {0}: \"{1}\"")]
+    InvalidValue(&'static str, String),
 }
 
 /// Get application configuration.
@@ -65,7 +81,7 @@ pub struct Config {
     pub path: Option<PathBuf>,
 
     #[serde(skip)]
-    error: Option<Error>,
+    pub(crate) error: Option<Error>,
 
     /// General settings. Most settings are here.
     #[serde(default = "General::default")]
@@ -82,6 +98,10 @@ pub struct Config {
     /// Packaging configuration.
     #[serde(default = "PackageConfig::default")]
     pub package: PackageConfig,
+
+    /// TLS settings. If set, the server accepts HTTPS connections only.
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
 }
 
 impl Default for Config {
@@ -93,6 +113,7 @@ impl Default for Config {
             database: DatabaseConfig::default(),
             websocket: WebsocketConfig::default(),
             package: PackageConfig::default(),
+            tls: None,
         }
         .transform()
         .unwrap()
@@ -124,7 +145,13 @@ impl Config {
             }
         }
 
-        Self::default()
+        match Self::default().env(|name| var(name).ok()) {
+            Ok(config) => config,
+            Err(err) => Config {
+                error: Some(err),
+                ..Default::default()
+            },
+        }
     }
 
     /// Load configuration file from a specific path.
@@ -133,11 +160,106 @@ impl Config {
         let mut config: Self = toml::from_str(&file)?;
         config.path = Some(path.as_ref().to_owned());
 
-        let config = config.transform()?;
+        let config = config.transform()?.env(|name| var(name).ok())?;
 
         Ok(config)
     }
 
+    /// Override server settings from environment variables:
+    ///
+    /// * `RWF_BIND`: [`General::bind_address`]
+    /// * `RWF_WORKERS`: [`General::worker_threads`]
+    /// * `RWF_REQUIRE_EXPLICIT_KEYS`: [`General::require_explicit_keys`]
+    fn env(mut self, var: impl Fn(&str) -> Option<String>) -> Result<Self, Error> {
+        if let Some(require) = var("RWF_REQUIRE_EXPLICIT_KEYS") {
+            self.general.require_explicit_keys = ["1", "true"].contains(&require.as_str());
+        }
+
+        if self.general.require_explicit_keys && self.general.generated_secret_key {
+            return Err(Error::MissingSecretKey);
+        }
+
+        if let Some(bind) = var("RWF_BIND") {
+            self.general.bind_address = Some(bind);
+        }
+
+        if let Some(workers) = var("RWF_WORKERS") {
+            self.general.worker_threads = Some(workers.parse().unwrap_or(0));
+
+            if self.general.worker_threads == Some(0) {
+                return Err(Error::InvalidValue("RWF_WORKERS", workers));
+            }
+        }
+
+        if let Some(ref bind) = self.general.bind_address {
+            let valid = match bind.rsplit_once(":") {
+                Some((host, port)) => !host.is_empty() && port.parse::<u16>().is_ok(),
+                None => false,
+            };
+
+            if !valid {
+                return Err(Error::InvalidValue("bind_address", bind.clone()));
+            }
+        }
+
+        if self.general.worker_threads == Some(0) {
+            return Err(Error::InvalidValue("worker_threads", "0".into()));
+        }
+
+        Ok(self)
+    }
+
+    /// Check that the configuration was loaded without errors.
+    ///
+    /// A config that failed to load falls back to the defaults, which
+    /// is fine for tooling but not for serving traffic.
+    pub fn check(&self) -> Result<(), Error> {
+        match &self.error {
+            None => Ok(()),
+            Some(err) => Err(match err {
+                Error::Toml(err) => Error::Toml(err.clone()),
+                Error::Io(err) => Error::Io(std::io::Error::new(err.kind(), err.to_string())),
+                Error::Base64(err) => Error::Base64(err.clone()),
+                Error::SecretKey => Error::SecretKey,
+                Error::MissingSecretKey => Error::MissingSecretKey,
+                Error::ConfigLoaded => Error::ConfigLoaded,
+                Error::NoConfig => Error::NoConfig,
+                Error::Tls(err) => Error::Tls(err.clone()),
+                Error::Pem(err) => Error::Io(std::io::Error::other(err.to_string())),
+                Error::InvalidValue(name, value) => Error::InvalidValue(name, value.clone()),
+            }),
+        }
+    }
+
+    /// Create a Tokio runtime with the configured number of worker threads.
+    ///
+    /// `#[tokio::main]` builds its own runtime and ignores
+    /// [`General::worker_threads`], so applications that want to control
+    /// the number of threads should use this instead.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rwf::config::Config;
+    /// fn main() {
+    ///     let runtime = Config::get().runtime().unwrap();
+    ///
+    ///     runtime.block_on(async {
+    ///         // Launch the server.
+    ///     });
+    /// }
+    /// ```
+    pub fn runtime(&self) -> Result<tokio::runtime::Runtime, Error> {
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+
+        if let Some(workers) = self.general.worker_threads {
+            builder.worker_threads(workers);
+        }
+
+        Ok(builder.build()?)
+    }
+
     fn transform(mut self) -> Result<Self, Error> {
         let mut default_middleware = vec![];
 
@@ -152,6 +274,11 @@ impl Config {
 
         self.general.default_middleware = MiddlewareSet::without_default(default_middleware);
 
+        if self.general.secret_key.is_none() {
+            self.general.secret_key = Some(General::random_secret_key());
+            self.general.generated_secret_key = true;
+        }
+
         let secret_key = self.general.secret_key()?;
 
         self.general.aes_key = Key::<AesGcmSiv<Aes128>>::clone_from_slice(&secret_key[0..128 / 8]);
@@ -184,8 +311,24 @@ pub struct General {
     /// On what port to run the HTTP server. Default: 8000.
     #[serde(default = "General::default_port")]
     pub port: u16,
+    /// Address and port to run the HTTP server on, e.g. `127.0.0.1:8080`.
+    /// Takes priority over `host` and `port`. Use [`General::bind_address`] to get the address.
+    #[serde(default)]
+    pub bind_address: Option<String>,
+    /// Number of threads used by the Tokio runtime. Default: one per CPU.
+    /// Only applies to runtimes built with [`Config::runtime`].
+    #[serde(default)]
+    pub worker_threads: Option<usize>,
     #[serde(default = "General::default_secret_key")]
-    secret_key: String,
+    secret_key: Option<String>,
+    /// Secret key wasn't configured and was randomly generated.
+    #[serde(skip)]
+    generated_secret_key: bool,
+    /// Fail to load the configuration if the secret key isn't set, instead of generating
+    /// a random one. Random keys change on every restart, invalidating sessions and encrypted data,
+    /// so this should be enabled in production.
+    #[serde(default = "General::default_require_explicit_keys")]
+    pub require_explicit_keys: bool,
     /// AES-128 encryption key. Derived from the secret key. Used for encrypting cookies, sessions, and arbitrary user data.
     #[serde(skip)]
     pub aes_key: Key<AesGcmSiv<Aes128>>,
@@ -198,9 +341,17 @@ pub struct General {
     /// Enable caching templates at runtime.
     #[serde(default = "General::default_cache_templates")]
     pub cache_templates: bool,
+    /// Log a line for every completed request, including method, path, status,
+    /// bytes sent, duration, client IP and request ID.
+    #[serde(default = "General::default_access_log")]
+    pub access_log: bool,
     /// Record HTTP requests made to the server in the database.
     #[serde(default = "General::default_track_requests")]
     pub track_requests: bool,
+    /// Show error details, e.g. SQL errors, to the client in `500 - Internal Server Error` responses.
+    /// Errors are always logged. Default: `false`.
+    #[serde(default = "General::default_debug_errors")]
+    pub debug_errors: bool,
     /// Enable CSRF attack protection.
     #[serde(default = "General::default_csrf_protection")]
     pub csrf_protection: bool,
@@ -211,12 +362,52 @@ pub struct General {
     /// The terminal where Rwf is running is TTY.
     #[serde(default = "General::default_tty")]
     pub tty: bool,
+    /// Treat query parameters with empty values, e.g. `?status=`, as if they weren't set.
+    #[serde(default = "General::default_query_empty_as_none")]
+    pub query_empty_as_none: bool,
+    /// Hosts this application serves, e.g. `["example.com", "*.example.com"]`. Requests for other hosts
+    /// are rejected with `400 - Bad Request`. All hosts are allowed if the list is empty.
+    #[serde(default = "General::default_allowed_hosts")]
+    pub allowed_hosts: Vec<String>,
+    /// Keep encoded slashes (`%2F`) in request paths instead of rejecting the request.
+    #[serde(default = "General::default_allow_encoded_slashes")]
+    pub allow_encoded_slashes: bool,
+    /// Match request paths to routes ignoring case, e.g. `/Users` matches `/users`.
+    #[serde(default = "General::default_case_insensitive_routes")]
+    pub case_insensitive_routes: bool,
+    /// Match the trailing slash of routes exactly, e.g. `/users/` doesn't match `/users`.
+    #[serde(default = "General::default_strict_trailing_slash")]
+    pub strict_trailing_slash: bool,
+    /// How long to keep an idle connection open, waiting for the next request.
+    /// Configured in milliseconds.
+    /// Use [`General::keep_alive_timeout`] to get a valid [`Duration`].
+    #[serde(default = "General::default_keep_alive_timeout")]
+    pub keep_alive_timeout: usize,
+    /// Maximum length of the request line, e.g. `GET /path HTTP/1.1`.
+    #[serde(default = "General::default_max_line_length")]
+    pub max_line_length: usize,
     /// Maximum size allowed for an HTTP header.
     #[serde(default = "General::default_header_max_size")]
     pub header_max_size: usize,
     /// Maximum size allowed for an HTTP request.
     #[serde(default = "General::default_max_request_size")]
     pub max_request_size: usize,
+    /// Read request bodies sent with `GET`, `HEAD` and `DELETE` requests. When disabled,
+    /// these requests are rejected if they declare a body. Default: `true`, unless
+    /// `RWF_ALLOW_BODY_ON_ALL_METHODS` is `0` or `false`.
+    #[serde(default = "General::default_allow_body_on_all_methods")]
+    pub allow_body_on_all_methods: bool,
+    /// Character set used to decode request bodies when the client
+    /// doesn't specify one in the `Content-Type` header. Default: `utf-8`.
+    #[serde(default = "General::default_charset")]
+    pub default_charset: String,
+    /// How often to rotate the encryption key, in milliseconds. `0` disables rotation.
+    /// Use [`General::key_rotation_interval`] to get a valid [`Duration`].
+    #[serde(default)]
+    pub key_rotation_interval: usize,
+    /// Where sessions are stored: in an encrypted cookie, or on the server.
+    #[serde(default)]
+    pub session_store: SessionStorage,
     /// Global authentication handler. Used by default
     /// in all controllers.
     #[serde(skip)]
@@ -231,18 +422,35 @@ impl Default for General {
         Self {
             host: General::default_host(),
             port: General::default_port(),
+            bind_address: None,
+            worker_threads: None,
             secret_key: General::default_secret_key(),
+            generated_secret_key: false,
+            require_explicit_keys: General::default_require_explicit_keys(),
             aes_key: Key::<AesGcmSiv<Aes128>>::default(),
             secure_id_key: Key::<AesGcmSiv<Aes128>>::default(),
             log_queries: General::default_log_queries(),
             cache_templates: General::default_cache_templates(),
+            access_log: General::default_access_log(),
             track_requests: General::default_track_requests(),
+            debug_errors: General::default_debug_errors(),
             csrf_protection: General::default_csrf_protection(),
             cookie_max_age: General::default_cookie_max_age(),
             session_duration: General::default_session_duration(),
             tty: General::default_tty(),
+            query_empty_as_none: General::default_query_empty_as_none(),
+            allowed_hosts: General::default_allowed_hosts(),
+            allow_encoded_slashes: General::default_allow_encoded_slashes(),
+            case_insensitive_routes: General::default_case_insensitive_routes(),
+            strict_trailing_slash: General::default_strict_trailing_slash(),
+            keep_alive_timeout: General::default_keep_alive_timeout(),
+            max_line_length: General::default_max_line_length(),
             header_max_size: General::default_header_max_size(),
             max_request_size: General::default_max_request_size(),
+            allow_body_on_all_methods: General::default_allow_body_on_all_methods(),
+            default_charset: General::default_charset(),
+            key_rotation_interval: 0,
+            session_store: SessionStorage::default(),
             default_auth: AuthHandler::default(),
             default_middleware: MiddlewareSet::without_default(vec![]),
         }
@@ -257,6 +465,15 @@ fn true_from_env(name: &str) -> bool {
     }
 }
 
+/// Parse a boolean setting, using `default` if it's not set or not recognized.
+fn bool_from_env(value: Option<String>, default: bool) -> bool {
+    match value.as_deref() {
+        Some("1") | Some("true") => true,
+        Some("0") | Some("false") => false,
+        _ => default,
+    }
+}
+
 impl General {
     fn default_host() -> String {
         String::from("0.0.0.0")
@@ -266,12 +483,21 @@ impl General {
         8000
     }
 
+    /// Address and port the HTTP server will listen on. Default: `0.0.0.0:8000`.
+    pub fn bind_address(&self) -> String {
+        match self.bind_address {
+            Some(ref bind) => bind.clone(),
+            None => format!("{}:{}", self.host, self.port),
+        }
+    }
+
     /// Extract the secret key from configuration.
     /// It should be provided as a base64 string
     /// encoding 256 bits of entropy.
     pub fn secret_key(&self) -> Result<Vec<u8>, Error> {
         use base64::{engine::general_purpose, Engine as _};
-        let bytes = general_purpose::STANDARD.decode(&self.secret_key)?;
+        let secret_key = self.secret_key.as_ref().ok_or(Error::MissingSecretKey)?;
+        let bytes = general_purpose::STANDARD.decode(secret_key)?;
 
         if bytes.len() == 256 / 8 {
             Ok(bytes)
@@ -288,11 +514,11 @@ impl General {
         false
     }
 
-    fn default_secret_key() -> String {
-        if let Ok(key) = var("RWF_SECRET_KEY") {
-            return key;
-        }
+    fn default_secret_key() -> Option<String> {
+        var("RWF_SECRET_KEY").ok()
+    }
 
+    fn random_secret_key() -> String {
         use base64::{engine::general_purpose, Engine as _};
         use rand::Rng;
 
@@ -301,6 +527,10 @@ impl General {
         general_purpose::STANDARD.encode(&bytes)
     }
 
+    fn default_require_explicit_keys() -> bool {
+        true_from_env("RWF_REQUIRE_EXPLICIT_KEYS")
+    }
+
     fn default_cache_templates() -> bool {
         if true_from_env("RWF_CACHE_TEMPLATES") {
             return true;
@@ -312,6 +542,10 @@ impl General {
         return true;
     }
 
+    fn default_access_log() -> bool {
+        true_from_env("RWF_ACCESS_LOG")
+    }
+
     fn default_track_requests() -> bool {
         if true_from_env("RWF_TRACK_REQUESTS") {
             return true;
@@ -319,6 +553,10 @@ impl General {
         false
     }
 
+    fn default_debug_errors() -> bool {
+        true_from_env("RWF_DEBUG_ERRORS")
+    }
+
     fn default_csrf_protection() -> bool {
         if true_from_env("RWF_CSRF_PROTECTION") {
             return true;
@@ -350,6 +588,56 @@ impl General {
         std::io::stderr().is_terminal()
     }
 
+    fn default_query_empty_as_none() -> bool {
+        true_from_env("RWF_QUERY_EMPTY_AS_NONE")
+    }
+
+    fn default_allowed_hosts() -> Vec<String> {
+        var("RWF_ALLOWED_HOSTS")
+            .map(|hosts| {
+                hosts
+                    .split(',')
+                    .map(|host| host.trim().to_string())
+                    .filter(|host| !host.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn default_allow_encoded_slashes() -> bool {
+        true_from_env("RWF_ALLOW_ENCODED_SLASHES")
+    }
+
+    fn default_case_insensitive_routes() -> bool {
+        true_from_env("RWF_CASE_INSENSITIVE_ROUTES")
+    }
+
+    fn default_strict_trailing_slash() -> bool {
+        true_from_env("RWF_STRICT_TRAILING_SLASH")
+    }
+
+    fn default_keep_alive_timeout() -> usize {
+        Duration::seconds(60).whole_milliseconds() as usize
+    }
+
+    /// How long to keep an idle connection open, waiting for the next request.
+    pub fn keep_alive_timeout(&self) -> Duration {
+        Duration::milliseconds(self.keep_alive_timeout as i64)
+    }
+
+    /// How often to rotate the encryption key, if at all.
+    pub fn key_rotation_interval(&self) -> Option<Duration> {
+        if self.key_rotation_interval == 0 {
+            None
+        } else {
+            Some(Duration::milliseconds(self.key_rotation_interval as i64))
+        }
+    }
+
+    fn default_max_line_length() -> usize {
+        8 * 1024 // 8K
+    }
+
     fn default_header_max_size() -> usize {
         16 * 1024 // 16K
     }
@@ -357,6 +645,14 @@ impl General {
     fn default_max_request_size() -> usize {
         5 * 1024 * 1024 // 5M
     }
+
+    fn default_allow_body_on_all_methods() -> bool {
+        bool_from_env(var("RWF_ALLOW_BODY_ON_ALL_METHODS").ok(), true)
+    }
+
+    fn default_charset() -> String {
+        var("RWF_DEFAULT_CHARSET").unwrap_or(String::from("utf-8"))
+    }
 }
 
 /// WebSocket connections configuration.
@@ -378,6 +674,46 @@ pub struct WebsocketConfig {
     /// closing the connection.
     #[serde(default = "WebsocketConfig::default_disconnect_count")]
     pub ping_disconnect_count: usize,
+    /// Maximum size of a message, including all of its fragments, in bytes.
+    /// Connections sending larger messages are closed.
+    #[serde(default = "WebsocketConfig::default_max_message_size")]
+    pub max_message_size: usize,
+    /// Maximum number of messages queued for each session
+    /// before the [`WebsocketConfig::send_queue_policy`] kicks in.
+    #[serde(default = "WebsocketConfig::default_send_queue_capacity")]
+    pub send_queue_capacity: usize,
+    /// What to do when a client can't receive messages fast enough.
+    #[serde(default)]
+    pub send_queue_policy: SendQueuePolicy,
+}
+
+/// Where sessions are stored.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStorage {
+    /// The whole session is encrypted and stored in a cookie.
+    #[default]
+    Cookie,
+    /// The session is stored on the server, using the store set with
+    /// [`crate::http::session_store::set_session_store`]. The client only
+    /// receives the session identifier.
+    Server,
+}
+
+/// What to do when the WebSocket send queue is full.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SendQueuePolicy {
+    /// Drop the oldest messages the client hasn't received yet. Messages are
+    /// delivered on a best effort basis.
+    #[default]
+    DropMessages,
+    /// Close the connection of the client that fell behind.
+    Disconnect,
+    /// Refuse new messages until the client catches up. Senders receive
+    /// [`crate::comms::Error::QueueFull`] and must retry or drop the message
+    /// themselves; nothing waits for the queue to drain.
+    Reject,
 }
 
 impl Default for WebsocketConfig {
@@ -386,6 +722,9 @@ impl Default for WebsocketConfig {
             ping_timeout: Self::default_ping_timeout(),
             ping_interval: Self::default_ping_interval(),
             ping_disconnect_count: Self::default_disconnect_count(),
+            max_message_size: Self::default_max_message_size(),
+            send_queue_capacity: Self::default_send_queue_capacity(),
+            send_queue_policy: SendQueuePolicy::default(),
         }
     }
 }
@@ -412,6 +751,14 @@ impl WebsocketConfig {
     fn default_disconnect_count() -> usize {
         3
     }
+
+    fn default_max_message_size() -> usize {
+        16 * 1024 * 1024 // 16M
+    }
+
+    fn default_send_queue_capacity() -> usize {
+        1024
+    }
 }
 
 /// Database connection configuration.
@@ -436,6 +783,23 @@ pub struct DatabaseConfig {
     /// in the pool.
     #[serde(default = "DatabaseConfig::default_pool_size")]
     pub pool_size: usize,
+    /// How many times to retry a query that failed because of a transient error,
+    /// e.g. lost connection or serialization failure.
+    #[serde(default = "DatabaseConfig::default_max_retries")]
+    pub max_retries: usize,
+    /// How long to wait before the first retry. Doubled after every retry.
+    /// Configured in milliseconds.
+    /// Use [`DatabaseConfig::retry_backoff`] to get a valid [`Duration`] struct.
+    #[serde(default = "DatabaseConfig::default_retry_backoff")]
+    pub retry_backoff: usize,
+    /// Retry writes (`INSERT`, `UPDATE`, raw queries) as well as reads. Writes
+    /// may not be idempotent, so this is disabled by default.
+    #[serde(default = "DatabaseConfig::default_retry_writes")]
+    pub retry_writes: bool,
+    /// Statements executed on every new connection before it's used,
+    /// e.g. `SET TIME ZONE 'UTC'`.
+    #[serde(default)]
+    pub on_connect: Vec<String>,
 }
 
 impl Default for DatabaseConfig {
@@ -447,6 +811,10 @@ impl Default for DatabaseConfig {
             idle_timeout: DatabaseConfig::default_idle_timeout(),
             checkout_timeout: DatabaseConfig::default_checkout_timeout(),
             pool_size: DatabaseConfig::default_pool_size(),
+            max_retries: DatabaseConfig::default_max_retries(),
+            retry_backoff: DatabaseConfig::default_retry_backoff(),
+            retry_writes: DatabaseConfig::default_retry_writes(),
+            on_connect: vec![],
         }
     }
 }
@@ -490,6 +858,26 @@ impl DatabaseConfig {
         10
     }
 
+    fn default_max_retries() -> usize {
+        match var("RWF_DATABASE_MAX_RETRIES") {
+            Ok(retries) => retries.parse().unwrap_or(3),
+            Err(_) => 3,
+        }
+    }
+
+    fn default_retry_backoff() -> usize {
+        100
+    }
+
+    /// How long to wait before the first retry.
+    pub fn retry_backoff(&self) -> Duration {
+        Duration::milliseconds(self.retry_backoff as i64)
+    }
+
+    fn default_retry_writes() -> bool {
+        true_from_env("RWF_DATABASE_RETRY_WRITES")
+    }
+
     /// Convert the connection config to a valid
     /// database URL as described by the
     /// Twelve Factor Application.
@@ -538,6 +926,7 @@ cache_templates = true
 
 [database]
 name = "test"
+on_connect = ["SET TIME ZONE 'UTC'"]
     "#;
             let path = path.join(config_path);
             let mut file = File::create(path).unwrap();
@@ -545,6 +934,149 @@ name = "test"
 
             let config = Config::load_default();
             assert_eq!(config.path, Some(PathBuf::from(config_path)));
+            assert_eq!(config.database.on_connect, vec!["SET TIME ZONE 'UTC'"]);
+        }
+    }
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_env_bind_workers() {
+        let config = Config::default().env(env(&[])).unwrap();
+        assert_eq!(config.general.bind_address(), "0.0.0.0:8000");
+        assert_eq!(config.general.worker_threads, None);
+
+        let config = Config::default()
+            .env(env(&[("RWF_BIND", "127.0.0.1:9000"), ("RWF_WORKERS", "4")]))
+            .unwrap();
+        assert_eq!(config.general.bind_address(), "127.0.0.1:9000");
+        assert_eq!(config.general.worker_threads, Some(4));
+
+        let config = Config::default()
+            .env(env(&[("RWF_BIND", "[::1]:8080")]))
+            .unwrap();
+        assert_eq!(config.general.bind_address(), "[::1]:8080");
+
+        let runtime = Config::default()
+            .env(env(&[("RWF_WORKERS", "2")]))
+            .unwrap()
+            .runtime()
+            .unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 2);
+    }
+
+    #[test]
+    fn test_allow_body_on_all_methods() {
+        assert!(bool_from_env(None, true));
+        assert!(bool_from_env(Some("1".into()), true));
+        assert!(bool_from_env(Some("true".into()), true));
+        assert!(!bool_from_env(Some("0".into()), true));
+        assert!(!bool_from_env(Some("false".into()), true));
+        assert!(bool_from_env(Some("yes please".into()), true));
+
+        if var("RWF_ALLOW_BODY_ON_ALL_METHODS").is_err() {
+            assert!(General::default().allow_body_on_all_methods);
+        }
+
+        let config: Config = toml::from_str(
+            r#"
+[general]
+allow_body_on_all_methods = false
+"#,
+        )
+        .unwrap();
+        assert!(!config.general.allow_body_on_all_methods);
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(Config::default().check().is_ok());
+
+        // What `load_default` does with a bad environment; `Server::launch` refuses it.
+        for vars in [[("RWF_BIND", "not an address")], [("RWF_WORKERS", "none")]] {
+            let err = Config::default()
+                .env(env(&vars))
+                .err()
+                .expect("invalid value");
+            let config = Config {
+                error: Some(err),
+                ..Default::default()
+            };
+            assert!(matches!(config.check(), Err(Error::InvalidValue(_, _))));
+        }
+
+        let config = Config {
+            error: Some(Error::MissingSecretKey),
+            ..Default::default()
+        };
+        assert!(matches!(config.check(), Err(Error::MissingSecretKey)));
+    }
+
+    #[test]
+    fn test_require_explicit_keys() {
+        // Development: a random key is generated.
+        let config = Config::default()
+            .env(env(&[("RWF_REQUIRE_EXPLICIT_KEYS", "0")]))
+            .unwrap();
+        assert!(config.general.generated_secret_key);
+        assert_eq!(config.general.secret_key().unwrap().len(), 32);
+
+        // Production: no key, no config.
+        let err = Config::default()
+            .env(env(&[("RWF_REQUIRE_EXPLICIT_KEYS", "1")]))
+            .err()
+            .expect("missing key");
+        assert!(matches!(err, Error::MissingSecretKey));
+
+        let config: Config = toml::from_str(
+            r#"
+[general]
+require_explicit_keys = true
+"#,
+        )
+        .unwrap();
+        let err = config.transform().unwrap().env(env(&[])).err();
+        assert!(matches!(err, Some(Error::MissingSecretKey)));
+
+        // Production with a key.
+        let config: Config = toml::from_str(
+            r#"
+[general]
+require_explicit_keys = true
+secret_key = "VXPRf4zQd/X9xMnr/IqUsLBjqUfOEyZcKRj2CjW6ApQ="
+"#,
+        )
+        .unwrap();
+        let config = config.transform().unwrap().env(env(&[])).unwrap();
+        assert!(!config.general.generated_secret_key);
+        assert_eq!(
+            config.general.aes_key.as_slice(),
+            &config.general.secret_key().unwrap()[..16]
+        );
+    }
+
+    #[test]
+    fn test_env_invalid() {
+        for (name, value) in [
+            ("RWF_WORKERS", "lots"),
+            ("RWF_WORKERS", "0"),
+            ("RWF_WORKERS", "-1"),
+            ("RWF_BIND", "localhost"),
+            ("RWF_BIND", "0.0.0.0:http"),
+            ("RWF_BIND", ":8000"),
+        ] {
+            let err = Config::default()
+                .env(env(&[(name, value)]))
+                .err()
+                .expect("invalid value");
+            assert!(matches!(err, Error::InvalidValue(_, ref v) if v == value));
+            assert!(err.to_string().contains(value));
         }
     }
 }
@@ -586,3 +1118,37 @@ impl PackageConfig {
         vec![]
     }
 }
+
+/// TLS configuration, used to serve HTTPS directly, without a proxy.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TlsConfig {
+    /// Path to the certificate chain, PEM-encoded.
+    pub certificate: PathBuf,
+    /// Path to the private key, PEM-encoded.
+    pub private_key: PathBuf,
+}
+
+impl TlsConfig {
+    /// Load the certificate and private key and create a TLS acceptor.
+    /// The acceptor advertises `http/1.1` using ALPN.
+    pub fn acceptor(&self) -> Result<tokio_rustls::TlsAcceptor, Error> {
+        use tokio_rustls::rustls::{
+            crypto::ring::default_provider,
+            pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+            ServerConfig,
+        };
+
+        let certificates =
+            CertificateDer::pem_file_iter(&self.certificate)?.collect::<Result<Vec<_>, _>>()?;
+        let private_key = PrivateKeyDer::from_pem_file(&self.private_key)?;
+
+        let mut config =
+            ServerConfig::builder_with_provider(std::sync::Arc::new(default_provider()))
+                .with_safe_default_protocol_versions()?
+                .with_no_client_auth()
+                .with_single_cert(certificates, private_key)?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(tokio_rustls::TlsAcceptor::from(std::sync::Arc::new(config)))
+    }
+}
@@ -0,0 +1,162 @@
+//! Record request bodies for audit logs.
+//!
+//! Bodies of requests which can have one (e.g. `POST`, `PUT`, `PATCH`) are passed to a hook, with sensitive
+//! fields redacted and the size limited. The request itself is forwarded unchanged, so the controller
+//! can still parse the body.
+use std::sync::Arc;
+
+use crate::controller::middleware::prelude::*;
+
+type AuditHook = Arc<dyn Fn(&Request, &str) + Send + Sync>;
+
+/// Audit log middleware.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::controller::middleware::AuditLog;
+/// let audit = AuditLog::new(|request, body| {
+///     println!("{} {} {}", request.method(), request.path().path(), body);
+/// })
+/// .max_size(4096)
+/// .redact(&["password", "token"]);
+/// ```
+#[derive(Clone)]
+pub struct AuditLog {
+    hook: AuditHook,
+    max_size: usize,
+    redact: Vec<String>,
+}
+
+impl AuditLog {
+    /// Create the middleware calling `hook` with each request and its body.
+    ///
+    /// By default, bodies are limited to 1 KB and `password` fields are redacted.
+    pub fn new(hook: impl Fn(&Request, &str) + Send + Sync + 'static) -> Self {
+        Self {
+            hook: Arc::new(hook),
+            max_size: 1024,
+            redact: vec!["password".into()],
+        }
+    }
+
+    /// Maximum number of bytes of the body passed to the hook.
+    pub fn max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Fields in JSON and form bodies which should be redacted.
+    pub fn redact(mut self, fields: &[&str]) -> Self {
+        self.redact = fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+}
+
+#[async_trait]
+impl Middleware for AuditLog {
+    async fn handle_request(&self, request: Request) -> Result<Outcome, Error> {
+        if request.method().has_body() {
+            let redact = self
+                .redact
+                .iter()
+                .map(|field| field.as_str())
+                .collect::<Vec<_>>();
+            let body = request.body_for_audit(self.max_size, &redact);
+            (self.hook)(&request, &body);
+        }
+
+        Ok(Outcome::Forward(request))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::http::request::test::dummy_ip;
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let hook_log = log.clone();
+        let audit = AuditLog::new(move |request, body| {
+            hook_log
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", request.method(), body));
+        })
+        .redact(&["password", "token"]);
+
+        let body = r#"{"email":"alice@example.com","password":"hunter2","nested":{"token":"abc"}}"#;
+        let request = format!(
+            "POST /login HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let request = Request::read(dummy_ip(), request.as_bytes()).await.unwrap();
+
+        let request = match audit.handle_request(request).await.unwrap() {
+            Outcome::Forward(request) => request,
+            Outcome::Stop(_, _) => panic!("audit log should forward the request"),
+        };
+
+        assert_eq!(
+            log.lock().unwrap().as_slice(),
+            [
+                r#"POST {"email":"alice@example.com","nested":{"token":"[REDACTED]"},"password":"[REDACTED]"}"#
+            ]
+        );
+
+        // The controller still gets the original body.
+        let json = request.json_raw().unwrap();
+        assert_eq!(json["password"], "hunter2");
+        assert_eq!(json["nested"]["token"], "abc");
+
+        // Form bodies are redacted and truncated.
+        let body = "password=hunter2&email=alice%40example.com";
+        let request = format!(
+            "PUT /login HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let request = Request::read(dummy_ip(), request.as_bytes()).await.unwrap();
+        assert_eq!(
+            request.body_for_audit(24, &["password"]),
+            "password=[REDACTED]&emai"
+        );
+        assert_eq!(
+            request.form_data().unwrap().get::<String>("password"),
+            Some("hunter2".into())
+        );
+
+        // Bodies which can't be redacted aren't recorded verbatim.
+        for (content_type, body) in [
+            ("application/json", r#"{"password": "hunter2""#),
+            ("text/plain", "password=hunter2"),
+            (
+                "multipart/form-data; boundary=x",
+                "--x\r\n\r\nhunter2\r\n--x--",
+            ),
+        ] {
+            let request = format!(
+                "POST /login HTTP/1.1\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            );
+            let request = Request::read(dummy_ip(), request.as_bytes()).await.unwrap();
+            assert_eq!(
+                request.body_for_audit(1024, &["password"]),
+                format!("<redacted: {} bytes>", body.len())
+            );
+            assert_eq!(request.body_for_audit(1024, &[]), body);
+        }
+
+        // Requests without a body aren't recorded.
+        let request = Request::default();
+        audit.handle_request(request).await.unwrap();
+        assert_eq!(log.lock().unwrap().len(), 1);
+    }
+}
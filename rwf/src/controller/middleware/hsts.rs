@@ -0,0 +1,141 @@
+//! Tell browsers to only connect to the application over HTTPS.
+//!
+//! Adds the `Strict-Transport-Security` header to responses served over HTTPS. Browsers ignore
+//! the header on plain HTTP responses, so it's not sent on those.
+use crate::controller::middleware::prelude::*;
+
+/// HTTP Strict Transport Security (HSTS) middleware.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::controller::middleware::Hsts;
+/// let hsts = Hsts::new()
+///     .max_age(63_072_000)
+///     .include_subdomains()
+///     .preload();
+///
+/// assert_eq!(hsts.header(), "max-age=63072000; includeSubDomains; preload");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Hsts {
+    max_age: u64,
+    include_subdomains: bool,
+    preload: bool,
+}
+
+impl Default for Hsts {
+    fn default() -> Self {
+        Self {
+            max_age: 31_536_000, // One year.
+            include_subdomains: false,
+            preload: false,
+        }
+    }
+}
+
+impl Hsts {
+    /// Create the middleware with a `max-age` of one year.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How long, in seconds, browsers should only use HTTPS.
+    pub fn max_age(mut self, seconds: u64) -> Self {
+        self.max_age = seconds;
+        self
+    }
+
+    /// Apply the policy to all subdomains as well.
+    pub fn include_subdomains(mut self) -> Self {
+        self.include_subdomains = true;
+        self
+    }
+
+    /// Allow the domain to be included in the browsers' HSTS preload list.
+    pub fn preload(mut self) -> Self {
+        self.preload = true;
+        self
+    }
+
+    /// Value of the `Strict-Transport-Security` header.
+    pub fn header(&self) -> String {
+        let mut header = format!("max-age={}", self.max_age);
+
+        if self.include_subdomains {
+            header.push_str("; includeSubDomains");
+        }
+
+        if self.preload {
+            header.push_str("; preload");
+        }
+
+        header
+    }
+}
+
+#[async_trait]
+impl Middleware for Hsts {
+    async fn handle_request(&self, request: Request) -> Result<Outcome, Error> {
+        Ok(Outcome::Forward(request))
+    }
+
+    async fn handle_response(
+        &self,
+        request: &Request,
+        response: Response,
+    ) -> Result<Response, Error> {
+        if request.is_secure() {
+            Ok(response.header("strict-transport-security", self.header()))
+        } else {
+            Ok(response)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hsts() {
+        let hsts = Hsts::new().include_subdomains();
+
+        // Secure request.
+        let request = Request::default().with_tls(true);
+        let response = hsts
+            .handle_response(&request, Response::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers().get("strict-transport-security"),
+            Some(&"max-age=31536000; includeSubDomains".to_string())
+        );
+
+        // Insecure request.
+        let request = Request::default();
+        let response = hsts
+            .handle_response(&request, Response::new())
+            .await
+            .unwrap();
+        assert!(response
+            .headers()
+            .get("strict-transport-security")
+            .is_none());
+
+        // TLS terminated by a proxy.
+        let mut request = Request::default();
+        request
+            .head_mut()
+            .headers_mut()
+            .insert("x-forwarded-proto", "https");
+        let response = hsts
+            .handle_response(&request, Response::new())
+            .await
+            .unwrap();
+        assert!(response
+            .headers()
+            .get("strict-transport-security")
+            .is_some());
+    }
+}
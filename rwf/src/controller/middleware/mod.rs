@@ -26,9 +26,18 @@ pub mod prelude;
 pub mod secure_id;
 pub use secure_id::SecureId;
 
+pub mod audit;
+pub use audit::AuditLog;
+
 pub mod csrf;
 pub mod request_tracker;
 
+pub mod hsts;
+pub use hsts::Hsts;
+
+pub mod security_headers;
+pub use security_headers::SecurityHeaders;
+
 /// The result of middleware processing a request.
 pub enum Outcome {
     /// Forward the request to the next middleware in the chain, or if none are left,
@@ -0,0 +1,158 @@
+//! Add security headers to all responses.
+//!
+//! By default, responses get `X-Content-Type-Options: nosniff`, `X-Frame-Options: DENY` and
+//! `Referrer-Policy: strict-origin-when-cross-origin`. A `Content-Security-Policy` isn't set unless configured,
+//! since it depends on which scripts and styles the application loads.
+//!
+//! Headers already set by the controller are left unchanged.
+use crate::controller::middleware::prelude::*;
+
+/// Security headers middleware.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::controller::middleware::SecurityHeaders;
+/// let headers = SecurityHeaders::new()
+///     .content_security_policy("default-src 'self'")
+///     .referrer_policy("no-referrer");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    frame_options: Option<String>,
+    content_security_policy: Option<String>,
+    referrer_policy: Option<String>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self {
+            frame_options: Some("DENY".into()),
+            content_security_policy: None,
+            referrer_policy: Some("strict-origin-when-cross-origin".into()),
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// Create the middleware with the default headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the `X-Frame-Options` header, e.g. `SAMEORIGIN`. Default: `DENY`.
+    pub fn frame_options(mut self, value: impl ToString) -> Self {
+        self.frame_options = Some(value.to_string());
+        self
+    }
+
+    /// Don't set the `X-Frame-Options` header, allowing pages to be embedded in frames.
+    pub fn allow_frames(mut self) -> Self {
+        self.frame_options = None;
+        self
+    }
+
+    /// Set the `Content-Security-Policy` header, e.g. `default-src 'self'`.
+    pub fn content_security_policy(mut self, value: impl ToString) -> Self {
+        self.content_security_policy = Some(value.to_string());
+        self
+    }
+
+    /// Set the `Referrer-Policy` header, e.g. `no-referrer`. Default: `strict-origin-when-cross-origin`.
+    pub fn referrer_policy(mut self, value: impl ToString) -> Self {
+        self.referrer_policy = Some(value.to_string());
+        self
+    }
+
+    fn headers(&self) -> Vec<(&'static str, &str)> {
+        let mut headers = vec![("x-content-type-options", "nosniff")];
+
+        if let Some(ref value) = self.frame_options {
+            headers.push(("x-frame-options", value));
+        }
+
+        if let Some(ref value) = self.content_security_policy {
+            headers.push(("content-security-policy", value));
+        }
+
+        if let Some(ref value) = self.referrer_policy {
+            headers.push(("referrer-policy", value));
+        }
+
+        headers
+    }
+}
+
+#[async_trait]
+impl Middleware for SecurityHeaders {
+    async fn handle_request(&self, request: Request) -> Result<Outcome, Error> {
+        Ok(Outcome::Forward(request))
+    }
+
+    async fn handle_response(
+        &self,
+        _request: &Request,
+        mut response: Response,
+    ) -> Result<Response, Error> {
+        for (name, value) in self.headers() {
+            if response.headers().get(name).is_none() {
+                response.headers_mut().insert(name, value);
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_security_headers() {
+        let request = Request::default();
+        let response = SecurityHeaders::new()
+            .handle_response(&request, Response::new())
+            .await
+            .unwrap();
+
+        let headers = response.headers();
+        assert_eq!(
+            headers.get("x-content-type-options"),
+            Some(&"nosniff".to_string())
+        );
+        assert_eq!(headers.get("x-frame-options"), Some(&"DENY".to_string()));
+        assert_eq!(
+            headers.get("referrer-policy"),
+            Some(&"strict-origin-when-cross-origin".to_string())
+        );
+        assert!(headers.get("content-security-policy").is_none());
+
+        // Configured, and headers set by the controller are kept.
+        let response = SecurityHeaders::new()
+            .content_security_policy("default-src 'self'")
+            .referrer_policy("no-referrer")
+            .allow_frames()
+            .handle_response(
+                &request,
+                Response::new().header("X-Content-Type-Options", "custom"),
+            )
+            .await
+            .unwrap();
+
+        let headers = response.headers();
+        assert_eq!(
+            headers.get("content-security-policy"),
+            Some(&"default-src 'self'".to_string())
+        );
+        assert_eq!(
+            headers.get("referrer-policy"),
+            Some(&"no-referrer".to_string())
+        );
+        assert!(headers.get("x-frame-options").is_none());
+        assert_eq!(
+            headers.get("x-content-type-options"),
+            Some(&"custom".to_string())
+        );
+    }
+}
@@ -53,7 +53,7 @@ pub use turbo_stream::TurboStream;
 
 use super::http::{
     websocket::{self, DataFrame},
-    Handler, Method, Request, Response, Stream, ToParameter,
+    Error as HttpError, Handler, Method, Request, Response, Stream, ToParameter,
 };
 use super::model::{get_connection, Insert, Model, Query, ToValue, Update, Value};
 use crate::colors::MaybeColorize;
@@ -226,19 +226,14 @@ pub trait Controller: Sync + Send {
             (Outcome::Forward(request), executed) => match self.handle(&request).await {
                 Ok(response) => {
                     self.middleware()
-                        .handle_response(&request, response.from_request(&request)?, executed)
+                        .handle_response(&request, response.from_request(&request).await?, executed)
                         .await?
                 }
                 Err(err) => {
                     error!("{:?}", err);
 
                     let response = match err {
-                        Error::HttpError(err) => match err.code() {
-                            400 => Response::bad_request(),
-                            403 => Response::forbidden(),
-                            413 => Response::content_too_large(),
-                            _ => Response::internal_error(err),
-                        },
+                        Error::HttpError(err) => Response::from(*err),
 
                         Error::ViewError(err) => {
                             Response::error_pretty("Template error", err.to_string().as_str())
@@ -255,7 +250,7 @@ pub trait Controller: Sync + Send {
             },
             (Outcome::Stop(request, response), executed) => {
                 self.middleware()
-                    .handle_response(&request, response.from_request(&request)?, executed)
+                    .handle_response(&request, response.from_request(&request).await?, executed)
                     .await?
             }
         };
@@ -408,7 +403,7 @@ pub trait RestController: Controller {
                 Method::Post => self.create(request).await,
                 _ => Ok(Response::method_not_allowed()),
             },
-            _ => Ok(Response::bad_request()),
+            Err(err) => Err(err.into()),
         }
     }
 
@@ -546,7 +541,7 @@ pub trait ModelController: Controller {
                 _ => Ok(Response::method_not_allowed()),
             },
 
-            Err(_) => Ok(Response::bad_request()),
+            Err(err) => Err(err.into()),
         }
     }
 
@@ -685,23 +680,7 @@ pub trait ModelController: Controller {
 pub trait WebsocketController: Controller {
     /// Handle WebSocket connection.
     async fn handle(&self, request: &Request) -> Result<Response, Error> {
-        use base64::{engine::general_purpose, Engine as _};
-        use sha1::{Digest, Sha1};
-
-        if !request.upgrade_websocket() {
-            return Ok(Response::bad_request());
-        }
-
-        let headers = match websocket::Headers::from_http_request(request) {
-            Ok(headers) => headers,
-            Err(_) => return Ok(Response::bad_request()),
-        };
-
-        let accept = headers.key.clone() + "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
-        let digest = Sha1::digest(accept.as_bytes());
-        let base64 = general_purpose::STANDARD.encode(digest);
-
-        Ok(Response::switching_protocols("websocket").header("sec-websocket-accept", base64))
+        Ok(websocket::handshake(request))
     }
 
     /// Handle an incoming client message.
@@ -721,12 +700,17 @@ pub trait WebsocketController: Controller {
     /// Handle the WebSocket TCP stream. Provides the WebSocket
     /// protocol implementation. You may not want to override this unless you
     /// want to change how WebSockets work in Rwf.
+    ///
+    /// Requests that didn't ask for a WebSocket, e.g. pages served with [`Request::if_websocket_else`],
+    /// are handled like regular HTTP requests.
     async fn handle_stream(
         &self,
         request: &Request,
         mut stream: Stream<'_>,
     ) -> Result<bool, Error> {
-        use tokio::sync::broadcast::error::RecvError;
+        if !request.upgrade_websocket() {
+            return Ok(request.keep_alive());
+        }
 
         let session_id = request.session().session_id.clone();
 
@@ -742,6 +726,7 @@ pub trait WebsocketController: Controller {
         let mut receiver = Comms::receiver(&session_id);
         let mut check = interval(config.websocket.ping_interval().unsigned_abs());
         let mut lost_pings = 0_i64;
+        let mut fragments = websocket::Fragments::new(config.websocket.max_message_size);
 
         self.client_connected(&session_id).await?;
 
@@ -765,7 +750,7 @@ pub trait WebsocketController: Controller {
                     }
                 }
 
-                message = receiver.recv() => {
+                message = receiver.next() => {
                     match message {
                         Ok(message) => {
                             debug!("{} sending {:?} to session \"{}\"",
@@ -774,18 +759,21 @@ pub trait WebsocketController: Controller {
                             message.send(&mut stream).await?;
                         }
 
-                        Err(RecvError::Closed) => break,
-
-                        // Lagging behind. This is best effort
-                        // message delivery, so we are ok dropping
-                        // messages if the client can't receive them
-                        // fast enough.
-                        Err(RecvError::Lagged(_)) => continue,
+                        // Channel closed or the client is lagging behind
+                        // and the send queue policy is to disconnect it.
+                        Err(_) => break,
                     }
                 }
 
                 frame = DataFrame::read(&mut stream) => {
-                    let frame = frame?;
+                    let frame = match frame {
+                        Ok(frame) => frame,
+                        Err(HttpError::MessageTooBig) => {
+                            DataFrame::new_close(websocket::CLOSE_MESSAGE_TOO_BIG).flush(&mut stream).await?;
+                            break;
+                        }
+                        Err(err) => return Err(err.into()),
+                    };
 
                     if frame.is_pong() {
                         debug!("{} session \"{}\" is alive", "websocket".purple(), session_id);
@@ -800,9 +788,20 @@ pub trait WebsocketController: Controller {
                     } else if frame.is_ping() {
                         DataFrame::new_pong(frame).flush(&mut stream).await?;
                         continue;
+                    } else if frame.is_close() {
+                        DataFrame::new_close(1000).flush(&mut stream).await?;
+                        break;
                     }
 
-                    self.client_message(&session_id, frame.message()).await?;
+                    match fragments.push(frame) {
+                        Ok(Some(message)) => self.client_message(&session_id, message).await?,
+                        Ok(None) => continue,
+                        Err(HttpError::MessageTooBig) => {
+                            DataFrame::new_close(websocket::CLOSE_MESSAGE_TOO_BIG).flush(&mut stream).await?;
+                            break;
+                        }
+                        Err(err) => return Err(err.into()),
+                    }
                 }
 
             }
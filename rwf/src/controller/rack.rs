@@ -86,10 +86,10 @@ impl Controller for RackController {
             ("CONTENT_LENGTH".into(), content_length),
         ]);
 
-        for (key, value) in request.headers().iter() {
+        for (key, value) in request.headers().clone().into_raw() {
             env.insert(
-                format!("HTTP_{}", crate::snake_case(key).to_ascii_uppercase()),
-                value.to_string(),
+                format!("HTTP_{}", crate::snake_case(&key).to_ascii_uppercase()),
+                value,
             );
         }
 
@@ -3,20 +3,29 @@
 //! The cipher used is AES-128.
 use aes_gcm_siv::{
     aead::{Aead, KeyInit},
-    Aes128GcmSiv, Nonce,
+    Aes128GcmSiv, Key, Nonce,
 };
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
 use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use thiserror::Error;
 use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+use tracing::info;
 
 use crate::config::get_config;
 
+static KEY_RING: Lazy<RwLock<KeyRing>> =
+    Lazy::new(|| RwLock::new(KeyRing::new(get_config().general.aes_key)));
+
 /// Errors returned by the crypto implementation.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -90,6 +99,109 @@ impl Encrypted {
     }
 }
 
+/// AES keys used to encrypt cookies, sessions and arbitrary user data.
+///
+/// New data is encrypted with the primary key. After a rotation, the previous primary key
+/// is kept for decryption, so data encrypted before the rotation, e.g. sessions,
+/// remains valid until the next rotation.
+///
+/// # Example
+///
+/// ```
+/// use rwf::crypto::KeyRing;
+///
+/// let mut ring = KeyRing::random();
+/// let ciphertext = ring.encrypt(b"hello world").unwrap();
+///
+/// ring.rotate();
+/// assert_eq!(ring.decrypt(&ciphertext).unwrap(), b"hello world");
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyRing {
+    primary: Key<Aes128GcmSiv>,
+    previous: Option<Key<Aes128GcmSiv>>,
+}
+
+impl KeyRing {
+    /// Create a key ring with the primary key.
+    pub fn new(primary: Key<Aes128GcmSiv>) -> Self {
+        Self {
+            primary,
+            previous: None,
+        }
+    }
+
+    /// Create a key ring with a randomly generated primary key.
+    pub fn random() -> Self {
+        Self::new(Self::random_key())
+    }
+
+    fn random_key() -> Key<Aes128GcmSiv> {
+        Key::<Aes128GcmSiv>::clone_from_slice(&rand::thread_rng().gen::<[u8; 128 / 8]>())
+    }
+
+    /// Replace the primary key with a randomly generated one. The replaced key
+    /// is kept for decryption only.
+    pub fn rotate(&mut self) {
+        self.previous = Some(std::mem::replace(&mut self.primary, Self::random_key()));
+    }
+
+    /// Encrypt data with the primary key.
+    pub fn encrypt(&self, data: &[u8]) -> Result<String, Error> {
+        let nonce = nonce();
+
+        let cipher = Aes128GcmSiv::new(&self.primary);
+        let aes_nonce = Nonce::from_slice(&nonce); // 96-bits; unique per message
+        let ciphertext = cipher
+            .encrypt(aes_nonce, data)
+            .expect("aes-128 encryption failed");
+
+        Encrypted { ciphertext, nonce }.to_bytes()
+    }
+
+    /// Decrypt data encrypted with the primary key or the previous primary key.
+    pub fn decrypt(&self, data: &str) -> Result<Vec<u8>, Error> {
+        let encrypted = Encrypted::from_base64(data)?;
+        let aes_nonce = Nonce::from_slice(&encrypted.nonce);
+
+        let mut result =
+            Aes128GcmSiv::new(&self.primary).decrypt(aes_nonce, encrypted.ciphertext.as_ref());
+
+        if let (Err(_), Some(ref previous)) = (&result, self.previous) {
+            result = Aes128GcmSiv::new(previous).decrypt(aes_nonce, encrypted.ciphertext.as_ref());
+        }
+
+        Ok(result?)
+    }
+}
+
+/// Rotate the application encryption key. See [`KeyRing::rotate`].
+///
+/// The new key only exists in memory, so applications running
+/// multiple instances shouldn't rotate keys this way.
+pub fn rotate_key() {
+    KEY_RING.write().rotate();
+}
+
+/// Rotate the application encryption key periodically,
+/// as configured with [`crate::config::General::key_rotation_interval`].
+///
+/// Returns `None` if key rotation is disabled.
+pub fn spawn_key_rotation() -> Option<JoinHandle<()>> {
+    let interval = get_config().general.key_rotation_interval()?;
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval.unsigned_abs());
+        interval.tick().await; // The first tick completes immediately.
+
+        loop {
+            interval.tick().await;
+            rotate_key();
+            info!("rotated encryption key");
+        }
+    }))
+}
+
 /// Encrypt data using the application secret key.
 ///
 /// # Example
@@ -100,17 +212,7 @@ impl Encrypted {
 /// let ciphertext = encrypt(b"hello world").expect("encryption failed");
 /// ```
 pub fn encrypt(data: &[u8]) -> Result<String, Error> {
-    let config = get_config();
-    let nonce = nonce();
-
-    let key = config.general.aes_key;
-    let cipher = Aes128GcmSiv::new(&key);
-    let aes_nonce = Nonce::from_slice(&nonce); // 96-bits; unique per message
-    let ciphertext = cipher
-        .encrypt(aes_nonce, data)
-        .expect("aes-128 encryption failed");
-
-    Encrypted { ciphertext, nonce }.to_bytes()
+    KEY_RING.read().encrypt(data)
 }
 
 /// Decrypt data encrypted with the application secret key.
@@ -126,15 +228,7 @@ pub fn encrypt(data: &[u8]) -> Result<String, Error> {
 /// assert_eq!(plain, "super secret".as_bytes());
 /// ```
 pub fn decrypt(data: &str) -> Result<Vec<u8>, Error> {
-    let config = get_config();
-    let encrypted = Encrypted::from_base64(data)?;
-
-    let key = config.general.aes_key;
-    let cipher = Aes128GcmSiv::new(&key);
-    let aes_nonce = Nonce::from_slice(&encrypted.nonce);
-    let plaintext = cipher.decrypt(aes_nonce, encrypted.ciphertext.as_ref())?;
-
-    Ok(plaintext)
+    KEY_RING.read().decrypt(data)
 }
 
 /// Encrypt an integer using the application secret key and return
@@ -203,14 +297,21 @@ pub fn decrypt_number(s: &str) -> Result<i64, Error> {
     // Remove the pretty format.
     let s = s.replace("-", "");
 
-    if s.len() % 2 != 0 {
+    // Comes from the client, so could be anything.
+    if !s.is_ascii() || !s.len().is_multiple_of(2) {
         return Err(Error::Generic("incorrect secure id format"));
     }
 
-    let bytes = (0..s.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap_or(0))
-        .collect::<Vec<_>>();
+    let bytes = s
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or(Error::Generic("incorrect secure id format"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
     // Should contain at least the nonce.
     if bytes.len() < 12 {
@@ -232,6 +333,128 @@ pub fn decrypt_number(s: &str) -> Result<i64, Error> {
     Ok(i64::from_be_bytes(plaintext.try_into().unwrap()))
 }
 
+fn mac() -> Hmac<Sha256> {
+    let key = get_config().general.secure_id_key;
+    <Hmac<Sha256> as Mac>::new_from_slice(&key).expect("hmac accepts keys of any size")
+}
+
+/// Sign data with HMAC-SHA256, using the secure ID key. The signature
+/// is encoded with URL-safe Base64, so it can be used in URLs.
+///
+/// # Example
+///
+/// ```
+/// use rwf::crypto::{sign, sign_validate};
+///
+/// let signature = sign(b"/unsubscribe?user=5");
+/// assert!(sign_validate(b"/unsubscribe?user=5", &signature));
+/// assert!(!sign_validate(b"/unsubscribe?user=6", &signature));
+/// ```
+pub fn sign(data: &[u8]) -> String {
+    let mut mac = mac();
+    mac.update(data);
+    general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+/// Check that the signature was created by [`sign`] for this data.
+/// The comparison is done in constant time.
+pub fn sign_validate(data: &[u8], signature: &str) -> bool {
+    let signature = match general_purpose::URL_SAFE_NO_PAD.decode(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+
+    let mut mac = mac();
+    mac.update(data);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// A primary key, e.g. of an order, that's safe to expose in URLs.
+///
+/// The ID is encrypted with the secure ID key, so it doesn't reveal how many
+/// records there are or allow guessing the ID of other records.
+/// IDs that weren't encrypted with the same key or were tampered with are rejected.
+///
+/// # Example
+///
+/// ```
+/// use rwf::crypto::SecureId;
+///
+/// let id = SecureId::new(1234);
+/// let public = id.to_string();
+/// let id = public.parse::<SecureId>().unwrap();
+///
+/// assert_eq!(id.id(), 1234);
+/// assert!("1234".parse::<SecureId>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SecureId(i64);
+
+impl SecureId {
+    /// Create a secure ID from a primary key.
+    pub fn new(id: i64) -> Self {
+        Self(id)
+    }
+
+    /// Get the primary key.
+    pub fn id(&self) -> i64 {
+        self.0
+    }
+}
+
+impl From<i64> for SecureId {
+    fn from(id: i64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<SecureId> for i64 {
+    fn from(id: SecureId) -> Self {
+        id.0
+    }
+}
+
+impl std::fmt::Display for SecureId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let id = encrypt_number(self.0).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", id)
+    }
+}
+
+impl std::str::FromStr for SecureId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(decrypt_number(s)?))
+    }
+}
+
+impl Serialize for SecureId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecureId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        id.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl crate::http::ToParameter for SecureId {
+    fn to_parameter(s: &str) -> Result<Self, crate::http::Error> {
+        s.parse()
+            .map_err(|_| crate::http::Error::MalformedRequest("secure id"))
+    }
+}
+
+impl crate::model::ToValue for SecureId {
+    fn to_value(&self) -> crate::model::Value {
+        crate::model::Value::Integer(self.0)
+    }
+}
+
 /// Generate a random string of length n.
 ///
 /// # Example
@@ -358,6 +581,28 @@ mod test {
         assert_eq!(text, String::from_utf8_lossy(&plain));
     }
 
+    #[test]
+    fn test_key_rotation() {
+        let mut ring = KeyRing::random();
+        let session = serde_json::to_string(&crate::controller::Session::anonymous()).unwrap();
+        let before = ring.encrypt(session.as_bytes()).unwrap();
+
+        ring.rotate();
+        let after = ring.encrypt(session.as_bytes()).unwrap();
+
+        // Sessions issued before the rotation are still valid.
+        assert_eq!(ring.decrypt(&before).unwrap(), session.as_bytes());
+        assert_eq!(ring.decrypt(&after).unwrap(), session.as_bytes());
+
+        // Only the previous key is kept.
+        ring.rotate();
+        assert!(ring.decrypt(&before).is_err());
+        assert_eq!(ring.decrypt(&after).unwrap(), session.as_bytes());
+
+        // Keys are different after rotation.
+        assert!(KeyRing::random().decrypt(&after).is_err());
+    }
+
     #[test]
     fn test_encrypt_number() {
         let n = 2345;
@@ -369,4 +614,46 @@ mod test {
         let result = decrypt_number(&bad_input);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_secure_id() {
+        for n in [0, 1, 2345, -5, i64::MAX] {
+            let id = SecureId::new(n);
+            let public = id.to_string();
+            assert_eq!(public.parse::<SecureId>().unwrap(), id);
+
+            let json = serde_json::to_string(&id).unwrap();
+            assert!(json.starts_with('"'));
+            assert_eq!(serde_json::from_str::<SecureId>(&json).unwrap(), id);
+        }
+
+        // Encrypted with a random nonce.
+        assert_ne!(SecureId::new(1).to_string(), SecureId::new(1).to_string());
+    }
+
+    #[test]
+    fn test_secure_id_tampered() {
+        let public = SecureId::new(2345).to_string();
+
+        // Flip one hex digit.
+        let mut tampered = public.clone().into_bytes();
+        tampered[0] = if tampered[0] == b'0' { b'1' } else { b'0' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(tampered.parse::<SecureId>().is_err());
+
+        assert!(public[..public.len() - 2].parse::<SecureId>().is_err());
+        assert!("2345".parse::<SecureId>().is_err());
+        assert!("".parse::<SecureId>().is_err());
+        assert!("é0a".parse::<SecureId>().is_err());
+        assert!("zz".repeat(14).parse::<SecureId>().is_err());
+        assert!(serde_json::from_str::<SecureId>("2345").is_err());
+    }
+
+    #[test]
+    fn test_sign() {
+        let signature = sign(b"hello world");
+        assert!(sign_validate(b"hello world", &signature));
+        assert!(!sign_validate(b"hello world!", &signature));
+        assert!(!sign_validate(b"hello world", "not base64!"));
+    }
 }
@@ -0,0 +1,132 @@
+//! Access log.
+//!
+//! When enabled with the `access_log` setting, a line is logged for every completed request,
+//! in a `key=value` format that's easy to parse with log processing tools, e.g.:
+//!
+//! ```text
+//! method=GET path=/orders status=200 bytes=1024 duration_ms=1.250 remote=127.0.0.1 request_id=5b1c7c8e-...
+//! ```
+//!
+//! Values sent by the client containing spaces, quotes, `=` or control characters are quoted and escaped,
+//! e.g. `path="/my orders"`, so they can't add fields or lines to the log.
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use super::{Method, Request};
+
+/// Access log entry for a completed request.
+#[derive(Debug, Clone)]
+pub struct AccessLog {
+    /// Request method.
+    pub method: Method,
+    /// Request path, without the query.
+    pub path: String,
+    /// Response status code.
+    pub status: u16,
+    /// Number of bytes sent to the client, including the response head.
+    pub bytes: usize,
+    /// How long it took to serve the request.
+    pub duration: Duration,
+    /// Client IP address.
+    pub remote: IpAddr,
+    /// Unique request identifier.
+    pub request_id: String,
+}
+
+impl AccessLog {
+    /// Create access log entry for a request.
+    pub fn new(request: &Request, status: u16, bytes: usize, duration: Duration) -> Self {
+        Self {
+            method: request.method().clone(),
+            path: request.path().path().to_string(),
+            status,
+            bytes,
+            duration,
+            remote: request.peer().ip(),
+            request_id: request.id().to_string(),
+        }
+    }
+}
+
+impl Display for AccessLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "method={} path={} status={} bytes={} duration_ms={:.3} remote={} request_id={}",
+            self.method,
+            quote(&self.path),
+            self.status,
+            self.bytes,
+            self.duration.as_secs_f64() * 1000.0,
+            self.remote,
+            quote(&self.request_id),
+        )
+    }
+}
+
+/// Quote the value if it contains characters that would break the `key=value` format.
+fn quote(value: &str) -> String {
+    if !value.is_empty()
+        && value
+            .chars()
+            .all(|c| !c.is_control() && !c.is_whitespace() && !matches!(c, '"' | '=' | '\\'))
+    {
+        value.to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::{
+        request::test::{dummy_ip, dummy_request},
+        Response,
+    };
+
+    #[tokio::test]
+    async fn test_access_log() {
+        let request = dummy_request().await.unwrap();
+        let response = Response::new().text("hello");
+        let mut sent = vec![];
+        let bytes = response.send(&mut sent).await.unwrap();
+        assert_eq!(bytes, sent.len());
+
+        let log = AccessLog::new(&request, 200, bytes, Duration::from_micros(1250)).to_string();
+        let fields = log
+            .split(" ")
+            .filter_map(|field| field.split_once("="))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        assert_eq!(fields["method"], "GET");
+        assert_eq!(fields["path"], "/");
+        assert_eq!(fields["status"], "200");
+        assert_eq!(fields["bytes"], bytes.to_string());
+        assert_eq!(fields["duration_ms"], "1.250");
+        assert_eq!(fields["remote"], "127.0.0.1");
+        assert_eq!(fields["request_id"], request.id());
+    }
+
+    #[tokio::test]
+    async fn test_access_log_injection() {
+        let request =
+            "GET /x%20status=500%20remote=10.0.0.1 HTTP/1.1\r\nX-Request-Id: abc=1\r\n\r\n";
+        let request = Request::read(dummy_ip(), request.as_bytes()).await.unwrap();
+
+        let log = AccessLog::new(&request, 200, 0, Duration::ZERO).to_string();
+        assert_eq!(
+            log,
+            r#"method=GET path="/x status=500 remote=10.0.0.1" status=200 bytes=0 duration_ms=0.000 remote=127.0.0.1 request_id="abc=1""#
+        );
+
+        let mut entry = AccessLog::new(&request, 200, 0, Duration::ZERO);
+        entry.request_id = "".into();
+        assert!(entry.to_string().ends_with(r#"request_id="""#));
+        entry.path = "/\nmethod=GET path=\"/admin\"".into();
+        let log = entry.to_string();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.contains(r#"path="/\nmethod=GET path=\"/admin\"""#));
+    }
+}
@@ -2,10 +2,10 @@
 //!
 //! This module handles decoding the `Cookie` header,
 //! and generating `Set-Cookie` headers.
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use time::{Duration, OffsetDateTime};
 
-use super::url::urldecode;
+use super::url::{urldecode, urlencode};
 use super::Error;
 use crate::config::get_config;
 use crate::controller::Session;
@@ -86,6 +86,41 @@ impl Cookies {
         }
     }
 
+    /// Add several values as one encrypted cookie. See [`CookieBag`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::{Cookies, CookieBag};
+    /// let mut bag = CookieBag::new("prefs");
+    /// bag.set("theme", "dark");
+    /// bag.set("lang", "en");
+    ///
+    /// let mut cookies = Cookies::new();
+    /// cookies.add_bag(&bag).unwrap();
+    ///
+    /// let bag = cookies.get_bag("prefs").unwrap().unwrap();
+    /// assert_eq!(bag.get("theme"), Some("dark"));
+    /// ```
+    pub fn add_bag(&mut self, bag: &CookieBag) -> Result<(), Error> {
+        let value = serde_json::to_string(&bag.values)?;
+        self.add_private((bag.name.as_str(), value.as_str()))
+    }
+
+    /// Get an encrypted cookie bag received from the client.
+    ///
+    /// Like [`Cookies::get_private`], if the decryption fails, `None` is returned.
+    pub fn get_bag(&self, name: &str) -> Result<Option<CookieBag>, Error> {
+        if let Some(cookie) = self.get_private(name)? {
+            Ok(Some(CookieBag {
+                name: name.to_string(),
+                values: serde_json::from_str(cookie.value())?,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Add a cookie.
     ///
     /// If this is done to the response, the cookie will be sent it to the client,
@@ -100,6 +135,39 @@ impl Cookies {
         self.cookies.get(name)
     }
 
+    /// Add a cookie holding a JSON-encoded value. The value is percent-encoded,
+    /// since JSON uses characters which aren't allowed in cookies, e.g. `"` and `,`.
+    ///
+    /// The value isn't encrypted, so the client can read and modify it. Use [`Cookies::add_bag`]
+    /// for anything the client shouldn't change.
+    pub fn add_json<T: serde::Serialize>(&mut self, name: &str, value: &T) -> Result<(), Error> {
+        let value = serde_json::to_string(value)?;
+        self.add((name, urlencode(&value).as_str()));
+        Ok(())
+    }
+
+    /// Get a cookie holding a JSON-encoded value sent by the client, and deserialize it.
+    /// Cookie values are percent-decoded when they are received.
+    ///
+    /// If the cookie isn't set, `None` is returned. If the value isn't valid JSON
+    /// or doesn't match the type, an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Cookies;
+    /// let cookies = Cookies::parse("cart=%7B%22items%22%3A%5B1%2C2%5D%7D");
+    /// let cart = cookies.get_json::<serde_json::Value>("cart").unwrap().unwrap();
+    ///
+    /// assert_eq!(cart["items"], serde_json::json!([1, 2]));
+    /// ```
+    pub fn get_json<T: serde::de::DeserializeOwned>(&self, name: &str) -> Result<Option<T>, Error> {
+        match self.get(name) {
+            Some(cookie) => Ok(Some(serde_json::from_str(cookie.value())?)),
+            None => Ok(None),
+        }
+    }
+
     /// Get the session cookie, if one is set. If no session is set,
     /// `None` is returned. While all requests should have a session, there is
     /// no guarantee the browser respects cookie settings we send over (e.g. cURL won't).
@@ -148,6 +216,56 @@ impl std::fmt::Display for Cookies {
     }
 }
 
+/// Multiple values stored in one encrypted cookie.
+///
+/// Each encrypted cookie carries its own nonce and encoding overhead. Storing
+/// small values together saves space and keeps the `Cookie` header small.
+#[derive(Debug, Clone, Default)]
+pub struct CookieBag {
+    name: String,
+    values: BTreeMap<String, String>,
+}
+
+impl CookieBag {
+    /// Create new empty bag, stored in the cookie `name`.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Cookie name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get a value from the bag.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|value| value.as_str())
+    }
+
+    /// Set a value in the bag, replacing the previous value, if any.
+    pub fn set(&mut self, key: impl ToString, value: impl ToString) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// Remove a value from the bag.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.values.remove(key)
+    }
+
+    /// Number of values in the bag.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// The bag has no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
 /// Convert a value to a cookie.
 ///
 /// This is syntax sugar to help create cookies more easily. Most use cases would
@@ -480,4 +598,70 @@ mod test {
             "super_secret_key"
         );
     }
+
+    #[test]
+    fn test_json_cookie() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Prefs {
+            theme: String,
+            columns: Vec<String>,
+        }
+
+        let prefs = Prefs {
+            theme: "dark; contrast=high".into(),
+            columns: vec!["name".into(), "email".into()],
+        };
+
+        let mut cookies = Cookies::new();
+        cookies.add_json("prefs", &prefs).unwrap();
+        let s = cookies.to_string();
+        assert!(!s.contains('"'));
+
+        let received = Cookies::parse(&s);
+        assert_eq!(received.get_json::<Prefs>("prefs").unwrap(), Some(prefs));
+        assert!(received.get_json::<Prefs>("missing").unwrap().is_none());
+
+        // Not JSON.
+        let received = Cookies::parse("prefs=dark");
+        assert!(received.get_json::<Prefs>("prefs").is_err());
+
+        // JSON, but not the right shape.
+        let received = Cookies::parse("prefs=%7B%22theme%22%3A1%7D");
+        assert!(matches!(
+            received.get_json::<Prefs>("prefs"),
+            Err(Error::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_cookie_bag() {
+        let mut bag = CookieBag::new("bag");
+        bag.set("user_id", 1234);
+        bag.set("theme", "dark");
+        bag.set("lang", "en; q=1");
+
+        let mut cookies = Cookies::new();
+        cookies.add_bag(&bag).expect("bag");
+        let s = cookies.to_string();
+
+        let received = Cookies::parse(&s);
+        let bag = received.get_bag("bag").expect("decrypt").expect("bag");
+        assert_eq!(bag.len(), 3);
+        assert_eq!(bag.get("user_id"), Some("1234"));
+        assert_eq!(bag.get("theme"), Some("dark"));
+        assert_eq!(bag.get("lang"), Some("en; q=1"));
+        assert_eq!(bag.get("missing"), None);
+        assert!(received.get_bag("missing").unwrap().is_none());
+
+        // Smaller than encrypting each value separately.
+        let mut separate = Cookies::new();
+        separate.add_private(("user_id", "1234")).unwrap();
+        separate.add_private(("theme", "dark")).unwrap();
+        separate.add_private(("lang", "en; q=1")).unwrap();
+        assert!(s.len() < separate.to_string().len());
+
+        // Tampered with.
+        let tampered = Cookies::parse("bag=aGVsbG8gd29ybGQ");
+        assert!(tampered.get_bag("bag").unwrap().is_none());
+    }
 }
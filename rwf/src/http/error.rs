@@ -44,10 +44,19 @@ pub enum Error {
     #[error("parameter is missing")]
     MissingParameter,
 
+    /// A URL parameter, e.g. `:id`, couldn't be converted
+    /// to the requested type.
+    #[error("invalid parameter: {0}")]
+    InvalidParameter(String),
+
     /// Something took too long.
     #[error("timeout exceeded")]
     Timeout(#[from] tokio::time::error::Elapsed),
 
+    /// Configuration is not valid.
+    #[error("config: {0}")]
+    Config(#[from] crate::config::Error),
+
     /// The ORM returned an error.
     #[error("database error: {0}")]
     Orm(#[from] crate::model::Error),
@@ -67,6 +76,27 @@ pub enum Error {
     /// Model used as user has null id column.
     #[error("user model is is null")]
     UserIdIsNull,
+
+    /// WebSocket message is larger than the configured maximum.
+    #[error("websocket message too big")]
+    MessageTooBig,
+
+    /// A conditional request header, e.g. `If-Match`, didn't match
+    /// the current state of the resource.
+    #[error("precondition failed")]
+    PreconditionFailed,
+
+    /// The request body is encoded in a format we don't understand.
+    #[error("unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    /// Form data or query parameters couldn't be deserialized into the requested type.
+    #[error("form: {0}")]
+    Form(#[from] super::form_data::FormError),
+
+    /// The request uses an HTTP version we can't serve, e.g. `HTTP/9.9`.
+    #[error("http version not supported: {0}")]
+    VersionNotSupported(String),
 }
 
 impl Error {
@@ -74,9 +104,17 @@ impl Error {
     /// that should be sent to the client.
     pub fn code(&self) -> u16 {
         match self {
-            Self::MissingParameter => 400,
+            Self::MalformedRequest(_)
+            | Self::MissingParameter
+            | Self::InvalidParameter(_)
+            | Self::Json(_) => 400,
             Self::Forbidden => 403,
+            Self::Timeout(_) => 408,
+            Self::PreconditionFailed => 412,
             Self::ContentTooLarge(_) => 413,
+            Self::UnsupportedMediaType(_) => 415,
+            Self::Form(_) => 422,
+            Self::VersionNotSupported(_) => 505,
             _ => 500,
         }
     }
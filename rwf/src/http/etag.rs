@@ -0,0 +1,251 @@
+//! Entity tags (`ETag`) and conditional request headers.
+//!
+//! Entity tags identify a specific version of a resource. Clients send them back
+//! in headers like `If-Match` to make a request conditional on the resource
+//! not having changed since they last saw it.
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+
+use super::Error;
+
+/// An entity tag, e.g. `"xyzzy"` or `W/"xyzzy"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ETag {
+    tag: String,
+    weak: bool,
+}
+
+impl ETag {
+    /// Create a strong entity tag.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::ETag;
+    /// let etag = ETag::strong("v1");
+    /// assert_eq!(etag.to_string(), r#""v1""#);
+    /// ```
+    pub fn strong(tag: impl ToString) -> Self {
+        Self {
+            tag: tag.to_string(),
+            weak: false,
+        }
+    }
+
+    /// Create a weak entity tag.
+    pub fn weak(tag: impl ToString) -> Self {
+        Self {
+            tag: tag.to_string(),
+            weak: true,
+        }
+    }
+
+    /// Parse an entity tag from a header value, e.g. `W/"xyzzy"`.
+    ///
+    /// Returns `None` if the value isn't a quoted entity tag.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (weak, value) = match value.strip_prefix("W/") {
+            Some(value) => (true, value),
+            None => (false, value),
+        };
+
+        if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            let tag = &value[1..value.len() - 1];
+            if tag.contains('"') {
+                None
+            } else {
+                Some(Self {
+                    tag: tag.to_string(),
+                    weak,
+                })
+            }
+        } else {
+            None
+        }
+    }
+
+    /// The opaque tag value, without quotes.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Is this a weak entity tag?
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Strong comparison: both tags must be strong and identical.
+    ///
+    /// This is the comparison used by `If-Match`.
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Weak comparison: tags are identical, regardless of weakness.
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl std::fmt::Display for ETag {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.weak {
+            write!(f, "W/")?;
+        }
+
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+/// Value of the `If-Match` header.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IfMatch {
+    /// `If-Match: *`, matches any current representation.
+    Any,
+    /// A list of entity tags, e.g. `If-Match: "v1", "v2"`.
+    Tags(Vec<ETag>),
+}
+
+impl IfMatch {
+    /// Parse the `If-Match` header value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::{ETag, IfMatch};
+    /// let if_match = IfMatch::parse(r#""v1", "v2""#).unwrap();
+    /// assert!(if_match.matches(&ETag::strong("v2")));
+    /// ```
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let value = value.trim();
+
+        if value == "*" {
+            return Ok(IfMatch::Any);
+        }
+
+        let mut tags = vec![];
+
+        for tag in value.split(",") {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                continue;
+            }
+
+            tags.push(ETag::parse(tag).ok_or(Error::MalformedRequest("if-match"))?);
+        }
+
+        if tags.is_empty() {
+            Err(Error::MalformedRequest("if-match"))
+        } else {
+            Ok(IfMatch::Tags(tags))
+        }
+    }
+
+    /// Check the precondition against the current entity tag of the resource.
+    ///
+    /// `If-Match` uses the strong comparison, so weak tags never match.
+    pub fn matches(&self, current: &ETag) -> bool {
+        match self {
+            IfMatch::Any => true,
+            IfMatch::Tags(tags) => tags.iter().any(|tag| tag.strong_eq(current)),
+        }
+    }
+}
+
+/// Value of the `If-Range` header.
+///
+/// Used with range requests to resume a download only if the resource
+/// hasn't changed. If the condition doesn't match, the server should ignore
+/// the `Range` header and send the full resource.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IfRange {
+    /// Entity tag, e.g. `If-Range: "v1"`.
+    ETag(ETag),
+    /// HTTP date, e.g. `If-Range: Wed, 21 Oct 2015 07:28:00 GMT`.
+    Date(OffsetDateTime),
+}
+
+impl IfRange {
+    /// Parse the `If-Range` header value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::{ETag, IfRange};
+    /// let if_range = IfRange::parse(r#""v1""#).unwrap();
+    /// assert_eq!(if_range, IfRange::ETag(ETag::strong("v1")));
+    /// ```
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let value = value.trim();
+
+        if value.starts_with('"') || value.starts_with("W/") {
+            ETag::parse(value)
+                .map(IfRange::ETag)
+                .ok_or(Error::MalformedRequest("if-range"))
+        } else {
+            OffsetDateTime::parse(value, &Rfc2822)
+                .map(IfRange::Date)
+                .map_err(|_| Error::MalformedRequest("if-range"))
+        }
+    }
+
+    /// Check the condition against the current entity tag and modification date of the resource.
+    ///
+    /// Entity tags use the strong comparison and dates must match exactly.
+    pub fn matches(&self, etag: Option<&ETag>, last_modified: Option<&OffsetDateTime>) -> bool {
+        match self {
+            IfRange::ETag(tag) => etag.map(|etag| tag.strong_eq(etag)).unwrap_or(false),
+            IfRange::Date(date) => last_modified.map(|lm| lm == date).unwrap_or(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_etag() {
+        assert_eq!(ETag::parse(r#""xyzzy""#), Some(ETag::strong("xyzzy")));
+        assert_eq!(ETag::parse(r#"W/"xyzzy""#), Some(ETag::weak("xyzzy")));
+        assert_eq!(ETag::parse("xyzzy"), None);
+        assert_eq!(ETag::weak("v1").to_string(), r#"W/"v1""#);
+    }
+
+    #[test]
+    fn test_if_match() {
+        let current = ETag::strong("v2");
+
+        let fresh = IfMatch::parse(r#""v1", "v2""#).unwrap();
+        assert!(fresh.matches(&current));
+
+        let stale = IfMatch::parse(r#""v1""#).unwrap();
+        assert!(!stale.matches(&current));
+
+        let weak = IfMatch::parse(r#"W/"v2""#).unwrap();
+        assert!(!weak.matches(&current));
+
+        assert!(IfMatch::parse("*").unwrap().matches(&current));
+        assert!(IfMatch::parse("v2").is_err());
+    }
+
+    #[test]
+    fn test_if_range() {
+        let etag = ETag::strong("v1");
+        let if_range = IfRange::parse(r#""v1""#).unwrap();
+        assert_eq!(if_range, IfRange::ETag(etag.clone()));
+        assert!(if_range.matches(Some(&etag), None));
+        assert!(!if_range.matches(Some(&ETag::strong("v2")), None));
+        assert!(!IfRange::parse(r#"W/"v1""#)
+            .unwrap()
+            .matches(Some(&etag), None));
+
+        let date = OffsetDateTime::from_unix_timestamp(1445412480).unwrap();
+        let if_range = IfRange::parse("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(if_range, IfRange::Date(date));
+        assert!(if_range.matches(None, Some(&date)));
+        assert!(!if_range.matches(Some(&etag), None));
+
+        assert!(IfRange::parse("yesterday").is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Request extensions.
+//!
+//! Middleware can attach data to a request, e.g. the authenticated user,
+//! which controllers further down the chain can then retrieve by its type.
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Map of values, keyed by their type. Only one value of each type can be stored.
+///
+/// Values are reference-counted, so cloning the map (and the request) is cheap.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::http::Extensions;
+/// struct CurrentUser {
+///     id: i64,
+/// }
+///
+/// let mut extensions = Extensions::new();
+/// extensions.insert(CurrentUser { id: 5 });
+///
+/// assert_eq!(extensions.get::<CurrentUser>().unwrap().id, 5);
+/// assert!(extensions.get::<String>().is_none());
+/// ```
+#[derive(Default, Clone)]
+pub struct Extensions {
+    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a value, replacing the value of the same type, if any.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) {
+        self.map.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Get the value of this type.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Get a shared reference to the value of this type, which can outlive the request.
+    pub fn get_arc<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .cloned()
+            .and_then(|value| value.downcast().ok())
+    }
+
+    /// Remove the value of this type. Returns `true` if it was present.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> bool {
+        self.map.remove(&TypeId::of::<T>()).is_some()
+    }
+
+    /// A value of this type is present.
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Number of values.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// The map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
@@ -2,6 +2,12 @@
 //!
 //! Both `x-www-form-urlencoded` and `multipart/form-data` formats are supported.
 use super::{urldecode, Error, Query, Request};
+use serde::de::{
+    self, value::MapDeserializer, DeserializeOwned, Deserializer, IntoDeserializer, Unexpected,
+    Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::fmt::Display;
 use std::str::FromStr;
 
 use std::collections::btree_map::{BTreeMap, IntoIter};
@@ -107,6 +113,16 @@ impl FormData {
         }
     }
 
+    /// Deserialize the form into a Rust struct. All values except files are included.
+    ///
+    /// Values are converted to the type of the struct field, e.g. `"25"` into an `i64`.
+    /// Checkboxes (`on`/`off`) can be deserialized into `bool`, and empty values into `None`.
+    ///
+    /// If a value can't be converted, [`Error::Form`] is returned with the name of the field.
+    pub fn deserialize<T: DeserializeOwned>(self) -> Result<T, Error> {
+        deserialize_entries(self.into_iter())
+    }
+
     /// An owning iterator over the form data. All values except files are included.
     pub fn into_iter(self) -> IntoIter<String, String> {
         match self {
@@ -367,6 +383,149 @@ impl ContentDisposition {
     }
 }
 
+/// Deserialize form or query parameters into a Rust struct.
+pub(crate) fn deserialize_entries<T: DeserializeOwned>(
+    entries: impl Iterator<Item = (String, String)>,
+) -> Result<T, Error> {
+    let entries = entries.map(|(name, value)| (name.clone(), FormValue { name, value }));
+
+    Ok(T::deserialize(MapDeserializer::new(entries))?)
+}
+
+/// A form or query parameter couldn't be deserialized.
+///
+/// Returned to the client as `422 - Unprocessable Entity`.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{}{reason}", field.as_ref().map(|field| format!("{}: ", field)).unwrap_or_default())]
+pub struct FormError {
+    /// Name of the field which failed to deserialize, if known.
+    pub field: Option<String>,
+    /// Why it failed, e.g. `invalid value: string "abc", expected i64`.
+    pub reason: String,
+}
+
+impl FormError {
+    fn field(mut self, name: &str) -> Self {
+        if self.field.is_none() {
+            self.field = Some(name.to_string());
+        }
+        self
+    }
+}
+
+impl de::Error for FormError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self {
+            field: None,
+            reason: msg.to_string(),
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self {
+            field: Some(field.to_string()),
+            reason: "missing field".to_string(),
+        }
+    }
+}
+
+/// Form value, converted to the requested type on deserialization.
+struct FormValue {
+    name: String,
+    value: String,
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.value.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.value), &visitor)),
+                }
+                .map_err(|err: FormError| err.field(&self.name))
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for FormValue {
+    type Error = FormError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let name = self.name;
+        visitor
+            .visit_string(self.value)
+            .map_err(|err: FormError| err.field(&name))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value.as_str() {
+            "true" | "on" | "1" => visitor.visit_bool(true),
+            "false" | "off" | "0" => visitor.visit_bool(false),
+            _ => Err(de::Error::invalid_value(
+                Unexpected::Str(&self.value),
+                &visitor,
+            )),
+        }
+        .map_err(|err: FormError| err.field(&self.name))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.value.is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let name = self.name;
+        visitor
+            .visit_enum(IntoDeserializer::<FormError>::into_deserializer(self.value))
+            .map_err(|err: FormError| err.field(&name))
+    }
+
+    deserialize_parsed!(
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64
+    );
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl IntoDeserializer<'_, FormError> for FormValue {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
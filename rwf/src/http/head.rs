@@ -6,7 +6,9 @@ use std::marker::Unpin;
 
 use tokio::io::{AsyncRead, AsyncReadExt};
 
-use super::{Authorization, Cookies, Error, Headers, Path, Query};
+use super::{
+    url::Url, Authorization, Cookies, ETag, Error, Headers, IfMatch, IfRange, Path, Query,
+};
 use crate::config::get_config;
 
 /// HTTP method, e.g. GET, POST, etc.
@@ -45,6 +47,23 @@ impl TryFrom<String> for Method {
     }
 }
 
+impl Method {
+    /// The method is expected to have a request body. `GET` and `HEAD`
+    /// requests conventionally don't have one.
+    pub fn has_body(&self) -> bool {
+        !matches!(self, Method::Get | Method::Head)
+    }
+
+    /// The method is safe, i.e. read-only (RFC 9110, section 9.2.1): `GET`, `HEAD` and `OPTIONS`.
+    pub fn safe(&self) -> bool {
+        match self {
+            Method::Get | Method::Head => true,
+            Method::Other(name) => name.eq_ignore_ascii_case("OPTIONS"),
+            _ => false,
+        }
+    }
+}
+
 impl std::fmt::Display for Method {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use Method::*;
@@ -63,25 +82,48 @@ impl std::fmt::Display for Method {
     }
 }
 
+/// Maximum number of empty lines ignored before the request line.
+const MAX_EMPTY_LINES: usize = 8;
+
+/// Character allowed in a token, e.g. the request method (RFC 9110, section 5.6.2).
+fn is_token(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+}
+
 /// HTTP version, e.g. HTTP/1.1 or HTTP/2.
 #[derive(Debug, Clone, PartialEq, Default)]
 pub enum Version {
     /// HTTP/1.1.
     #[default]
     Http1,
+    /// HTTP/1.0.
+    Http10,
     /// HTTP/2.
     Http2,
     /// Some other HTTP version we haven't even thought about.
     Unknown,
 }
 
+impl Version {
+    /// Is this HTTP/1.1?
+    pub fn http1(&self) -> bool {
+        self == &Version::Http1
+    }
+
+    /// Is this HTTP/2?
+    pub fn http2(&self) -> bool {
+        self == &Version::Http2
+    }
+}
+
 impl TryFrom<String> for Version {
     type Error = Error;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         match value.as_str() {
             "HTTP/1.1" => Ok(Version::Http1),
-            "HTTP/2" => Ok(Version::Http2),
+            "HTTP/1.0" => Ok(Version::Http10),
+            "HTTP/2" | "HTTP/2.0" => Ok(Version::Http2),
             _ => Ok(Version::Unknown),
         }
     }
@@ -91,6 +133,7 @@ impl std::fmt::Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Version::Http1 => write!(f, "HTTP/1.1"),
+            Version::Http10 => write!(f, "HTTP/1.0"),
             Version::Http2 => write!(f, "HTTP/2"),
             Version::Unknown => write!(f, "UNKNOWN"),
         }
@@ -106,60 +149,182 @@ pub struct Head {
     headers: Headers,
 }
 
+/// Problem found in the request head by [`Head::read_lenient`].
+#[derive(Debug)]
+pub struct HeadProblem {
+    /// Line number, starting at 1 for the request line.
+    pub line: usize,
+    /// What's wrong with the line.
+    pub error: Error,
+}
+
+/// Request head read by [`Head::read_lenient`], along with all the problems
+/// found while parsing it.
+#[derive(Debug)]
+pub struct HeadReport {
+    /// Everything that could be parsed. Malformed parts are left at their defaults.
+    pub head: Head,
+    /// Problems, in the order they appear in the request.
+    pub problems: Vec<HeadProblem>,
+}
+
+impl HeadReport {
+    /// The request head is well-formed.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
 impl Head {
     /// Read request head from a stream.
-    pub async fn read(mut stream: impl AsyncRead + Unpin) -> Result<Self, Error> {
-        let bytes_remaining = get_config().general.header_max_size; // avoid DDoS
+    ///
+    /// The first problem found in the head is returned as the error.
+    pub async fn read(stream: impl AsyncRead + Unpin) -> Result<Self, Error> {
+        let report = Self::read_lenient(stream).await?;
 
-        let request = Self::read_line(&mut stream, bytes_remaining)
-            .await?
-            .split(" ")
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
+        match report.problems.into_iter().next() {
+            Some(problem) => Err(problem.error),
+            None => Ok(report.head),
+        }
+    }
+
+    /// Read request head from a stream, collecting all parse problems instead
+    /// of stopping at the first one. Useful to diagnose misbehaving clients.
+    ///
+    /// Errors reading from the stream, e.g. a line that's too long, are still returned
+    /// immediately since the rest of the request can't be read reliably.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let report = Head::read_lenient(stream).await?;
+    ///
+    /// for problem in &report.problems {
+    ///     warn!("line {}: {}", problem.line, problem.error);
+    /// }
+    /// ```
+    pub async fn read_lenient(mut stream: impl AsyncRead + Unpin) -> Result<HeadReport, Error> {
+        // avoid DDoS
+        let max_line_length = get_config().general.max_line_length;
+        let bytes_remaining = get_config().general.header_max_size;
+
+        let mut problems = vec![];
+        let mut problem = |line: usize, error: Error| problems.push(HeadProblem { line, error });
+
+        // Clients may send empty lines between requests on a keep-alive connection,
+        // e.g. after a POST body, which should be ignored (RFC 9112, section 2.2).
+        let mut request_line = 1;
+        let request = loop {
+            let line = Self::read_line(&mut stream, max_line_length).await?;
+
+            if !line.is_empty() {
+                break line;
+            } else if request_line > MAX_EMPTY_LINES {
+                return Err(Error::MalformedRequest("empty request line"));
+            }
+
+            request_line += 1;
+        };
+
+        // Method, path and version separated by single spaces. The path can't contain spaces.
+        let request = request.split(" ").collect::<Vec<_>>();
 
-        let method = request
-            .get(0)
-            .ok_or(Error::MalformedRequest("method"))?
-            .to_string();
-        let method = Method::try_from(method)?;
+        if request.len() > 3 {
+            problem(request_line, Error::MalformedRequest("request line"));
+        }
+
+        let part = |i: usize| request.get(i).copied().filter(|part| !part.is_empty());
+
+        let method = match part(0) {
+            Some(method) if method.bytes().all(is_token) => Method::try_from(method.to_string())?,
+            _ => {
+                problem(request_line, Error::MalformedRequest("method"));
+                Method::default()
+            }
+        };
 
-        let path = request.get(1).ok_or(Error::MalformedRequest("path"))?;
-        let path = Path::parse(path)?;
+        let path = match part(1) {
+            Some(path) => match Path::parse(path)
+                .and_then(|path| path.decode(get_config().general.allow_encoded_slashes))
+            {
+                Ok(path) => path,
+                Err(err) => {
+                    problem(request_line, err);
+                    Path::default()
+                }
+            },
+            None => {
+                problem(request_line, Error::MalformedRequest("path"));
+                Path::default()
+            }
+        };
 
-        let version = request
-            .get(2)
-            .ok_or(Error::MalformedRequest("version"))?
-            .to_string();
-        let version = Version::try_from(version)?;
+        // This is an HTTP/1 parser, so anything else can't be framed correctly.
+        let version = match part(2) {
+            Some(version) => match Version::try_from(version.to_string())? {
+                version @ (Version::Http1 | Version::Http10) => version,
+                _ => {
+                    problem(
+                        request_line,
+                        Error::VersionNotSupported(version.to_string()),
+                    );
+                    Version::default()
+                }
+            },
+            None => {
+                problem(request_line, Error::MalformedRequest("version"));
+                Version::default()
+            }
+        };
 
         let mut headers = Headers::new();
+        let mut line = request_line;
 
         loop {
             let header = Self::read_line(&mut stream, bytes_remaining).await?;
+            line += 1;
+
             if header.is_empty() {
                 break;
             } else {
-                let header = header
-                    .split(":")
-                    .map(|s| s.trim().to_string())
-                    .collect::<Vec<_>>();
-                let name = header
-                    .get(0)
-                    .ok_or(Error::MalformedRequest("header name"))?
-                    .to_lowercase();
-                let value = header
-                    .get(1)
-                    .ok_or(Error::MalformedRequest("header value"))?
-                    .clone();
-                headers.insert(name, value);
+                // Values can contain colons, e.g. dates and URLs, so split on the first one only.
+                let (name, value) = match header.split_once(":") {
+                    Some((name, value)) => (name.trim().to_lowercase(), value.trim().to_string()),
+                    None => {
+                        problem(line, Error::MalformedRequest("header value"));
+                        continue;
+                    }
+                };
+
+                // Repeated with different values, the request could be framed or routed differently
+                // by each server handling it, e.g. a proxy and the application.
+                let conflict = match name.as_str() {
+                    "content-length" => Some("content length"),
+                    "host" => Some("host"),
+                    _ => None,
+                };
+                if let Some(conflict) = conflict {
+                    if headers
+                        .get(&name)
+                        .is_some_and(|previous| previous != &value)
+                    {
+                        problem(line, Error::MalformedRequest(conflict));
+                        continue;
+                    }
+                }
+
+                headers.append(name, value);
             }
         }
 
-        Ok(Head {
-            method,
-            path,
-            version,
-            headers,
+        Ok(HeadReport {
+            head: Head {
+                method,
+                path,
+                version,
+                headers,
+            },
+            problems,
         })
     }
 
@@ -172,25 +337,72 @@ impl Head {
         })
     }
 
+    /// Get the parsed `If-Match` header, if one is set.
+    ///
+    /// Returns an error if the header is present but malformed.
+    pub fn if_match(&self) -> Result<Option<IfMatch>, Error> {
+        match self.header("if-match") {
+            Some(value) => Ok(Some(IfMatch::parse(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the parsed `If-Range` header, if one is set.
+    ///
+    /// The header is either an entity tag or an HTTP date. Use [`IfRange::matches`]
+    /// to decide whether to honor the `Range` header or send the full resource.
+    pub fn if_range(&self) -> Result<Option<IfRange>, Error> {
+        match self.header("if-range") {
+            Some(value) => Ok(Some(IfRange::parse(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Check the `If-Match` precondition against the current entity tag of the resource.
+    ///
+    /// If the client sent an `If-Match` header that doesn't match, [`Error::PreconditionFailed`]
+    /// is returned. Used with the `?` operator in a controller, this returns `412 - Precondition Failed`
+    /// to the client. Requests without the header always pass.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// async fn update(&self, request: &Request, id: &i64) -> Result<Response, Error> {
+    ///     let order = Order::find(*id).fetch(&mut conn).await?;
+    ///     request.check_if_match(&order.etag())?;
+    ///     // Safe to update.
+    /// }
+    /// ```
+    pub fn check_if_match(&self, current: &ETag) -> Result<(), Error> {
+        match self.if_match()? {
+            Some(if_match) if !if_match.matches(current) => Err(Error::PreconditionFailed),
+            _ => Ok(()),
+        }
+    }
+
     /// Get cookies manager for this request.
     ///
     /// Cookies storage is used to retrieve regular and encrypted cookies.
     pub fn cookies(&self) -> Cookies {
-        if let Some(cookie) = self.headers.get("cookie") {
-            Cookies::parse(&cookie)
-        } else {
-            Cookies::default()
+        match self.headers.get_all("cookie") {
+            [] => Cookies::default(),
+            cookies => Cookies::parse(&cookies.join("; ")),
         }
     }
 
+    /// HTTP version of the request. Versions we don't support are [`Version::Unknown`].
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
     /// Is this a HTTP/2 request?
     pub fn http2(&self) -> bool {
-        self.version == Version::Http2
+        self.version.http2()
     }
 
     /// Is this a HTTP/1.1 request?
     pub fn http1(&self) -> bool {
-        self.version == Version::Http1
+        self.version.http1()
     }
 
     /// Get the request path, including query parameters, e.g., `/foo?hello=world`.
@@ -233,6 +445,127 @@ impl Head {
         }
     }
 
+    /// The body is sent with `Transfer-Encoding: chunked`, i.e. its size isn't known in advance.
+    ///
+    /// Codings listed over several `Transfer-Encoding` headers are combined, and `chunked`
+    /// has to be the final one.
+    pub fn chunked(&self) -> bool {
+        self.headers_all("transfer-encoding")
+            .iter()
+            .flat_map(|encoding| encoding.split(','))
+            .map(|encoding| encoding.trim())
+            .rfind(|encoding| !encoding.is_empty())
+            .map(|encoding| encoding.eq_ignore_ascii_case("chunked"))
+            .unwrap_or(false)
+    }
+
+    /// The `charset` parameter of the `Content-Type` header, if any, e.g. `iso-8859-1`.
+    ///
+    /// The value is lowercased and stripped of quotes.
+    pub fn charset(&self) -> Option<String> {
+        self.header("content-type")?
+            .split(";")
+            .skip(1)
+            .filter_map(|param| param.split_once("="))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+            .map(|(_, value)| value.trim().trim_matches('"').to_lowercase())
+    }
+
+    /// Languages listed in the `Accept-Language` header, with their quality,
+    /// sorted from most to least preferred.
+    ///
+    /// Languages without a quality value default to `1.0`. Languages with quality `0`
+    /// are not acceptable and are excluded.
+    pub fn accept_languages(&self) -> Vec<(String, f32)> {
+        let mut languages = self
+            .language_ranges()
+            .into_iter()
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect::<Vec<_>>();
+
+        // Stable sort keeps the client's order for languages with equal quality.
+        languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        languages
+    }
+
+    /// All language ranges in the `Accept-Language` header, including rejected ones (`q=0`),
+    /// in the client's order.
+    fn language_ranges(&self) -> Vec<(String, f32)> {
+        self.header("accept-language")
+            .map(|header| {
+                header
+                    .split(",")
+                    .filter_map(|language| {
+                        let mut parts = language.split(";");
+                        let tag = parts.next()?.trim().to_lowercase();
+                        let quality = parts
+                            .filter_map(|param| param.split_once("="))
+                            .find(|(name, _)| name.trim() == "q")
+                            .map(|(_, q)| q.trim().parse::<f32>().unwrap_or(0.0))
+                            .unwrap_or(1.0);
+
+                        if tag.is_empty() {
+                            None
+                        } else {
+                            Some((tag, quality.max(0.0)))
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Pick the best supported language, given the client's `Accept-Language` header.
+    ///
+    /// Region subtags fall back to the primary language, e.g. `en-US` matches `en`,
+    /// and `*` matches the first supported language the client didn't reject with `q=0`.
+    /// If none of the supported languages are acceptable, `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let locale = request.preferred_language(&["en", "fr"]).unwrap_or("en");
+    /// ```
+    pub fn preferred_language<'a>(&self, supported: &[&'a str]) -> Option<&'a str> {
+        let primary = |tag: &str| tag.split("-").next().unwrap_or("").to_lowercase();
+
+        for (language, _) in self.accept_languages() {
+            if language == "*" {
+                // `fr;q=0` rejects `fr` and `fr-CH`.
+                let rejected = self
+                    .language_ranges()
+                    .into_iter()
+                    .filter(|(_, quality)| *quality <= 0.0)
+                    .map(|(tag, _)| tag)
+                    .collect::<Vec<_>>();
+
+                return supported.iter().copied().find(|supported| {
+                    let supported = supported.to_lowercase();
+                    !rejected
+                        .iter()
+                        .any(|tag| supported == *tag || supported.starts_with(&format!("{}-", tag)))
+                });
+            }
+
+            if let Some(exact) = supported
+                .iter()
+                .find(|supported| supported.eq_ignore_ascii_case(&language))
+            {
+                return Some(exact);
+            }
+
+            if let Some(fallback) = supported
+                .iter()
+                .find(|supported| primary(supported) == primary(&language))
+            {
+                return Some(fallback);
+            }
+        }
+
+        None
+    }
+
     /// Get all request headers.
     pub fn headers(&self) -> &Headers {
         &self.headers
@@ -251,49 +584,230 @@ impl Head {
         self.headers.get(name)
     }
 
-    /// Is the keep-alive flag set?
+    /// Get all values of a header sent multiple times, e.g. `Via` or `Forwarded`,
+    /// in the order they were received.
+    ///
+    /// Case insensitive.
+    pub fn headers_all(&self, name: &str) -> &[String] {
+        self.headers.get_all(name)
+    }
+
+    /// Host (and port) the request was sent to. This is the `:authority` pseudo-header
+    /// in HTTP/2, and falls back to the `Host` header in HTTP/1.
+    pub fn authority(&self) -> Option<&String> {
+        self.header(":authority").or_else(|| self.header("host"))
+    }
+
+    /// URL scheme, e.g. `https`. This is the `:scheme` pseudo-header in HTTP/2.
+    ///
+    /// HTTP/1 requests don't include it, so it's taken from the `X-Forwarded-Proto` header
+    /// set by proxies and load balancers, and is `http` otherwise.
+    pub fn scheme(&self) -> String {
+        self.header(":scheme")
+            .or_else(|| self.header("x-forwarded-proto"))
+            .map(|scheme| scheme.trim().to_lowercase())
+            .unwrap_or_else(|| "http".to_string())
+    }
+
+    /// URL of the page which made the request, taken from the `Referer` header.
+    /// Returns `None` if the header is missing or isn't a valid absolute URL.
+    pub fn referrer(&self) -> Option<Url> {
+        self.header("referer")
+            .and_then(|referrer| Url::parse(referrer))
+    }
+
+    /// Origin (scheme, host and port) of the page which made the request, taken
+    /// from the `Origin` header. Returns `None` if the header is missing, malformed or `null`,
+    /// e.g. when sent from a sandboxed page.
+    pub fn origin(&self) -> Option<Url> {
+        self.header("origin")
+            .and_then(|origin| Url::parse(origin))
+            .filter(|origin| origin.path().is_empty())
+    }
+
+    /// Host the request was sent to, without the port. The host is lowercased
+    /// and a trailing dot is removed, e.g. `Example.com.:8000` becomes `example.com`.
+    ///
+    /// Returns `None` if the `Host` header is missing or isn't a valid host name.
+    pub fn host(&self) -> Option<String> {
+        let authority = self.authority()?.trim().to_lowercase();
+
+        let host = if authority.starts_with('[') {
+            // IPv6 address, e.g. [::1]:8000
+            let end = authority.find(']')?;
+            let (host, port) = authority.split_at(end + 1);
+            if !port.is_empty() && !port.starts_with(':') {
+                return None;
+            }
+            let valid = host[1..host.len() - 1]
+                .chars()
+                .all(|c| c.is_ascii_hexdigit() || c == ':' || c == '.');
+            return valid.then(|| host.to_string());
+        } else {
+            match authority.split_once(':') {
+                Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+                Some(_) => return None,
+                None => authority.as_str(),
+            }
+        };
+
+        let host = host.strip_suffix('.').unwrap_or(host);
+        let valid = !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+        valid.then(|| host.to_string())
+    }
+
+    /// Check the request host against a list of allowed hosts.
+    ///
+    /// Hosts are matched exactly, except for wildcards: `*.example.com` matches all subdomains
+    /// of `example.com`, and `*` matches any host. An empty list allows all hosts.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // Host: api.example.com:8000
+    /// assert!(request.host_allowed(&["*.example.com"]));
+    /// assert!(!request.host_allowed(&["example.com"]));
+    /// ```
+    pub fn host_allowed(&self, allowed_hosts: &[impl AsRef<str>]) -> bool {
+        if allowed_hosts.is_empty() {
+            return true;
+        }
+
+        let host = match self.host() {
+            Some(host) => host,
+            None => return false,
+        };
+
+        allowed_hosts.iter().any(|pattern| {
+            let pattern = pattern.as_ref().trim().to_lowercase();
+            let pattern = pattern.strip_suffix('.').unwrap_or(&pattern);
+
+            match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .map(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.'))
+                    .unwrap_or(false),
+                None => pattern == "*" || pattern == host,
+            }
+        })
+    }
+
+    /// Check that the request was made from a page hosted by this application, by comparing
+    /// the `Origin` header (or the `Referer` header if it's missing) to the `Host` header.
+    ///
+    /// Returns `None` if the check can't be performed because the headers are missing. This complements
+    /// CSRF token validation, since browsers don't allow pages to forge these headers.
+    pub fn same_origin(&self) -> Option<bool> {
+        let host = self.authority()?.to_lowercase();
+
+        if self.header("origin").is_some() {
+            return Some(
+                self.origin()
+                    .map(|origin| origin.authority() == host)
+                    .unwrap_or(false),
+            );
+        }
+
+        self.header("referer")?;
+        Some(
+            self.referrer()
+                .map(|referrer| referrer.authority() == host)
+                .unwrap_or(false),
+        )
+    }
+
+    /// Get an HTTP/2 pseudo-header, i.e. `:method`, `:path`, `:scheme` or `:authority`,
+    /// regardless of the HTTP version used by the client.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let path = request.pseudo_header(":path"); // e.g. Some("/users?id=5")
+    /// ```
+    pub fn pseudo_header(&self, name: &str) -> Option<String> {
+        match name {
+            ":method" => Some(self.method.to_string()),
+            ":path" => Some(self.path.to_string()),
+            ":scheme" => Some(self.scheme()),
+            ":authority" => self.authority().cloned(),
+            _ => None,
+        }
+    }
+
+    /// Should the connection be kept open after this request?
     ///
-    /// This only makes sense for for HTTP/1.1.
+    /// HTTP/1.1 connections are kept open unless the client sends `Connection: close`.
+    /// Older versions have to ask for it with `Connection: keep-alive`.
     /// HTTP/2 connections are keep-alive by design.
     pub fn keep_alive(&self) -> bool {
-        self.http2()
-            || self
-                .headers
-                .get("connection")
-                // Requests can contain other attributes.
-                // We just case about keep-alive.
-                .map(|s| s.to_lowercase().contains("keep-alive"))
-                .unwrap_or(false)
+        if self.http2() {
+            return true;
+        }
+
+        // Requests can contain other attributes.
+        // We just care about keep-alive and close.
+        let connection = self.headers.get("connection").map(|s| s.to_lowercase());
+
+        match connection {
+            Some(ref connection) if connection.contains("close") => false,
+            Some(ref connection) if connection.contains("keep-alive") => true,
+            _ => match self.version {
+                Version::Http1 | Version::Http2 => true,
+                Version::Http10 | Version::Unknown => false,
+            },
+        }
     }
 
     /// Read a line from the stream, parsing out \r\n.
-    async fn read_line(
+    ///
+    /// Some clients terminate lines with a bare \n, which is accepted as well.
+    /// A \r that isn't immediately followed by \n is an error, and so is
+    /// a stream that closes in the middle of a line or a line longer
+    /// than `max_length` bytes.
+    pub(crate) async fn read_line(
         mut stream: impl AsyncRead + Unpin,
-        mut bytes_remaining: usize,
+        max_length: usize,
     ) -> Result<String, std::io::Error> {
         let mut buf = Vec::new();
-        let (mut cr, mut lf) = (false, false);
+        let mut cr = false;
+        let mut bytes_remaining = max_length;
 
-        while bytes_remaining > 0 {
-            // `stream` should be buffered.
-            let b = stream.read_u8().await?;
-            bytes_remaining -= 1;
+        loop {
+            if bytes_remaining == 0 {
+                return Err(std::io::Error::other(Error::MalformedRequest(
+                    "line too long",
+                )));
+            }
 
-            if b == '\r' as u8 {
-                cr = true;
-                if lf {
+            // `stream` should be buffered.
+            let b = match stream.read_u8().await {
+                Ok(b) => b,
+                Err(err)
+                    if err.kind() == std::io::ErrorKind::UnexpectedEof
+                        && (!buf.is_empty() || cr) =>
+                {
                     return Err(std::io::Error::other(Error::MalformedRequest(
-                        "nl before cr",
+                        "line not terminated",
                     )));
                 }
-            } else if b == '\n' as u8 {
-                lf = true;
-            } else {
-                buf.push(b);
-            }
+                Err(err) => return Err(err),
+            };
+            bytes_remaining -= 1;
 
-            if cr && lf {
+            if b == b'\n' {
                 break;
+            } else if cr {
+                return Err(std::io::Error::other(Error::MalformedRequest(
+                    "cr without nl",
+                )));
+            } else if b == b'\r' {
+                cr = true;
+            } else {
+                buf.push(b);
             }
         }
 
@@ -308,6 +822,12 @@ impl Head {
     pub fn replace_path(&mut self, path: Path) {
         self.path = path.clone();
     }
+
+    /// Change the method of this request. Like [`Head::replace_path`], this is used
+    /// to rewrite requests before they are routed.
+    pub fn replace_method(&mut self, method: Method) {
+        self.method = method;
+    }
 }
 
 #[cfg(test)]
@@ -319,6 +839,200 @@ mod test {
         let line = b"Content-Type: application/json\r\n";
         let result = Head::read_line(&line[..], 4096).await.expect("read_line");
         assert_eq!(result, "Content-Type: application/json");
+
+        let line = b"Content-Type: application/json\nAccept: */*\n";
+        let result = Head::read_line(&line[..], 4096).await.expect("read_line");
+        assert_eq!(result, "Content-Type: application/json");
+    }
+
+    #[tokio::test]
+    async fn test_read_line_eof() {
+        let err = Head::read_line(&b"Content-Type: application/json"[..], 4096)
+            .await
+            .expect_err("read_line");
+        assert!(format!("{:?}", err).contains("line not terminated"));
+
+        // Stream closed between lines.
+        let err = Head::read_line(&b""[..], 4096)
+            .await
+            .expect_err("read_line");
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        let err = Head::read("GET / HTTP/1.1\r\nAccept: */*".as_bytes())
+            .await
+            .expect_err("head");
+        assert!(format!("{:?}", err).contains("line not terminated"));
+    }
+
+    #[tokio::test]
+    async fn test_line_too_long() {
+        let line = b"Content-Type: application/json\r\n";
+        let err = Head::read_line(&line[..], 16).await.expect_err("read_line");
+        assert!(format!("{:?}", err).contains("line too long"));
+
+        // Terminator counts towards the limit.
+        assert!(Head::read_line(&line[..], line.len()).await.is_ok());
+
+        let path = "a".repeat(get_config().general.max_line_length);
+        let request = format!("GET /{} HTTP/1.1\r\n\r\n", path);
+        let err = Head::read(request.as_bytes()).await.expect_err("head");
+        assert!(format!("{:?}", err).contains("line too long"));
+    }
+
+    #[tokio::test]
+    async fn test_encoded_path() {
+        let head = Head::read("GET /users/%6A?name=%6A HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .expect("head");
+        assert_eq!(head.path().path(), "/users/j");
+
+        let route = Path::parse("/users/:name")
+            .unwrap()
+            .with_regex(crate::http::path::PathType::Route)
+            .unwrap();
+        assert!(route.regex().is_match(head.path().path()));
+        assert_eq!(
+            route.params().parameter(head.path().path(), "name"),
+            Some("j")
+        );
+
+        let err = Head::read("GET /users/a%2Fb HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .expect_err("encoded slash");
+        assert!(format!("{:?}", err).contains("encoded slash"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_headers() {
+        let body = "GET / HTTP/1.1\r\n".to_owned()
+            + "Accept: text/html\r\n"
+            + "Via: 1.1 proxy-a\r\n"
+            + "accept: application/json\r\n"
+            + "Cookie: a=1\r\n"
+            + "Via: 1.1 proxy-b\r\n"
+            + "Cookie: b=2\r\n"
+            + "\r\n";
+        let head = Head::read(body.as_bytes()).await.expect("head");
+
+        assert_eq!(head.header("accept"), Some(&String::from("text/html")));
+        assert_eq!(
+            head.headers_all("accept"),
+            ["text/html", "application/json"]
+        );
+        assert_eq!(head.headers_all("via"), ["1.1 proxy-a", "1.1 proxy-b"]);
+        assert!(head.headers_all("forwarded").is_empty());
+
+        let cookies = head.cookies();
+        assert_eq!(cookies.get("a").map(|c| c.value()), Some("1"));
+        assert_eq!(cookies.get("b").map(|c| c.value()), Some("2"));
+
+        // Repeating framing and routing headers is fine if they agree.
+        let body = "POST / HTTP/1.1\r\nHost: a.com\r\nContent-Length: 5\r\nHost: a.com\r\nContent-Length: 5\r\n\r\n";
+        let head = Head::read(body.as_bytes()).await.expect("head");
+        assert_eq!(head.content_length(), Some(5));
+        assert_eq!(head.header("host"), Some(&String::from("a.com")));
+
+        for (body, error) in [
+            (
+                "POST / HTTP/1.1\r\nContent-Length: 5\r\nContent-Length: 50\r\n\r\n",
+                "content length",
+            ),
+            (
+                "GET / HTTP/1.1\r\nHost: a.com\r\nHost: b.com\r\n\r\n",
+                "host",
+            ),
+        ] {
+            let err = Head::read(body.as_bytes()).await.expect_err("conflict");
+            assert!(matches!(err, Error::MalformedRequest(e) if e == error));
+            assert_eq!(err.code(), 400);
+        }
+
+        // Transfer codings are combined from all headers.
+        let body =
+            "POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let head = Head::read(body.as_bytes()).await.expect("head");
+        assert!(head.chunked());
+
+        let body =
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nTransfer-Encoding: gzip\r\n\r\n";
+        let head = Head::read(body.as_bytes()).await.expect("head");
+        assert!(!head.chunked());
+    }
+
+    #[tokio::test]
+    async fn test_header_value_with_colons() {
+        let body = "GET / HTTP/1.1\r\n".to_owned()
+            + "Host: example.com:8000\r\n"
+            + "Date: Mon, 01 Jan 2024 00:00:00 GMT\r\n"
+            + "Referer:https://example.com:8443/a:b\r\n"
+            + "\r\n";
+        let head = Head::read(body.as_bytes()).await.expect("head");
+
+        assert_eq!(head.header("host"), Some(&String::from("example.com:8000")));
+        assert_eq!(
+            head.header("date"),
+            Some(&String::from("Mon, 01 Jan 2024 00:00:00 GMT"))
+        );
+        assert_eq!(
+            head.header("referer"),
+            Some(&String::from("https://example.com:8443/a:b"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_version() {
+        for (version, expected) in [
+            ("HTTP/1.1", Version::Http1),
+            ("HTTP/1.0", Version::Http10),
+            ("HTTP/2", Version::Http2),
+            ("HTTP/2.0", Version::Http2),
+            ("HTTP/3", Version::Unknown),
+            ("http/1.1", Version::Unknown),
+        ] {
+            assert_eq!(Version::try_from(version.to_string()).unwrap(), expected);
+
+            let request = format!("GET / {}\r\n\r\n", version);
+            let head = Head::read(request.as_bytes()).await;
+
+            if expected.http1() || expected == Version::Http10 {
+                let head = head.expect("head");
+                assert_eq!(head.version(), &expected, "{}", version);
+                assert_eq!(head.http1(), expected == Version::Http1);
+            } else {
+                // Only HTTP/1 can be read from the stream.
+                let err = head.expect_err(version);
+                assert!(matches!(err, Error::VersionNotSupported(_)), "{}", version);
+                assert_eq!(err.code(), 505);
+            }
+        }
+
+        assert_eq!(Version::Http10.to_string(), "HTTP/1.0");
+
+        // Only HTTP/1.1 and later keep the connection open by default.
+        let head = Head::read("GET / HTTP/1.0\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert!(!head.keep_alive());
+        let head = Head::read("GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert!(head.keep_alive());
+    }
+
+    #[tokio::test]
+    async fn test_bare_lf() {
+        for nl in ["\r\n", "\n"] {
+            let body = format!(
+                "GET /?hello=world HTTP/1.1{nl}Content-Type: application/json{nl}Accept: */*{nl}{nl}"
+            );
+            let head = Head::read(body.as_bytes()).await.expect("head");
+            assert_eq!(head.path().path(), "/");
+            assert_eq!(head.header("accept"), Some(&String::from("*/*")));
+            assert_eq!(
+                head.header("content-type"),
+                Some(&String::from("application/json"))
+            );
+        }
     }
 
     #[tokio::test]
@@ -356,14 +1070,290 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_accept_language() {
+        let request = "GET / HTTP/1.1\r\nAccept-Language: fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5\r\n\r\n";
+        let head = Head::read(request.as_bytes()).await.unwrap();
+
+        assert_eq!(
+            head.accept_languages(),
+            vec![
+                ("fr-ch".to_string(), 1.0),
+                ("fr".to_string(), 0.9),
+                ("en".to_string(), 0.8),
+                ("de".to_string(), 0.7),
+                ("*".to_string(), 0.5),
+            ]
+        );
+        assert_eq!(head.preferred_language(&["en", "fr-CH"]), Some("fr-CH"));
+        assert_eq!(head.preferred_language(&["en", "fr"]), Some("fr"));
+        assert_eq!(head.preferred_language(&["de", "en"]), Some("en"));
+        assert_eq!(head.preferred_language(&["es"]), Some("es"));
+
+        let request = "GET / HTTP/1.1\r\nAccept-Language: en-US;q=0.5, es;q=0\r\n\r\n";
+        let head = Head::read(request.as_bytes()).await.unwrap();
+        assert_eq!(head.preferred_language(&["es", "en"]), Some("en"));
+        assert_eq!(head.preferred_language(&["es", "ja"]), None);
+
+        let request = "GET / HTTP/1.1\r\nAccept-Language: fr;q=0, *\r\n\r\n";
+        let head = Head::read(request.as_bytes()).await.unwrap();
+        assert_eq!(head.preferred_language(&["fr", "en"]), Some("en"));
+        assert_eq!(head.preferred_language(&["fr-CH", "de"]), Some("de"));
+        assert_eq!(head.preferred_language(&["fr"]), None);
+
+        let head = Head::read("GET / HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert!(head.accept_languages().is_empty());
+        assert_eq!(head.preferred_language(&["en"]), None);
+    }
+
+    #[tokio::test]
+    async fn test_read_lenient() {
+        let request = "GET\r\nHost\r\nAccept: */*\r\nBroken header\r\n\r\n";
+        let report = Head::read_lenient(request.as_bytes()).await.unwrap();
+
+        assert!(!report.is_ok());
+        let problems = report
+            .problems
+            .iter()
+            .map(|problem| (problem.line, problem.error.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            vec![
+                (1, "malformed request: path".to_string()),
+                (1, "malformed request: version".to_string()),
+                (2, "malformed request: header value".to_string()),
+                (4, "malformed request: header value".to_string()),
+            ]
+        );
+        assert_eq!(report.head.method(), &Method::Get);
+        assert_eq!(report.head.header("accept"), Some(&"*/*".to_string()));
+
+        // Fail fast reports the first problem only.
+        let err = Head::read(request.as_bytes()).await.unwrap_err();
+        assert!(matches!(err, Error::MalformedRequest("path")));
+
+        let report = Head::read_lenient("GET / HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert!(report.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_request_line() {
+        for (request, error) in [
+            ("GET /\r\n\r\n", "version"),
+            ("GET / \r\n\r\n", "version"),
+            (" / HTTP/1.1\r\n\r\n", "method"),
+            ("G(T / HTTP/1.1\r\n\r\n", "method"),
+            ("GET  / HTTP/1.1\r\n\r\n", "request line"),
+            ("GET /hello world HTTP/1.1\r\n\r\n", "request line"),
+            ("GET / HTTP/1.1 extra\r\n\r\n", "request line"),
+        ] {
+            let err = Head::read(request.as_bytes()).await.unwrap_err();
+            assert!(
+                matches!(err, Error::MalformedRequest(e) if e == error),
+                "{:?}: {:?}",
+                request,
+                err
+            );
+        }
+
+        // Empty lines before the request are ignored.
+        let report = Head::read_lenient("\r\n\r\nGET / HTTP/1.1\r\nBroken\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(report.head.path().path(), "/");
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(report.problems[0].line, 4);
+
+        // But not forever.
+        let err = Head::read("\r\n".repeat(MAX_EMPTY_LINES + 1).as_bytes())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::MalformedRequest("empty request line")));
+
+        // Connection closed without sending a request.
+        let err = Head::read("".as_bytes()).await.unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+    }
+
+    #[tokio::test]
+    async fn test_referrer_origin() {
+        let mut head = Head::read("POST /users HTTP/1.1\r\nHost: example.com\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+
+        // Missing.
+        assert_eq!(head.referrer(), None);
+        assert_eq!(head.origin(), None);
+        assert_eq!(head.same_origin(), None);
+
+        // Valid.
+        head.headers_mut()
+            .insert("referer", "https://example.com/users/new?step=2");
+        let referrer = head.referrer().unwrap();
+        assert_eq!(referrer.host(), "example.com");
+        assert_eq!(referrer.path(), "/users/new?step=2");
+        assert_eq!(head.same_origin(), Some(true));
+
+        head.headers_mut().insert("origin", "https://evil.com");
+        assert_eq!(head.origin().unwrap().origin(), "https://evil.com");
+        assert_eq!(head.same_origin(), Some(false));
+
+        // Malformed.
+        for origin in ["null", "evil.com", "https://evil.com/path", "https://"] {
+            head.headers_mut().insert("origin", origin);
+            assert_eq!(head.origin(), None);
+            assert_eq!(head.same_origin(), Some(false));
+        }
+
+        head.headers_mut().insert("referer", "not a url");
+        assert_eq!(head.referrer(), None);
+    }
+
+    #[tokio::test]
+    async fn test_pseudo_headers() {
+        let request = "POST /users?id=5 HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let mut head = Head::read(request.as_bytes()).await.unwrap();
+
+        assert_eq!(head.pseudo_header(":method"), Some("POST".into()));
+        assert_eq!(head.pseudo_header(":path"), Some("/users?id=5".into()));
+        assert_eq!(head.pseudo_header(":scheme"), Some("http".into()));
+        assert_eq!(head.pseudo_header(":authority"), Some("example.com".into()));
+        assert_eq!(head.pseudo_header(":status"), None);
+
+        head.headers_mut().insert("x-forwarded-proto", "HTTPS");
+        assert_eq!(head.scheme(), "https");
+
+        // HTTP/2 pseudo-headers take precedence.
+        head.headers_mut().insert(":authority", "example.org");
+        assert_eq!(head.authority(), Some(&"example.org".to_string()));
+
+        let head = Head::read("GET / HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(head.authority(), None);
+    }
+
+    #[test]
+    fn test_host_allowed() {
+        let head = |host: &str| {
+            let mut head = Head::default();
+            head.headers_mut().insert("host", host);
+            head
+        };
+
+        let allowed = ["example.com", "*.example.org"];
+
+        let allowed_host = head("Example.com.:8000");
+        assert_eq!(allowed_host.host(), Some("example.com".to_string()));
+        assert!(allowed_host.host_allowed(&allowed));
+
+        let disallowed = head("evil.com");
+        assert!(!disallowed.host_allowed(&allowed));
+        assert!(!head("example.com.evil.com").host_allowed(&allowed));
+        assert!(!head("example.com@evil.com").host_allowed(&allowed));
+        assert!(!head("example.com:80/evil").host_allowed(&allowed));
+
+        // Wildcards only match subdomains.
+        assert!(head("api.example.org").host_allowed(&allowed));
+        assert!(head("a.b.example.org:443").host_allowed(&allowed));
+        assert!(!head("example.org").host_allowed(&allowed));
+        assert!(!head("evilexample.org").host_allowed(&allowed));
+
+        // IPv6
+        let ipv6 = head("[::1]:8000");
+        assert_eq!(ipv6.host(), Some("[::1]".to_string()));
+        assert!(ipv6.host_allowed(&["[::1]"]));
+
+        // Missing host header.
+        let missing = Head::default();
+        assert!(!missing.host_allowed(&allowed));
+        let all: [&str; 0] = [];
+        assert!(missing.host_allowed(&all));
+        assert!(disallowed.host_allowed(&["*"]));
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive() {
+        for (request, keep_alive) in [
+            ("GET / HTTP/1.1\r\n\r\n", true),
+            ("GET / HTTP/1.1\r\nConnection: close\r\n\r\n", false),
+            ("GET / HTTP/1.1\r\nConnection: Close\r\n\r\n", false),
+            ("GET / HTTP/1.0\r\n\r\n", false),
+            ("GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n", true),
+        ] {
+            let head = Head::read(request.as_bytes()).await.unwrap();
+            assert_eq!(head.keep_alive(), keep_alive, "{}", request);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_if_match() {
+        let current = ETag::strong("v2");
+
+        let head = Head::read("PUT /orders/1 HTTP/1.1\r\nIf-Match: \"v2\"\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert!(head.check_if_match(&current).is_ok());
+
+        let head = Head::read("PUT /orders/1 HTTP/1.1\r\nIf-Match: \"v1\"\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        let err = head.check_if_match(&current).unwrap_err();
+        assert_eq!(err.code(), 412);
+
+        let head = Head::read("PUT /orders/1 HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert!(head.check_if_match(&current).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_if_range() {
+        let head = Head::read("GET /file HTTP/1.1\r\nIf-Range: \"v1\"\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(
+            head.if_range().unwrap(),
+            Some(IfRange::ETag(ETag::strong("v1")))
+        );
+
+        let mut head = Head::read("GET /file HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        head.headers_mut()
+            .insert("if-range", "Wed, 21 Oct 2015 07:28:00 GMT");
+        let date = time::OffsetDateTime::from_unix_timestamp(1445412480).unwrap();
+        assert_eq!(head.if_range().unwrap(), Some(IfRange::Date(date)));
+
+        let head = Head::read("GET /file HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(head.if_range().unwrap(), None);
+    }
+
     #[tokio::test]
     async fn test_nl_before_cr() {
-        let err = Head::read("GET / HTTP/1.1\n\r".as_bytes())
+        let err = Head::read("GET / HTTP/1.1\n\rAccept: */*\r\n\r\n".as_bytes())
             .await
             .expect_err("parser should throw err");
 
         // Hacky way to see what's in the error.
         let msg = format!("{:?}", err);
-        assert!(msg.contains("nl before cr"));
+        assert!(msg.contains("cr without nl"));
+    }
+
+    #[tokio::test]
+    async fn test_lone_cr() {
+        let err = Head::read("GET / HTTP/1.1\r\nAccept: a\rb\r\n\r\n".as_bytes())
+            .await
+            .expect_err("parser should throw err");
+
+        let msg = format!("{:?}", err);
+        assert!(msg.contains("cr without nl"));
     }
 }
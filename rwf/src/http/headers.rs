@@ -1,10 +1,13 @@
 //! HTTP request headers.
-use std::collections::{hash_map::Iter, HashMap};
+use std::collections::HashMap;
 
 /// HTTP headers.
+///
+/// A header can be sent multiple times, e.g. `Accept` or `Via`, so all values received
+/// for a name are kept.
 #[derive(Clone, Debug, Default)]
 pub struct Headers {
-    headers: HashMap<String, String>,
+    headers: HashMap<String, Vec<String>>,
 }
 
 impl Headers {
@@ -15,7 +18,8 @@ impl Headers {
         }
     }
 
-    /// Add a header to the headers storage. The name will be converted to lowercase.
+    /// Add a header to the headers storage, replacing any values already set
+    /// for this name. The name will be converted to lowercase.
     ///
     /// # Example
     ///
@@ -24,16 +28,29 @@ impl Headers {
     /// let mut headers = Headers::new();
     /// headers.insert("x-my-header", "134");
     /// ```
+    pub fn insert(&mut self, name: impl ToString, value: impl ToString) {
+        self.headers
+            .insert(name.to_string().to_lowercase(), vec![value.to_string()]);
+    }
+
+    /// Add another value for a header, keeping the values already set for this name.
     ///
-    /// #### Implementation note
+    /// # Example
     ///
-    /// This implementation doesn't support adding
-    /// multiple headers with the same name. This is
-    /// only needed for the `Set-Cookie` header which
-    /// is handled separately.
-    pub fn insert(&mut self, name: impl ToString, value: impl ToString) {
+    /// ```
+    /// # use rwf::http::Headers;
+    /// let mut headers = Headers::new();
+    /// headers.append("via", "1.1 proxy-a");
+    /// headers.append("Via", "1.1 proxy-b");
+    ///
+    /// assert_eq!(headers.get("via"), Some(&String::from("1.1 proxy-a")));
+    /// assert_eq!(headers.get_all("via"), ["1.1 proxy-a", "1.1 proxy-b"]);
+    /// ```
+    pub fn append(&mut self, name: impl ToString, value: impl ToString) {
         self.headers
-            .insert(name.to_string().to_lowercase(), value.to_string());
+            .entry(name.to_string().to_lowercase())
+            .or_default()
+            .push(value.to_string());
     }
 
     /// Get a header value by name. Case insensitive.
@@ -47,11 +64,21 @@ impl Headers {
     /// let header = headers.get("x-my-header");
     /// assert_eq!(header, Some(&String::from("134")));
     /// ```
+    ///
+    /// If the header was sent multiple times, the first value is returned.
     pub fn get(&self, name: &str) -> Option<&String> {
-        self.headers.get(&name.to_lowercase())
+        self.get_all(name).first()
+    }
+
+    /// Get all values of a header, in the order they were received. Case insensitive.
+    pub fn get_all(&self, name: &str) -> &[String] {
+        self.headers
+            .get(&name.to_lowercase())
+            .map(|values| values.as_slice())
+            .unwrap_or_default()
     }
 
-    /// Remove a header by name. Case insensitive.
+    /// Remove all values of a header by name, returning the first one. Case insensitive.
     ///
     /// # Example
     ///
@@ -61,7 +88,9 @@ impl Headers {
     /// headers.remove("x-my-header");
     /// ```
     pub fn remove(&mut self, name: &str) -> Option<String> {
-        self.headers.remove(&name.to_lowercase())
+        self.headers
+            .remove(&name.to_lowercase())
+            .and_then(|values| values.into_iter().next())
     }
 
     /// Remove all headers.
@@ -78,21 +107,28 @@ impl Headers {
         self.headers.clear();
     }
 
-    /// Convert headers into a [`HashMap`] keyed by header name.
+    /// Convert headers into a [`HashMap`] keyed by header name. Multiple values
+    /// of the same header are combined into one, separated by commas.
     pub fn into_raw(self) -> HashMap<String, String> {
         self.headers
+            .into_iter()
+            .map(|(name, values)| (name, values.join(", ")))
+            .collect()
     }
 
-    /// Get a borrowing interator to the headers.
-    pub fn iter(&self) -> Iter<String, String> {
-        self.headers.iter()
+    /// Get a borrowing interator to the headers. Headers sent multiple times
+    /// are returned once for each value.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.headers
+            .iter()
+            .flat_map(|(name, values)| values.iter().map(move |value| (name, value)))
     }
 
     /// Convert headers to bytes.
     /// Used to send headers to the client.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
-        for (name, value) in &self.headers {
+        for (name, value) in self.iter() {
             bytes.extend_from_slice(name.as_bytes());
             bytes.extend_from_slice(b": ");
             bytes.extend_from_slice(value.as_bytes());
@@ -104,6 +140,46 @@ impl Headers {
 
 impl From<HashMap<String, String>> for Headers {
     fn from(headers: HashMap<String, String>) -> Self {
-        Self { headers }
+        Self {
+            headers: headers
+                .into_iter()
+                .map(|(name, value)| (name, vec![value]))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_multiple_values() {
+        let mut headers = Headers::new();
+        headers.append("Accept", "text/html");
+        headers.append("accept", "application/json");
+        headers.insert("host", "example.com");
+
+        assert_eq!(headers.get("accept"), Some(&"text/html".to_string()));
+        assert_eq!(headers.get_all("ACCEPT"), ["text/html", "application/json"]);
+        assert_eq!(headers.get_all("host"), ["example.com"]);
+        assert!(headers.get_all("via").is_empty());
+        assert_eq!(headers.iter().count(), 3);
+
+        let bytes = String::from_utf8(headers.to_bytes()).unwrap();
+        assert!(bytes.contains("accept: text/html\r\n"));
+        assert!(bytes.contains("accept: application/json\r\n"));
+
+        assert_eq!(
+            headers.clone().into_raw().get("accept"),
+            Some(&"text/html, application/json".to_string())
+        );
+
+        // Insert replaces all values.
+        headers.insert("accept", "*/*");
+        assert_eq!(headers.get_all("accept"), ["*/*"]);
+
+        assert_eq!(headers.remove("accept"), Some("*/*".to_string()));
+        assert!(headers.get("accept").is_none());
     }
 }
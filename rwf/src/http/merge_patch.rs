@@ -0,0 +1,159 @@
+//! JSON merge patch, as described in [RFC 7386](https://www.rfc-editor.org/rfc/rfc7386).
+//!
+//! Unlike a partial JSON document, a merge patch distinguishes between fields
+//! which are absent (leave them alone) and fields set to `null` (delete them).
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+/// Change to a single field requested by a merge patch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Patch<T> {
+    /// Set the field to this value.
+    Set(T),
+    /// The field is `null`, delete it.
+    Delete,
+    /// The field is not in the patch, leave it unchanged.
+    Absent,
+}
+
+impl<T> Patch<T> {
+    /// The patch changes the field, i.e. it sets or deletes it.
+    pub fn present(&self) -> bool {
+        !matches!(self, Patch::Absent)
+    }
+
+    /// Convert into an [`Option`]: `None` for absent fields, `Some(None)` for deleted
+    /// fields and `Some(Some(value))` for fields with a new value.
+    pub fn into_option(self) -> Option<Option<T>> {
+        match self {
+            Patch::Set(value) => Some(Some(value)),
+            Patch::Delete => Some(None),
+            Patch::Absent => None,
+        }
+    }
+}
+
+/// JSON merge patch document, e.g. the body of a `PATCH` request
+/// with `Content-Type: application/merge-patch+json`.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::http::{MergePatch, merge_patch::Patch};
+/// let patch = MergePatch::new(serde_json::json!({"name": "Alice", "nickname": null}));
+///
+/// assert_eq!(patch.field::<String>("name").unwrap(), Patch::Set("Alice".into()));
+/// assert_eq!(patch.field::<String>("nickname").unwrap(), Patch::Delete);
+/// assert_eq!(patch.field::<String>("email").unwrap(), Patch::Absent);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergePatch {
+    patch: Value,
+}
+
+impl MergePatch {
+    /// Create a merge patch from a JSON document.
+    pub fn new(patch: Value) -> Self {
+        Self { patch }
+    }
+
+    /// The patch document.
+    pub fn value(&self) -> &Value {
+        &self.patch
+    }
+
+    /// Get the change requested for a top-level field.
+    pub fn get(&self, name: &str) -> Patch<&Value> {
+        match self.patch.get(name) {
+            Some(Value::Null) => Patch::Delete,
+            Some(value) => Patch::Set(value),
+            None => Patch::Absent,
+        }
+    }
+
+    /// Get the change requested for a top-level field, deserializing the new value.
+    pub fn field<T: DeserializeOwned>(&self, name: &str) -> Result<Patch<T>, serde_json::Error> {
+        Ok(match self.get(name) {
+            Patch::Set(value) => Patch::Set(T::deserialize(value)?),
+            Patch::Delete => Patch::Delete,
+            Patch::Absent => Patch::Absent,
+        })
+    }
+
+    /// Names of the top-level fields changed by the patch.
+    pub fn fields(&self) -> Vec<&str> {
+        match self.patch {
+            Value::Object(ref map) => map.keys().map(|key| key.as_str()).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Apply the patch to a JSON document, following the RFC 7386 algorithm.
+    pub fn apply(&self, target: &mut Value) {
+        apply(target, &self.patch);
+    }
+}
+
+fn apply(target: &mut Value, patch: &Value) {
+    match patch {
+        Value::Object(fields) => {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+
+            let map = target.as_object_mut().unwrap();
+
+            for (name, value) in fields {
+                if value.is_null() {
+                    map.remove(name);
+                } else {
+                    apply(map.entry(name.as_str()).or_insert(Value::Null), value);
+                }
+            }
+        }
+
+        patch => *target = patch.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_patch() {
+        let patch = MergePatch::new(json!({
+            "title": "Hello",
+            "phone": null,
+            "author": {"given_name": "Alice", "family_name": null},
+        }));
+
+        assert_eq!(patch.get("title"), Patch::Set(&json!("Hello")));
+        assert_eq!(patch.get("phone"), Patch::Delete);
+        assert_eq!(patch.get("content"), Patch::Absent);
+        assert!(!patch.get("content").present());
+        assert_eq!(
+            patch.field::<String>("phone").unwrap().into_option(),
+            Some(None)
+        );
+        assert!(patch.field::<i64>("title").is_err());
+
+        let mut target = json!({
+            "title": "Goodbye!",
+            "phone": "555-1234",
+            "content": "Hello world",
+            "author": {"given_name": "John", "family_name": "Doe"},
+        });
+        patch.apply(&mut target);
+
+        assert_eq!(
+            target,
+            json!({
+                "title": "Hello",
+                "content": "Hello world",
+                "author": {"given_name": "Alice"},
+            })
+        );
+    }
+}
@@ -9,40 +9,50 @@
 //! You can put the Rwf application behind a load balancer (like nginx) that supports
 //! HTTP/2 to take advantage of its performance enhancements.
 #![allow(dead_code)]
+pub mod access_log;
 pub mod authorization;
 pub mod body;
 pub mod cookies;
 pub mod error;
+pub mod etag;
+pub mod extensions;
 pub mod form;
 pub mod form_data;
 pub mod handler;
 pub mod head;
 pub mod headers;
+pub mod merge_patch;
 pub mod path;
 pub mod request;
 pub mod response;
 pub mod router;
 pub mod server;
+pub mod session_store;
 pub mod url;
 pub mod websocket;
 
 #[cfg(feature = "wsgi")]
 pub mod wsgi;
 
+pub use access_log::AccessLog;
 pub use authorization::Authorization;
 pub use body::Body;
-pub use cookies::{Cookie, CookieBuilder, Cookies};
+pub use cookies::{Cookie, CookieBag, CookieBuilder, Cookies};
 pub use error::Error;
+pub use etag::{ETag, IfMatch, IfRange};
+pub use extensions::Extensions;
 pub use form::{Form, FromFormData};
-pub use form_data::FormData;
+pub use form_data::{FormData, FormError};
 pub use handler::Handler;
-pub use head::{Head, Method};
+pub use head::{Head, HeadProblem, HeadReport, Method, Version};
 pub use headers::Headers;
+pub use merge_patch::MergePatch;
 pub use path::{Params, Path, Query, ToParameter};
 pub use request::Request;
 pub use response::Response;
 pub use router::Router;
 pub use server::{Server, Stream};
+pub use session_store::{MemoryStore, SessionStore};
 pub use url::{urldecode, urlencode};
 pub use websocket::{Message, ToMessage};
 
@@ -3,9 +3,11 @@
 //! Paths are parsed for each incoming request and compared against
 //! a global regex to find a route handler.
 use super::Error;
+use crate::crypto::{sign, sign_validate};
 
 use std::fmt::Debug;
 use std::path::PathBuf;
+use time::OffsetDateTime;
 
 pub mod with_regex;
 pub use with_regex::{PathType, PathWithRegex};
@@ -19,6 +21,9 @@ pub use params::Params;
 pub mod query;
 pub use query::Query;
 
+const SIGNATURE: &str = "signature";
+const EXPIRES: &str = "expires";
+
 /// HTTP URL path.
 #[derive(Clone, Debug)]
 pub struct Path {
@@ -110,6 +115,133 @@ impl Path {
         Ok(Path { base, query })
     }
 
+    /// Percent-decode the path, e.g. `/users/%6A` becomes `/users/j`.
+    /// The query is left as-is since it's decoded separately.
+    ///
+    /// Encoded slashes (`%2F`) are ambiguous, since they could be used to traverse
+    /// paths once decoded. They are rejected, unless `allow_encoded_slashes` is set,
+    /// in which case they are kept encoded and don't separate path segments.
+    ///
+    /// Control characters, e.g. `%00` or `%0A`, have no place in a path and are rejected as well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Path;
+    /// let path = Path::parse("/users/j%C3%BCrgen").unwrap();
+    /// assert_eq!(path.decode(false).unwrap().path(), "/users/jürgen");
+    ///
+    /// let path = Path::parse("/files/a%2Fb").unwrap();
+    /// assert!(path.decode(false).is_err());
+    /// assert_eq!(path.decode(true).unwrap().path(), "/files/a%2Fb");
+    ///
+    /// let path = Path::parse("/files/a%00.txt").unwrap();
+    /// assert!(path.decode(true).is_err());
+    /// ```
+    pub fn decode(&self, allow_encoded_slashes: bool) -> Result<Path, Error> {
+        let bytes = self.base.as_bytes();
+        if bytes.iter().any(|b| b.is_ascii_control()) {
+            return Err(Error::MalformedRequest("control character in path"));
+        }
+
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+            match (bytes[i], hex) {
+                (b'%', Some(b'/')) if allow_encoded_slashes => {
+                    decoded.extend_from_slice(&bytes[i..i + 3]);
+                    i += 3;
+                }
+
+                (b'%', Some(b'/')) => return Err(Error::MalformedRequest("encoded slash in path")),
+
+                (b'%', Some(byte)) if byte.is_ascii_control() => {
+                    return Err(Error::MalformedRequest("control character in path"))
+                }
+
+                (b'%', Some(byte)) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+
+                (byte, _) => {
+                    decoded.push(byte);
+                    i += 1;
+                }
+            }
+        }
+
+        let base =
+            String::from_utf8(decoded).map_err(|_| Error::MalformedRequest("path encoding"))?;
+
+        Ok(Self::from_parts(&base, &self.query))
+    }
+
+    /// Sign this path and its query, e.g. for unsubscribe or confirmation links.
+    /// The link is valid until `expires_at`. The `expires` and `signature` query
+    /// parameters are added to the path. See [`crate::crypto::sign`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Path;
+    /// # use time::{Duration, OffsetDateTime};
+    /// let path = Path::parse("/unsubscribe?user=5").unwrap();
+    /// let expires_at = OffsetDateTime::now_utc() + Duration::days(7);
+    /// let signed = path.sign(expires_at).unwrap();
+    ///
+    /// assert!(signed.verify_signature());
+    /// ```
+    pub fn sign(&self, expires_at: OffsetDateTime) -> Result<Path, Error> {
+        let mut path = self.clone();
+        path.query.remove(SIGNATURE);
+        path.query
+            .insert(EXPIRES.into(), expires_at.unix_timestamp().to_string());
+
+        // Requests are decoded before they get to the application.
+        let signature = sign(path.decode(true)?.signed_part().as_bytes());
+        path.query.insert(SIGNATURE.into(), signature);
+
+        Ok(path)
+    }
+
+    /// Check that the path was signed with [`Path::sign`] and the signature hasn't expired.
+    /// The path is expected to be already decoded, as it is when received by the server.
+    pub fn verify_signature(&self) -> bool {
+        let signature = match self.query.get::<String>(SIGNATURE) {
+            Some(signature) => signature,
+            None => return false,
+        };
+
+        let expires_at = match self
+            .query
+            .get::<i64>(EXPIRES)
+            .and_then(|ts| OffsetDateTime::from_unix_timestamp(ts).ok())
+        {
+            Some(expires_at) => expires_at,
+            None => return false,
+        };
+
+        if expires_at <= OffsetDateTime::now_utc() {
+            return false;
+        }
+
+        let mut path = self.clone();
+        path.query.remove(SIGNATURE);
+
+        sign_validate(path.signed_part().as_bytes(), &signature)
+    }
+
+    fn signed_part(&self) -> String {
+        format!("{}?{}", self.base, self.query)
+    }
+
     /// Convert path to a path that can be read from the file system.
     pub fn to_std(&self) -> PathBuf {
         std::path::Path::new(&self.base).to_owned()
@@ -176,6 +308,83 @@ mod test {
         assert!(regex.find("/api/orders/hello/world").is_some());
     }
 
+    #[test]
+    fn test_decode() {
+        let path = Path::parse("/users/%6A?name=j%20k")
+            .unwrap()
+            .decode(false)
+            .unwrap();
+        assert_eq!(path.path(), "/users/j");
+        assert_eq!(path.query().get::<String>("name"), Some("j k".into()));
+
+        let path = Path::parse("/users/100%25/%zz")
+            .unwrap()
+            .decode(false)
+            .unwrap();
+        assert_eq!(path.path(), "/users/100%/%zz");
+
+        let path = Path::parse("/files/..%2F..%2Fetc").unwrap();
+        assert!(path.decode(false).is_err());
+        assert_eq!(path.decode(true).unwrap().path(), "/files/..%2F..%2Fetc");
+
+        assert!(Path::parse("/%FF").unwrap().decode(false).is_err());
+
+        for path in ["/a%00.txt", "/a%0Ab", "/a%0d", "/a%1F", "/a%7F"] {
+            let err = Path::parse(path).unwrap().decode(true).unwrap_err();
+            assert!(matches!(
+                err,
+                Error::MalformedRequest("control character in path")
+            ));
+            assert_eq!(err.code(), 400);
+        }
+    }
+
+    #[test]
+    fn test_sign() {
+        let expires_at = OffsetDateTime::now_utc() + time::Duration::hours(1);
+        let path = Path::parse("/users/j%C3%BCrgen/unsubscribe?list=news")
+            .unwrap()
+            .sign(expires_at)
+            .unwrap();
+        assert!(path.query().contains_key("signature"));
+        assert_eq!(
+            path.query().get::<i64>("expires"),
+            Some(expires_at.unix_timestamp())
+        );
+
+        // As received by the server.
+        let valid = Path::parse(&path.to_string())
+            .unwrap()
+            .decode(false)
+            .unwrap();
+        assert!(valid.verify_signature());
+
+        let mut tampered = valid.clone();
+        tampered.query.insert("list".into(), "all".into());
+        assert!(!tampered.verify_signature());
+
+        let mut tampered = valid.clone();
+        tampered.base = "/users/bob/unsubscribe".into();
+        assert!(!tampered.verify_signature());
+
+        let mut unsigned = valid.clone();
+        unsigned.query.remove("signature");
+        assert!(!unsigned.verify_signature());
+
+        let expired = Path::parse("/unsubscribe?list=news")
+            .unwrap()
+            .sign(OffsetDateTime::now_utc() - time::Duration::seconds(1))
+            .unwrap();
+        assert!(!expired.verify_signature());
+
+        // Moving the expiration invalidates the signature.
+        let mut extended = expired.clone();
+        extended
+            .query
+            .insert("expires".into(), expires_at.unix_timestamp().to_string());
+        assert!(!extended.verify_signature());
+    }
+
     #[test]
     fn test_pop_base() {
         let path = Path::parse("/engine/users/1/engine").unwrap();
@@ -1,13 +1,16 @@
 //! Handles parsing the URL query.
 use std::collections::btree_map::{BTreeMap, IntoIter};
-use std::ops::{Deref, DerefMut};
+use std::ops::Deref;
 use std::str::FromStr;
 
+use crate::config::get_config;
 use crate::http::Error;
 use crate::http::{urldecode, urlencode};
 
 /// GET request query.
 ///
+/// All values of each parameter are kept, in the order they appear in the query.
+///
 /// # Example
 ///
 /// ```text
@@ -15,7 +18,8 @@ use crate::http::{urldecode, urlencode};
 /// ```
 #[derive(Debug, Clone)]
 pub struct Query {
-    query: BTreeMap<String, String>,
+    query: BTreeMap<String, Vec<String>>,
+    empty_as_none: bool,
 }
 
 impl Query {
@@ -23,9 +27,64 @@ impl Query {
     pub fn new() -> Self {
         Self {
             query: BTreeMap::new(),
+            empty_as_none: get_config().general.query_empty_as_none,
         }
     }
 
+    /// Treat parameters with empty values, e.g. `?status=`, as if they weren't set.
+    ///
+    /// Default is set by [`crate::config::General::query_empty_as_none`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Query;
+    /// let query = Query::parse("status=").empty_as_none(true);
+    /// assert_eq!(query.get::<String>("status"), None);
+    /// ```
+    pub fn empty_as_none(mut self, empty_as_none: bool) -> Self {
+        self.empty_as_none = empty_as_none;
+        self
+    }
+
+    // Last value of a parameter.
+    pub(crate) fn value(&self, name: &str) -> Option<&String> {
+        match self.query.get(name).and_then(|values| values.last()) {
+            Some(value) if self.empty_as_none && value.is_empty() => None,
+            value => value,
+        }
+    }
+
+    // All values of a parameter, in the order they appear in the query.
+    pub(crate) fn values(&self, name: &str) -> Vec<&String> {
+        self.query
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter(|value| !(self.empty_as_none && value.is_empty()))
+            .collect()
+    }
+
+    /// Set a query parameter, replacing all of its existing values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Query;
+    /// let mut query = Query::parse("tag=a&tag=b");
+    /// query.insert("tag".into(), "c".into());
+    ///
+    /// assert_eq!(query.to_string(), "tag=c");
+    /// ```
+    pub fn insert(&mut self, name: String, value: String) -> Option<Vec<String>> {
+        self.query.insert(name, vec![value])
+    }
+
+    /// Remove a query parameter and all of its values.
+    pub fn remove(&mut self, name: &str) -> Option<Vec<String>> {
+        self.query.remove(name)
+    }
+
     /// Parse query from a GET request.
     ///
     /// # Example
@@ -51,7 +110,7 @@ impl Query {
             let key = urldecode(&key_value.next().expect("path query key"));
             let value = urldecode(&key_value.next().unwrap_or(&"")); // ?key=&value=two
 
-            query.insert(key, value);
+            query.query.entry(key).or_default().push(value);
         }
 
         query
@@ -71,7 +130,7 @@ impl Query {
     /// );
     /// ```
     pub fn get<T: FromStr>(&self, name: &str) -> Option<T> {
-        match self.query.get(name) {
+        match self.value(name) {
             Some(value) => match urldecode(value).parse::<T>() {
                 Ok(value) => Some(value),
                 Err(_) => None,
@@ -81,6 +140,58 @@ impl Query {
         }
     }
 
+    /// Get a comma-separated query parameter value as a list, e.g. `?ids=1,2,3`.
+    /// Each item is converted to a Rust type. If any conversion fails, `None` is returned.
+    ///
+    /// Empty items, e.g. from `?ids=` or a trailing comma, are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Query;
+    /// let query = Query::parse("ids=1,2,3,");
+    /// assert_eq!(
+    ///     query.get_list::<i64>("ids"),
+    ///     Some(vec![1, 2, 3])
+    /// );
+    /// ```
+    pub fn get_list<T: FromStr>(&self, name: &str) -> Option<Vec<T>> {
+        self.value(name)?
+            .split(",")
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse::<T>().ok())
+            .collect()
+    }
+
+    /// Get all values of a parameter repeated in the query, e.g. `?tag=a&tag=b`.
+    /// Each value is converted to a Rust type. If any conversion fails, or the parameter
+    /// isn't set, `None` is returned.
+    ///
+    /// Other methods, e.g. [`Self::get`], return the last value only.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Query;
+    /// let query = Query::parse("tag=rust&tag=web");
+    /// assert_eq!(
+    ///     query.get_all::<String>("tag"),
+    ///     Some(vec!["rust".to_string(), "web".to_string()])
+    /// );
+    /// assert_eq!(query.get::<String>("tag"), Some("web".to_string()));
+    /// ```
+    pub fn get_all<T: FromStr>(&self, name: &str) -> Option<Vec<T>> {
+        if !self.query.contains_key(name) {
+            return None;
+        }
+
+        self.values(name)
+            .into_iter()
+            .map(|value| value.parse::<T>().ok())
+            .collect()
+    }
+
     /// Get a query parameter value. If it's not set, return an error.
     /// When used with the `?` operator, the controller will automatically
     /// return `400 - Bad Request`.
@@ -91,7 +202,8 @@ impl Query {
         }
     }
 
-    /// Convert the query to JSON representation.
+    /// Convert the query to JSON representation. Parameters repeated in the query
+    /// are set to their last value.
     ///
     /// # Example
     ///
@@ -106,10 +218,12 @@ impl Query {
     /// )
     /// ```
     pub fn to_json(&self) -> serde_json::Value {
-        serde_json::to_value(&self.query).unwrap_or(serde_json::Value::default())
+        serde_json::to_value(self.clone().into_iter().collect::<BTreeMap<_, _>>())
+            .unwrap_or(serde_json::Value::default())
     }
 
-    /// An owning iterator over the query.
+    /// An owning iterator over the query. Parameters repeated in the query
+    /// are returned with their last value.
     ///
     /// # Example
     ///
@@ -122,15 +236,44 @@ impl Query {
     /// }
     /// ```
     pub fn into_iter(self) -> IntoIter<String, String> {
-        self.query.into_iter()
+        self.query
+            .into_iter()
+            .filter_map(|(name, mut values)| values.pop().map(|value| (name, value)))
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+    }
+
+    /// Deserialize the query into a Rust struct. Values are converted to the type
+    /// of the struct field, the same way as form data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Query;
+    /// #[derive(serde::Deserialize)]
+    /// struct Search {
+    ///     page: i64,
+    ///     q: Option<String>,
+    /// }
+    ///
+    /// let search: Search = Query::parse("page=2").deserialize().unwrap();
+    /// assert_eq!(search.page, 2);
+    ///
+    /// let err = Query::parse("page=two").deserialize::<Search>().err().unwrap();
+    /// assert_eq!(err.code(), 422);
+    /// ```
+    pub fn deserialize<T: serde::de::DeserializeOwned>(self) -> Result<T, Error> {
+        crate::http::form_data::deserialize_entries(self.into_iter())
     }
 }
 
 impl std::fmt::Display for Query {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let mut params = vec![];
-        for (key, value) in &self.query {
-            params.push(format!("{}={}", urlencode(key), urlencode(value)));
+        for (key, values) in &self.query {
+            for value in values {
+                params.push(format!("{}={}", urlencode(key), urlencode(value)));
+            }
         }
 
         write!(f, "{}", params.join("&"))
@@ -138,15 +281,77 @@ impl std::fmt::Display for Query {
 }
 
 impl Deref for Query {
-    type Target = BTreeMap<String, String>;
+    type Target = BTreeMap<String, Vec<String>>;
 
     fn deref(&self) -> &Self::Target {
         &self.query
     }
 }
 
-impl DerefMut for Query {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.query
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_list() {
+        let query = Query::parse("ids=1,2,3&empty=&trailing=a,b,&bad=1,two");
+
+        assert_eq!(query.get_list::<i64>("ids"), Some(vec![1, 2, 3]));
+        assert_eq!(query.get_list::<i64>("empty"), Some(vec![]));
+        assert_eq!(
+            query.get_list::<String>("trailing"),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(query.get_list::<i64>("bad"), None);
+        assert_eq!(query.get_list::<i64>("missing"), None);
+    }
+
+    #[test]
+    fn test_empty_as_none() {
+        let query = Query::parse("status=&page=2").empty_as_none(false);
+        assert_eq!(query.get::<String>("status"), Some("".to_string()));
+        assert!(query.get_required::<String>("status").is_ok());
+
+        let query = query.empty_as_none(true);
+        assert_eq!(query.get::<String>("status"), None);
+        assert!(query.get_required::<String>("status").is_err());
+        assert_eq!(query.get_list::<i64>("status"), None);
+        assert_eq!(query.get::<i64>("page"), Some(2));
+    }
+
+    #[test]
+    fn test_get_all() {
+        let query = Query::parse("a=1&b=x&a=2&a=3").empty_as_none(false);
+
+        assert_eq!(query.get_all::<i64>("a"), Some(vec![1, 2, 3]));
+        assert_eq!(query.get::<i64>("a"), Some(3));
+        assert_eq!(query.get_all::<String>("b"), Some(vec!["x".to_string()]));
+        assert_eq!(query.get_all::<i64>("b"), None);
+        assert_eq!(query.get_all::<i64>("c"), None);
+
+        let query = Query::parse("a=1&a=&a=2");
+        assert_eq!(
+            query.clone().empty_as_none(true).get_all::<i64>("a"),
+            Some(vec![1, 2])
+        );
+        assert_eq!(query.empty_as_none(false).get_all::<i64>("a"), None);
+    }
+
+    #[test]
+    fn test_repeated_round_trip() {
+        let mut query = Query::parse("b=1&a=x&b=2&c=&b=3");
+        assert_eq!(query.to_string(), "a=x&b=1&b=2&b=3&c=");
+        assert_eq!(
+            Query::parse(&query.to_string()).to_string(),
+            query.to_string()
+        );
+
+        query.insert("b".into(), "4".into());
+        assert_eq!(query.get_all::<i64>("b"), Some(vec![4]));
+        assert_eq!(query.to_string(), "a=x&b=4&c=");
+
+        query.remove("b");
+        assert_eq!(query.get_all::<i64>("b"), None);
+        assert_eq!(query.to_string(), "a=x&c=");
     }
 }
@@ -6,6 +6,7 @@
 //! Parameters are denoted by the column-name notation, e.g. `:param1`.
 
 use super::{Error, Params, Path};
+use crate::config::get_config;
 use regex::Regex;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -33,11 +34,38 @@ pub enum PathType {
 impl PathWithRegex {
     /// Create the path-specified regex.
     pub(crate) fn new(path: Path, path_type: PathType) -> Result<Self, Error> {
+        let config = &get_config().general;
+        Self::build(
+            path,
+            path_type,
+            config.case_insensitive_routes,
+            config.strict_trailing_slash,
+        )
+    }
+
+    /// Create the path-specified regex.
+    ///
+    /// Unless `strict_trailing_slash` is set, route and REST paths match with
+    /// or without a trailing slash, whether it's declared in the path or not.
+    fn build(
+        path: Path,
+        path_type: PathType,
+        case_insensitive: bool,
+        strict_trailing_slash: bool,
+    ) -> Result<Self, Error> {
         let mut params = HashMap::new();
         // Parameter regex groups start at 1 since the first group
         // is the path base URL.
         let mut i = 1;
-        let mut iter = path.base().split("/");
+
+        let base = match path_type {
+            PathType::Route | PathType::Rest if !strict_trailing_slash && path.base() != "/" => {
+                path.base().trim_end_matches("/")
+            }
+            _ => path.base(),
+        };
+
+        let mut iter = base.split("/");
         let mut regex = Vec::new();
         while let Some(part) = iter.next() {
             let re = if part.starts_with(":") {
@@ -52,8 +80,10 @@ impl PathWithRegex {
             regex.push(re);
         }
         let regex =
+            // Optional case-insensitive matching
+            if case_insensitive { "(?i)" } else { "" }.to_string() +
             // Start of the URL
-            "^".to_string() +
+            "^" +
             // URL parts joined by '/'
             &regex.join(r#"\/"#) +
 
@@ -69,8 +99,8 @@ impl PathWithRegex {
 
             +
 
-            // Last slash is optional
-            if path.base().ends_with("/") { "$" } else { r#"\/?$"# };
+            // Last slash is optional, unless routes are strict
+            if base.ends_with("/") || (strict_trailing_slash && path_type != PathType::Wildcard) { "$" } else { r#"\/?$"# };
 
         // :id parameter
         if path_type == PathType::Rest {
@@ -148,4 +178,50 @@ mod test {
         let name = params.parameter(url, "name");
         assert_eq!(name, Some("hello-world"));
     }
+
+    #[test]
+    fn test_case_insensitive() {
+        let path = Path::parse("/Users/:id/profile").unwrap();
+
+        let regex = PathWithRegex::build(path.clone(), PathType::Route, false, false).unwrap();
+        assert!(regex.regex().is_match("/Users/5/profile"));
+        assert!(!regex.regex().is_match("/users/5/profile"));
+
+        let regex = PathWithRegex::build(path, PathType::Route, true, false).unwrap();
+        for url in [
+            "/users/AbC/profile",
+            "/USERS/AbC/PROFILE/",
+            "/Users/AbC/profile",
+        ] {
+            assert!(regex.regex().is_match(url), "{}", url);
+            // Parameters keep their case.
+            assert_eq!(regex.params().parameter(url, "id"), Some("AbC"));
+        }
+        assert!(!regex.regex().is_match("/users/5/profiles"));
+    }
+
+    #[test]
+    fn test_trailing_slash() {
+        for route in ["/users", "/users/"] {
+            let path = Path::parse(route).unwrap();
+
+            let regex = PathWithRegex::build(path.clone(), PathType::Route, false, false).unwrap();
+            assert!(regex.regex().is_match("/users"), "{}", route);
+            assert!(regex.regex().is_match("/users/"), "{}", route);
+
+            let regex = PathWithRegex::build(path.clone(), PathType::Rest, false, false).unwrap();
+            assert_eq!(regex.params().parameter("/users/5/", "id"), Some("5"));
+            assert_eq!(regex.params().parameter("/users/5", "id"), Some("5"));
+
+            // Strict paths must match exactly.
+            let regex = PathWithRegex::build(path, PathType::Route, false, true).unwrap();
+            assert_eq!(regex.regex().is_match("/users"), route == "/users");
+            assert_eq!(regex.regex().is_match("/users/"), route == "/users/");
+        }
+
+        let root =
+            PathWithRegex::build(Path::parse("/").unwrap(), PathType::Route, false, false).unwrap();
+        assert!(root.regex().is_match("/"));
+        assert!(!root.regex().is_match("/users"));
+    }
 }
@@ -3,14 +3,20 @@ use std::marker::Unpin;
 use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Duration;
 use std::{collections::HashMap, fmt::Debug};
 
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
 use serde_json::{Deserializer, Value};
 use time::OffsetDateTime;
 use tokio::io::{AsyncRead, AsyncReadExt};
+use uuid::Uuid;
 
-use super::{Cookies, Error, FormData, FromFormData, Head, Params, Response, ToParameter};
+use super::session_store::{get_session_store, load_session};
+use super::{
+    urldecode, Cookies, Error, Extensions, FormData, FromFormData, Head, MergePatch, Method,
+    Params, Path, Response, ToParameter,
+};
 use crate::prelude::ToConnectionRequest;
 use crate::{
     config::get_config,
@@ -26,10 +32,12 @@ pub struct Request {
     session: Session,
     inner: Arc<Inner>,
     params: Option<Arc<Params>>,
+    extensions: Extensions,
     received_at: OffsetDateTime,
     // Don't check for valid CSRF token.
     skip_csrf: bool,
     renew_session: bool,
+    tls: bool,
 }
 
 impl Default for Request {
@@ -39,18 +47,27 @@ impl Default for Request {
             session: Session::default(),
             inner: Arc::new(Inner::default()),
             params: None,
+            extensions: Extensions::default(),
             received_at: OffsetDateTime::now_utc(),
             skip_csrf: false,
             renew_session: false,
+            tls: false,
         }
     }
 }
 
+/// Initial buffer size for request bodies. The buffer grows as more of the body is received.
+const BODY_CHUNK_SIZE: usize = 64 * 1024;
+/// Longest `X-Request-Id` accepted from the client.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
 #[derive(Debug, Clone)]
 struct Inner {
     body: Vec<u8>,
     cookies: Cookies,
     peer: SocketAddr,
+    id: String,
+    session_key: Option<String>,
 }
 
 impl Default for Inner {
@@ -59,6 +76,8 @@ impl Default for Inner {
             body: Vec::default(),
             cookies: Cookies::default(),
             peer: "127.0.0.1:8000".parse().unwrap(), // Just used for testing.
+            id: Uuid::new_v4().to_string(),
+            session_key: None,
         }
     }
 }
@@ -70,37 +89,165 @@ impl Request {
     ///
     /// The request is fully received and loaded into memory before it's passed to a controller.
     /// It's safe to clone since the contents are behind an [`std::sync::Arc`].
-    pub async fn read(peer: SocketAddr, mut stream: impl AsyncRead + Unpin) -> Result<Self, Error> {
+    pub async fn read(peer: SocketAddr, stream: impl AsyncRead + Unpin) -> Result<Self, Error> {
+        Self::read_bounded(peer, stream, get_config().general.max_request_size, None).await
+    }
+
+    /// Read the request from a stream, limiting the size of the body and, optionally,
+    /// how long the client has to send it.
+    ///
+    /// Useful for endpoints accepting untrusted uploads. A body larger than `max_size` returns
+    /// [`Error::ContentTooLarge`], and a body which isn't received before the timeout returns [`Error::Timeout`].
+    /// A body which is too large is still drained from the stream, for up to the same timeout, and returns
+    /// [`Error::ContentTooLarge`] whether or not the client finishes sending it.
+    ///
+    /// Bodies sent with `Transfer-Encoding: chunked` are decoded, and `max_size` applies to the decoded body.
+    /// Requests with any other final transfer coding can't be framed and are rejected with [`Error::MalformedRequest`],
+    /// as are requests which set both `Transfer-Encoding` and `Content-Length`.
+    ///
+    /// If `allow_body_on_all_methods` is disabled in the configuration, `GET`, `HEAD` and `DELETE` requests which declare
+    /// a body are rejected with [`Error::MalformedRequest`], without reading it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::{Request, Error};
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Error> {
+    /// # let peer = "127.0.0.1:8000".parse().unwrap();
+    /// let stream = "POST /upload HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello".as_bytes();
+    /// let request = Request::read_bounded(peer, stream, 1024 * 1024, Some(Duration::from_secs(5))).await?;
+    ///
+    /// assert_eq!(request.body(), b"hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_bounded(
+        peer: SocketAddr,
+        stream: impl AsyncRead + Unpin,
+        max_size: usize,
+        timeout: Option<Duration>,
+    ) -> Result<Self, Error> {
+        Self::read_limited(
+            peer,
+            stream,
+            max_size,
+            timeout,
+            get_config().general.allow_body_on_all_methods,
+        )
+        .await
+    }
+
+    async fn read_limited(
+        peer: SocketAddr,
+        mut stream: impl AsyncRead + Unpin,
+        max_size: usize,
+        timeout: Option<Duration>,
+        allow_body_on_all_methods: bool,
+    ) -> Result<Self, Error> {
         let head = Head::read(&mut stream).await?;
+        let chunked = head.chunked();
         let content_length = head.content_length().unwrap_or(0);
 
+        // Without chunked as the final coding, the body can only end when the connection closes,
+        // which a request can't do (RFC 9112, section 6.3).
+        if !chunked && head.header("transfer-encoding").is_some() {
+            return Err(Error::MalformedRequest("transfer encoding"));
+        }
+
+        // Proxies can disagree on which header frames the body, so
+        // the rest of the connection can't be trusted (RFC 9112, section 6.1).
+        if chunked && head.header("content-length").is_some() {
+            return Err(Error::MalformedRequest(
+                "transfer encoding and content length",
+            ));
+        }
+
+        // Don't read a body the method isn't supposed to have, however large it claims to be.
+        if (content_length > 0 || chunked)
+            && matches!(head.method(), Method::Get | Method::Head | Method::Delete)
+            && !allow_body_on_all_methods
+        {
+            return Err(Error::MalformedRequest("body not allowed"));
+        }
+
         // Handle requests which are too large.
-        if content_length > get_config().general.max_request_size {
-            // Throw away whatever we receive.
-            let mut throw_away = vec![0u8; 4096];
-            let mut content_length = content_length as i64;
+        if !chunked && content_length > max_size {
+            let drain = async {
+                // Throw away whatever we receive.
+                let mut throw_away = vec![0u8; 4096];
+                let mut content_length = content_length as i64;
+
+                loop {
+                    let read = stream.read(&mut throw_away).await?;
+                    content_length -= read as i64;
+
+                    if content_length <= 0 || read == 0 {
+                        break;
+                    }
+                }
 
-            loop {
-                let read = stream.read(&mut throw_away).await?;
-                content_length -= read as i64;
+                Ok::<_, std::io::Error>(())
+            };
 
-                if content_length <= 0 || read == 0 {
-                    break;
+            // The body is rejected either way, so running out of time while draining it
+            // doesn't change the response.
+            match timeout {
+                Some(timeout) => {
+                    if let Ok(result) = tokio::time::timeout(timeout, drain).await {
+                        result?;
+                    }
                 }
+                None => drain.await?,
             }
 
             return Err(Error::ContentTooLarge(head));
         }
 
-        let mut body = vec![0u8; content_length];
-        stream
-            .read_exact(&mut body)
-            .await
-            .map_err(|_| Error::MalformedRequest("incorrect content length"))?;
+        let read_body = async {
+            // Content-Length was rejected above if both are set.
+            if chunked {
+                return Self::read_chunked(&mut stream, max_size).await;
+            }
+
+            // Grow the buffer as the body arrives instead of trusting the client-supplied length.
+            let mut body = Vec::with_capacity(content_length.min(BODY_CHUNK_SIZE));
+            (&mut stream)
+                .take(content_length as u64)
+                .read_to_end(&mut body)
+                .await?;
+
+            if body.len() != content_length {
+                return Err(Error::MalformedRequest("incorrect content length"));
+            }
+
+            Ok::<_, Error>(Some(body))
+        };
+
+        let body = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, read_body).await??,
+            None => read_body.await?,
+        };
+
+        let body = match body {
+            Some(body) => body,
+            None => return Err(Error::ContentTooLarge(head)),
+        };
 
         let cookies = head.cookies();
+        let id = head
+            .header("x-request-id")
+            .filter(|id| {
+                !id.is_empty()
+                    && id.len() <= MAX_REQUEST_ID_LENGTH
+                    && id.bytes().all(|b| b.is_ascii_graphic())
+            })
+            .cloned()
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
 
-        let (session, renew_session) = match cookies.get_session()? {
+        let (session, session_key) = load_session(get_session_store().as_deref(), &cookies).await?;
+        let (session, renew_session) = match session {
             Some(session) => (session, false),
             None => (Session::anonymous(), true),
         };
@@ -108,18 +255,81 @@ impl Request {
         Ok(Request {
             head,
             params: None,
+            extensions: Extensions::default(),
             session,
             inner: Arc::new(Inner {
                 body,
                 peer,
                 cookies,
+                id,
+                session_key,
             }),
             received_at: OffsetDateTime::now_utc(),
             skip_csrf: false,
             renew_session,
+            tls: false,
         })
     }
 
+    /// Decode a body sent with `Transfer-Encoding: chunked`. Returns `None` if
+    /// the body is larger than `max_size`.
+    async fn read_chunked(
+        mut stream: impl AsyncRead + Unpin,
+        max_size: usize,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let max_line_length = get_config().general.max_line_length;
+        let mut body = vec![];
+
+        loop {
+            let line = Head::read_line(&mut stream, max_line_length).await?;
+
+            // Chunk extensions, e.g. `5;name=value`, are ignored.
+            let size = line.split(';').next().unwrap_or_default().trim();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| Error::MalformedRequest("chunk size"))?;
+
+            if size == 0 {
+                break;
+            }
+
+            match body.len().checked_add(size) {
+                Some(len) if len <= max_size => (),
+                _ => return Ok(None),
+            }
+
+            let start = body.len();
+            body.resize(start + size, 0);
+            stream
+                .read_exact(&mut body[start..])
+                .await
+                .map_err(|_| Error::MalformedRequest("incorrect chunk size"))?;
+
+            if !Head::read_line(&mut stream, max_line_length)
+                .await?
+                .is_empty()
+            {
+                return Err(Error::MalformedRequest("chunk not terminated"));
+            }
+        }
+
+        // Skip trailer fields until the empty line ending the body.
+        while !Head::read_line(&mut stream, max_line_length)
+            .await?
+            .is_empty()
+        {}
+
+        Ok(Some(body))
+    }
+
+    /// Unique request identifier.
+    ///
+    /// Taken from the `X-Request-Id` header if the client (or a load balancer) sets it,
+    /// otherwise a random UUID is generated. Header values longer than 128 characters,
+    /// or containing spaces or non-ASCII characters, are ignored.
+    pub fn id(&self) -> &str {
+        &self.inner.id
+    }
+
     /// Get the request source IP address.
     ///
     /// This is the IP address of the TCP socket, and does
@@ -134,6 +344,70 @@ impl Request {
         self
     }
 
+    /// Copy of the request with a different path, e.g. to rewrite `/old` to `/new`
+    /// and dispatch it internally, without a round trip to the client.
+    ///
+    /// The path can include a query string. Route parameters are cleared since they belong
+    /// to the original route; the request has to be routed again.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let rewritten = request.clone().with_path("/new?page=2")?;
+    ///
+    /// if let Some(handler) = router.find(rewritten.path()) {
+    ///     let rewritten = rewritten.with_params(handler.path_with_regex().params());
+    ///     return handler.handle_internal(rewritten).await;
+    /// }
+    /// ```
+    pub fn with_path(mut self, path: &str) -> Result<Self, Error> {
+        let path = Path::parse(path)?.decode(get_config().general.allow_encoded_slashes)?;
+        self.head.replace_path(path);
+        self.params = None;
+        Ok(self)
+    }
+
+    /// Copy of the request with a different method, e.g. to handle a `POST` as a `GET`
+    /// after it's been processed. See [`Request::with_path`].
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.head.replace_method(method);
+        self
+    }
+
+    /// Mark the request as received over a TLS connection. *This is used internally automatically.*
+    pub fn with_tls(mut self, tls: bool) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// The request was sent over HTTPS, either directly to the server using TLS,
+    /// or through a proxy which terminated TLS and set the `X-Forwarded-Proto` header.
+    pub fn is_secure(&self) -> bool {
+        self.tls || self.scheme() == "https"
+    }
+
+    /// Data attached to the request by middleware, e.g. the authenticated user.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get a mutable reference to the request extensions, to attach data for
+    /// the middleware and controllers further down the chain.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// async fn handle_request(&self, mut request: Request) -> Result<Outcome, Error> {
+    ///     let user = User::find(1).fetch(Pool::pool()).await?;
+    ///     request.extensions_mut().insert(user);
+    ///
+    ///     Ok(Outcome::Forward(request))
+    /// }
+    /// ```
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
     /// Return request head (headers, method, etc.).
     ///
     /// [`crate::http::Head`] is dereferenced from this struct,
@@ -152,16 +426,62 @@ impl Request {
     /// The parameter must be specified
     /// in the path provided to the router at controller registration. The only exception
     /// is the `id` parameter which is automatically configured on REST controllers.
+    ///
+    /// If the parameter can't be converted to `T`, [`Error::InvalidParameter`] is returned,
+    /// which is rendered as `400 - Bad Request`.
     pub fn parameter<T: ToParameter>(&self, name: &str) -> Result<Option<T>, Error> {
         if let Some(ref params) = self.params {
             if let Some(parameter) = params.parameter(self.path().base(), name) {
-                return Ok(Some(T::to_parameter(&parameter)?));
+                return T::to_parameter(parameter)
+                    .map(Some)
+                    .map_err(|_| Error::InvalidParameter(name.to_string()));
             }
         }
 
         Ok(None)
     }
 
+    /// Get a URL query parameter, e.g. `page` in `?page=2`, converted to a Rust type.
+    /// Percent-encoded values are decoded. If the parameter is repeated, the last value is used.
+    ///
+    /// If the parameter can't be converted to `T`, [`Error::InvalidParameter`] is returned,
+    /// which is rendered as `400 - Bad Request`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let page = request.query_param::<i64>("page")?.unwrap_or(1);
+    /// ```
+    pub fn query_param<T: ToParameter>(&self, name: &str) -> Result<Option<T>, Error> {
+        match self.query().value(name) {
+            Some(value) => T::to_parameter(value)
+                .map(Some)
+                .map_err(|_| Error::InvalidParameter(name.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Get all values of a repeated URL query parameter, e.g. `?tag=a&tag=b`, converted to a Rust type.
+    /// If the parameter isn't set, the list is empty.
+    pub fn query_param_list<T: ToParameter>(&self, name: &str) -> Result<Vec<T>, Error> {
+        self.query()
+            .values(name)
+            .into_iter()
+            .map(|value| {
+                T::to_parameter(value).map_err(|_| Error::InvalidParameter(name.to_string()))
+            })
+            .collect()
+    }
+
+    /// Check that the request path was signed with [`Path::sign`](super::Path::sign)
+    /// and the signature hasn't expired.
+    ///
+    /// Use this for links sent by email, e.g. to unsubscribe or confirm an account,
+    /// without storing one-time tokens on the server.
+    pub fn verify_signature(&self) -> bool {
+        self.path().verify_signature()
+    }
+
     /// Retrieve the reequest body as bytes.
     ///
     /// It's the job of the caller to handle encoding, if any.
@@ -169,16 +489,118 @@ impl Request {
         &self.inner.body
     }
 
+    /// Copy of the request body suitable for audit logs. The body itself is unchanged,
+    /// so it can still be parsed by the controller.
+    ///
+    /// Values of the `redact` fields in JSON and form bodies are replaced with `[REDACTED]`,
+    /// and the result is truncated to at most `max_size` bytes. Other bodies, e.g. multipart, text or
+    /// JSON which can't be parsed, can't be redacted and are replaced with `<redacted: N bytes>` instead,
+    /// unless `redact` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // {"email": "alice@example.com", "password": "hunter2"}
+    /// let body = request.body_for_audit(1024, &["password"]);
+    /// assert_eq!(body, r#"{"email":"alice@example.com","password":"[REDACTED]"}"#);
+    /// ```
+    pub fn body_for_audit(&self, max_size: usize, redact: &[&str]) -> String {
+        const REDACTED: &str = "[REDACTED]";
+
+        fn redact_json(value: &mut Value, redact: &[&str]) {
+            match value {
+                Value::Object(map) => {
+                    for (key, value) in map.iter_mut() {
+                        if redact.iter().any(|name| name.eq_ignore_ascii_case(key)) {
+                            *value = Value::String(REDACTED.into());
+                        } else {
+                            redact_json(value, redact);
+                        }
+                    }
+                }
+                Value::Array(values) => values
+                    .iter_mut()
+                    .for_each(|value| redact_json(value, redact)),
+                _ => (),
+            }
+        }
+
+        let content_type = self
+            .header("content-type")
+            .map(|content_type| content_type.to_lowercase())
+            .unwrap_or_default();
+
+        let unredacted = || format!("<redacted: {} bytes>", self.body().len());
+
+        let mut body = if redact.is_empty() || self.body().is_empty() {
+            self.string()
+        } else if content_type.contains("json") {
+            match self.json_raw() {
+                Ok(mut json) => {
+                    redact_json(&mut json, redact);
+                    json.to_string()
+                }
+                Err(_) => unredacted(),
+            }
+        } else if content_type.starts_with("application/x-www-form-urlencoded") {
+            self.string()
+                .split('&')
+                .map(|pair| match pair.split_once('=') {
+                    Some((name, _))
+                        if redact
+                            .iter()
+                            .any(|field| field.eq_ignore_ascii_case(&urldecode(name))) =>
+                    {
+                        format!("{}={}", name, REDACTED)
+                    }
+                    _ => pair.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("&")
+        } else {
+            unredacted()
+        };
+
+        if body.len() > max_size {
+            let mut end = max_size;
+            while !body.is_char_boundary(end) {
+                end -= 1;
+            }
+            body.truncate(end);
+        }
+
+        body
+    }
+
     /// Request body parsed JSON value. If the body isn't JSON, an error is returned.
     pub fn json_raw(&self) -> Result<Value, serde_json::Error> {
         self.json()
     }
 
-    /// Request body, parsed as a UTF-8 string.
+    /// Request body, decoded into a UTF-8 string.
     ///
-    /// The encoding is assumed to be valid, and all incompatible characters are dropped.
+    /// The body is decoded using the `charset` parameter of the `Content-Type` header,
+    /// or [`crate::config::General::default_charset`] if none is set. Latin-1 (ISO-8859-1) and
+    /// US-ASCII are transcoded; all other character sets are decoded as UTF-8. Incompatible characters
+    /// are replaced with `U+FFFD`.
     pub fn string(&self) -> String {
-        String::from_utf8_lossy(self.body()).to_string()
+        let charset = self
+            .charset()
+            .unwrap_or_else(|| get_config().general.default_charset.to_lowercase());
+
+        match charset.as_str() {
+            "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" | "l1" => {
+                self.body().iter().map(|b| *b as char).collect()
+            }
+
+            "us-ascii" | "ascii" => self
+                .body()
+                .iter()
+                .map(|b| if b.is_ascii() { *b as char } else { '\u{FFFD}' })
+                .collect(),
+
+            _ => String::from_utf8_lossy(self.body()).to_string(),
+        }
     }
 
     /// Return data submitted via a form.
@@ -199,13 +621,86 @@ impl Request {
         T::from_form_data(&self.form_data()?)
     }
 
+    /// Deserialize the URL query parameters into a Rust struct.
+    ///
+    /// If a parameter has the wrong type, [`Error::Form`] is returned with the name of
+    /// the parameter, which returns `422 - Unprocessable Entity` to the client.
+    pub fn query_params<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        self.query().clone().deserialize()
+    }
+
     /// Deserialize request body from JSON into a Rust struct. If deserialization fails,
     /// an error is returned.
+    ///
+    /// Numbers keep their full precision, so large integers, e.g. `i128`, and decimals deserialized
+    /// into [`serde_json::Number`] or [`serde_json::Value`] aren't rounded to a `f64`.
     pub fn json<'a, T: Deserialize<'a>>(&'a self) -> Result<T, serde_json::Error> {
         let mut deserializer = Deserializer::from_slice(self.body());
         T::deserialize(&mut deserializer)
     }
 
+    /// Deserialize the request body into a Rust struct, using the format
+    /// specified in the `Content-Type` header.
+    ///
+    /// JSON, `application/x-www-form-urlencoded` and `multipart/form-data` bodies are supported.
+    /// Other content types return [`Error::UnsupportedMediaType`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// #[derive(Deserialize)]
+    /// struct User {
+    ///     email: String,
+    /// }
+    ///
+    /// let user = request.parse::<User>()?;
+    /// ```
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let content_type = self
+            .header("content-type")
+            .map(|content_type| content_type.to_lowercase())
+            .unwrap_or_default();
+
+        if content_type.contains("json") {
+            Ok(self.json()?)
+        } else if content_type.contains("application/x-www-form-urlencoded")
+            || content_type.contains("multipart/form-data")
+        {
+            self.form_data()?.deserialize()
+        } else {
+            Err(Error::UnsupportedMediaType(content_type))
+        }
+    }
+
+    /// Parse a JSON merge patch (RFC 7386) sent with `Content-Type: application/merge-patch+json`.
+    ///
+    /// Fields set to `null` in the patch should be deleted, while absent fields
+    /// should be left unchanged, see [`MergePatch`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let patch = request.merge_patch()?;
+    ///
+    /// match patch.field::<String>("nickname")? {
+    ///     Patch::Set(nickname) => user.nickname = Some(nickname),
+    ///     Patch::Delete => user.nickname = None,
+    ///     Patch::Absent => (),
+    /// }
+    /// ```
+    pub fn merge_patch(&self) -> Result<MergePatch, Error> {
+        let content_type = self
+            .header("content-type")
+            .map(|content_type| content_type.to_lowercase())
+            .unwrap_or_default();
+
+        if content_type.contains("application/merge-patch+json") {
+            Ok(MergePatch::new(self.json()?))
+        } else {
+            Err(Error::UnsupportedMediaType(content_type))
+        }
+    }
+
     /// Return cookies set on the request. If no cookies are set,
     /// an empty [`crate::http::Cookies`] is returned.
     pub fn cookies(&self) -> &Cookies {
@@ -309,6 +804,33 @@ impl Request {
         self
     }
 
+    /// Serve both a WebSocket and a regular page from the same route.
+    ///
+    /// If the client asked for a WebSocket, the handshake response (`101 - Switching Protocols`) is returned
+    /// and the connection is handed to the controller's [`crate::controller::WebsocketController::handle_stream`].
+    /// Otherwise, the closure is called to build a regular response.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// async fn handle(&self, request: &Request) -> Result<Response, Error> {
+    ///     request
+    ///         .if_websocket_else(|| async { Ok(Response::new().html("<h1>Chat</h1>")) })
+    ///         .await
+    /// }
+    /// ```
+    pub async fn if_websocket_else<F, Fut, E>(&self, otherwise: F) -> Result<Response, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<Response, E>>,
+    {
+        if self.upgrade_websocket() {
+            Ok(super::websocket::handshake(self))
+        } else {
+            otherwise().await
+        }
+    }
+
     /// Did the client request a HTTP connection upgrade to WebSocket?
     pub fn upgrade_websocket(&self) -> bool {
         self.headers()
@@ -389,6 +911,11 @@ impl Request {
     pub(crate) fn renew_session(&self) -> bool {
         self.renew_session
     }
+
+    /// Identifier of the session stored on the server, if any.
+    pub(crate) fn session_key(&self) -> Option<&str> {
+        self.inner.session_key.as_deref()
+    }
 }
 
 impl Deref for Request {
@@ -417,6 +944,7 @@ impl ToTemplateValue for Request {
 #[cfg(test)]
 pub mod test {
     use super::*;
+    use crate::http::path::{Path, PathType};
 
     pub async fn dummy_request() -> Result<Request, Error> {
         let body = ("GET /?hello=world HTTP/1.1\r\n".to_owned()
@@ -437,6 +965,211 @@ pub mod test {
         "127.0.0.1:1234".parse().unwrap()
     }
 
+    #[tokio::test]
+    async fn test_parse() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct User {
+            email: String,
+            age: i64,
+            admin: bool,
+            nickname: Option<String>,
+        }
+
+        let expected = User {
+            email: "test@test.com".into(),
+            age: 25,
+            admin: true,
+            nickname: None,
+        };
+
+        for (content_type, body) in [
+            (
+                "application/json",
+                r#"{"email": "test@test.com", "age": 25, "admin": true}"#,
+            ),
+            (
+                "application/x-www-form-urlencoded",
+                "email=test%40test.com&age=25&admin=on",
+            ),
+        ] {
+            let request = format!(
+                "POST / HTTP/1.1\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            );
+            let request = Request::read(dummy_ip(), request.as_bytes()).await.unwrap();
+            assert_eq!(request.parse::<User>().unwrap(), expected);
+        }
+
+        let request = Request::read(
+            dummy_ip(),
+            "POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello"
+                .as_bytes(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            request.parse::<User>(),
+            Err(Error::UnsupportedMediaType(_))
+        ));
+
+        let request = Request::read(
+            dummy_ip(),
+            "POST / HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: 24\r\n\r\nemail=a&age=old&admin=on".as_bytes(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(request.parse::<User>(), Err(Error::Form(_))));
+    }
+
+    #[tokio::test]
+    async fn test_form_field_errors() {
+        use crate::http::FormError;
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Signup {
+            email: String,
+            age: i64,
+            newsletter: Option<bool>,
+        }
+
+        let body = "email=a@b.c&age=old";
+        let request = format!(
+            "POST /signup?age=12x HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let request = Request::read(dummy_ip(), request.as_bytes()).await.unwrap();
+
+        let err = request.parse::<Signup>().unwrap_err();
+        assert_eq!(err.code(), 422);
+        match err {
+            Error::Form(FormError {
+                ref field,
+                ref reason,
+            }) => {
+                assert_eq!(field.as_deref(), Some("age"));
+                assert!(reason.contains("i64"), "{}", reason);
+            }
+            ref err => panic!("unexpected error: {:?}", err),
+        }
+        assert_eq!(Response::from(err).status().code(), 422);
+
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Contact {
+            email: String,
+        }
+
+        let err = request.query_params::<Contact>().err().unwrap();
+        assert_eq!(err.to_string(), "form: email: missing field");
+        assert!(matches!(
+            err,
+            Error::Form(FormError { ref field, .. }) if field.as_deref() == Some("email")
+        ));
+
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Age {
+            age: u8,
+        }
+
+        let err = request.query_params::<Age>().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            r#"form: age: invalid value: string "12x", expected u8"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_patch() {
+        use crate::http::merge_patch::Patch;
+
+        let body = r#"{"email": "new@test.com", "nickname": null}"#;
+        let request = format!(
+            "PATCH /users/1 HTTP/1.1\r\nContent-Type: application/merge-patch+json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let request = Request::read(dummy_ip(), request.as_bytes()).await.unwrap();
+        let patch = request.merge_patch().unwrap();
+
+        assert_eq!(
+            patch.field::<String>("email").unwrap(),
+            Patch::Set("new@test.com".into())
+        );
+        assert_eq!(patch.field::<String>("nickname").unwrap(), Patch::Delete);
+        assert_eq!(patch.field::<String>("age").unwrap(), Patch::Absent);
+
+        let request = Request::read(
+            dummy_ip(),
+            "PATCH /users/1 HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}"
+                .as_bytes(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            request.merge_patch(),
+            Err(Error::UnsupportedMediaType(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_if_websocket_else() {
+        let upgrade = "GET /chat HTTP/1.1\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n";
+        let request = Request::read(dummy_ip(), upgrade.as_bytes()).await.unwrap();
+        let response = request
+            .if_websocket_else(|| async { Ok::<_, Error>(Response::new().text("page")) })
+            .await
+            .unwrap();
+        assert_eq!(response.status().code(), 101);
+        assert_eq!(
+            response.headers().get("sec-websocket-accept"),
+            Some(&"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string())
+        );
+
+        let request = Request::read(dummy_ip(), "GET /chat HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        let response = request
+            .if_websocket_else(|| async { Ok::<_, Error>(Response::new().text("page")) })
+            .await
+            .unwrap();
+        assert_eq!(response.status().code(), 200);
+        assert!(!response.websocket_upgrade());
+    }
+
+    #[tokio::test]
+    async fn test_json_precision() {
+        #[derive(Deserialize)]
+        struct Payment {
+            id: i128,
+            amount: serde_json::Number,
+        }
+
+        let body =
+            r#"{"id": 123456789012345678901234567890, "amount": 0.10000000000000000000000001}"#;
+        let request = format!(
+            "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let request = Request::read(dummy_ip(), request.as_bytes()).await.unwrap();
+
+        let payment = request.json::<Payment>().unwrap();
+        assert_eq!(payment.id, 123456789012345678901234567890);
+        assert_eq!(payment.amount.to_string(), "0.10000000000000000000000001");
+
+        let json = request.json_raw().unwrap();
+        assert_eq!(json["id"].to_string(), "123456789012345678901234567890");
+        assert_eq!(
+            serde_json::to_string(&json["amount"]).unwrap(),
+            "0.10000000000000000000000001"
+        );
+    }
+
     #[tokio::test]
     async fn test_json() {
         #[derive(Deserialize)]
@@ -455,6 +1188,24 @@ pub mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_string_charset() {
+        let head = "POST / HTTP/1.1\r\n".to_owned()
+            + "Content-Type: text/plain; charset=ISO-8859-1\r\n"
+            + "Content-Length: 5\r\n"
+            + "\r\n";
+        let mut body = head.as_bytes().to_vec();
+        body.extend_from_slice(&[b'c', b'a', b'f', 0xe9, 0xff]);
+
+        let request = Request::read(dummy_ip(), &body[..]).await.unwrap();
+        assert_eq!(request.charset(), Some("iso-8859-1".to_string()));
+        assert_eq!(request.string(), "caf\u{e9}\u{ff}");
+
+        let request = dummy_request().await.unwrap();
+        assert_eq!(request.charset(), None);
+        assert_eq!(request.string(), r#"{"hello": "world"}"#);
+    }
+
     #[tokio::test]
     async fn test_basic_req() {
         let normal = "GET /apples?hello=world HTTP/1.1\r\n".to_owned()
@@ -504,6 +1255,412 @@ pub mod test {
         assert!(err.starts_with("ContentTooLarge"));
     }
 
+    #[tokio::test]
+    async fn test_read_bounded() {
+        let request = "POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n0123456789";
+        let timeout = Some(Duration::from_millis(50));
+
+        let req = Request::read_bounded(dummy_ip(), request.as_bytes(), 10, timeout)
+            .await
+            .unwrap();
+        assert_eq!(req.body(), b"0123456789");
+
+        // Body is larger than allowed.
+        let err = Request::read_bounded(dummy_ip(), request.as_bytes(), 9, timeout)
+            .await
+            .expect_err("too large");
+        assert!(matches!(err, Error::ContentTooLarge(_)));
+        assert_eq!(err.code(), 413);
+
+        // Client stops sending half way through the body.
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::io::AsyncWriteExt::write_all(&mut client, &request.as_bytes()[..40])
+            .await
+            .unwrap();
+        let err = Request::read_bounded(dummy_ip(), server, 10, timeout)
+            .await
+            .expect_err("timeout");
+        assert!(matches!(err, Error::Timeout(_)));
+        assert_eq!(err.code(), 408);
+
+        // Client stops sending half way through a body which is too large.
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::io::AsyncWriteExt::write_all(&mut client, &request.as_bytes()[..40])
+            .await
+            .unwrap();
+        let err = Request::read_bounded(dummy_ip(), server, 9, timeout)
+            .await
+            .expect_err("too large");
+        assert!(matches!(err, Error::ContentTooLarge(_)));
+        assert_eq!(err.code(), 413);
+    }
+
+    #[tokio::test]
+    async fn test_query_param() {
+        let request = Request::read(
+            dummy_ip(),
+            "GET /search?q=hello%20world%26more&tag=a&page=2&tag=b+c HTTP/1.1\r\n\r\n".as_bytes(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            request.query_param::<String>("q").unwrap(),
+            Some("hello world&more".to_string())
+        );
+        assert_eq!(request.query_param::<i64>("page").unwrap(), Some(2));
+
+        // Missing keys.
+        assert_eq!(request.query_param::<i64>("per_page").unwrap(), None);
+        assert!(request
+            .query_param_list::<String>("missing")
+            .unwrap()
+            .is_empty());
+
+        // Duplicate keys.
+        assert_eq!(
+            request.query_param_list::<String>("tag").unwrap(),
+            vec!["a".to_string(), "b c".to_string()]
+        );
+        assert_eq!(
+            request.query_param::<String>("tag").unwrap(),
+            Some("b c".to_string())
+        );
+
+        let err = request.query_param::<i64>("q").unwrap_err();
+        assert!(matches!(err, Error::InvalidParameter(ref name) if name == "q"));
+        assert!(request.query_param_list::<i64>("tag").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rewrite() {
+        use crate::http::{Handler, Router};
+        use crate::prelude::*;
+
+        struct Echo;
+
+        #[async_trait]
+        impl Controller for Echo {
+            async fn handle(
+                &self,
+                request: &Request,
+            ) -> Result<Response, crate::controller::Error> {
+                Ok(Response::new().header(
+                    "x-echo",
+                    format!(
+                        "{} {} {:?}",
+                        request.method(),
+                        request.path().path(),
+                        request.parameter::<i64>("id")?
+                    ),
+                ))
+            }
+        }
+
+        let router = Router::new(vec![
+            Handler::route("/old/:id", Echo),
+            Handler::route("/new/:id", Echo),
+        ])
+        .unwrap();
+
+        let original = dummy_request()
+            .await
+            .unwrap()
+            .with_method(Method::Post)
+            .with_path("/old/5")
+            .unwrap();
+        let handler = router.find(original.path()).unwrap();
+        let original = original.with_params(handler.path_with_regex().params());
+        assert_eq!(original.parameter::<i64>("id").unwrap(), Some(5));
+
+        let rewritten = original
+            .clone()
+            .with_path("/new/7?page=3")
+            .unwrap()
+            .with_method(Method::Get);
+        assert_eq!(rewritten.path().path(), "/new/7");
+        assert_eq!(rewritten.query().get::<i64>("page"), Some(3));
+        assert_eq!(rewritten.method(), &Method::Get);
+        assert_eq!(rewritten.body(), original.body());
+        // Parameters of the original route are cleared.
+        assert_eq!(rewritten.parameter::<i64>("id").unwrap(), None);
+
+        // The original request is unchanged.
+        assert_eq!(original.path().path(), "/old/5");
+        assert_eq!(original.method(), &Method::Post);
+
+        let handler = router.find(rewritten.path()).unwrap();
+        let rewritten = rewritten.with_params(handler.path_with_regex().params());
+        let response = handler.handle_internal(rewritten).await.unwrap();
+        assert_eq!(
+            response.headers().get("x-echo"),
+            Some(&"GET /new/7 Some(7)".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_max_body_size() {
+        let max_size = get_config().general.max_request_size;
+
+        let request = Request::read(
+            dummy_ip(),
+            "POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello".as_bytes(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(request.body(), b"hello");
+
+        // Declared length over the limit is rejected.
+        let request = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\nhello",
+            max_size + 1
+        );
+        let err = Request::read(dummy_ip(), request.as_bytes())
+            .await
+            .expect_err("too large");
+        assert!(matches!(err, Error::ContentTooLarge(_)));
+
+        // Declared length within the limit, but the body is much shorter.
+        let request = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\nhello",
+            max_size
+        );
+        let err = Request::read(dummy_ip(), request.as_bytes())
+            .await
+            .expect_err("short body");
+        assert!(matches!(
+            err,
+            Error::MalformedRequest("incorrect content length")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked() {
+        let request = "POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_owned()
+            + "5\r\nhello\r\n"
+            + "1;ext=value\r\n \r\n"
+            + "A\r\nworld, abc\r\n"
+            + "0\r\n"
+            + "Checksum: abc\r\n"
+            + "\r\n";
+
+        let req = Request::read_bounded(dummy_ip(), request.as_bytes(), 16, None)
+            .await
+            .unwrap();
+        assert!(req.head().chunked());
+        assert_eq!(req.body(), b"hello world, abc");
+
+        // Reassembled body is larger than allowed.
+        let err = Request::read_bounded(dummy_ip(), request.as_bytes(), 15, None)
+            .await
+            .expect_err("too large");
+        assert!(matches!(err, Error::ContentTooLarge(_)));
+
+        // Chunk shorter than its declared size.
+        let request =
+            "POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n5\r\nhel\r\n0\r\n\r\n";
+        let err = Request::read_bounded(dummy_ip(), request.as_bytes(), 16, None)
+            .await
+            .expect_err("malformed");
+        assert!(matches!(err, Error::MalformedRequest(_)));
+
+        let request = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nxyz\r\n";
+        let err = Request::read_bounded(dummy_ip(), request.as_bytes(), 16, None)
+            .await
+            .expect_err("bad size");
+        assert!(matches!(err, Error::MalformedRequest("chunk size")));
+
+        // Chunk size overflows the body length.
+        let request = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".to_owned()
+            + "5\r\nhello\r\n"
+            + &format!("{:x}\r\n", usize::MAX);
+        let err = Request::read_bounded(dummy_ip(), request.as_bytes(), usize::MAX, None)
+            .await
+            .expect_err("too large");
+        assert!(matches!(err, Error::ContentTooLarge(_)));
+
+        // Codings on separate lines are combined.
+        let request =
+            "POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n"
+                .to_owned()
+                + "5\r\nhello\r\n0\r\n\r\n";
+        let req = Request::read_bounded(dummy_ip(), request.as_bytes(), 16, None)
+            .await
+            .unwrap();
+        assert_eq!(req.body(), b"hello");
+
+        // Both Transfer-Encoding and Content-Length.
+        let request = "POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n".to_owned()
+            + "5\r\nhello\r\n0\r\n\r\n";
+        let err = Request::read_bounded(dummy_ip(), request.as_bytes(), 16, None)
+            .await
+            .expect_err("ambiguous");
+        assert!(matches!(
+            err,
+            Error::MalformedRequest("transfer encoding and content length")
+        ));
+        assert_eq!(err.code(), 400);
+
+        // Chunked isn't the final coding, so the body can't be framed.
+        for encoding in ["gzip", "chunked, gzip"] {
+            let request = format!(
+                "POST / HTTP/1.1\r\nTransfer-Encoding: {}\r\nContent-Length: 5\r\n\r\nhello",
+                encoding
+            );
+            let err = Request::read_bounded(dummy_ip(), request.as_bytes(), 16, None)
+                .await
+                .expect_err("not chunked");
+            assert!(matches!(err, Error::MalformedRequest("transfer encoding")));
+            assert_eq!(err.code(), 400);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_bodyless_method() {
+        let request = Request::read_limited(
+            dummy_ip(),
+            "GET / HTTP/1.1\r\nContent-Length: 0\r\n\r\n".as_bytes(),
+            10,
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(request.body().is_empty());
+
+        // DELETE can only have a body if it's allowed.
+        let request = "DELETE / HTTP/1.1\r\nContent-Length: 5\r\n\r\n12345";
+        let err = Request::read_limited(dummy_ip(), request.as_bytes(), 10, None, false)
+            .await
+            .expect_err("body not allowed");
+        assert!(matches!(err, Error::MalformedRequest("body not allowed")));
+
+        let request = Request::read_limited(dummy_ip(), request.as_bytes(), 10, None, true)
+            .await
+            .unwrap();
+        assert_eq!(request.body(), b"12345");
+
+        // Other methods can always have one.
+        let request = "PUT / HTTP/1.1\r\nContent-Length: 5\r\n\r\n12345";
+        let request = Request::read_limited(dummy_ip(), request.as_bytes(), 10, None, false)
+            .await
+            .unwrap();
+        assert_eq!(request.body(), b"12345");
+
+        // The body is never read, even though the client keeps the connection open.
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::io::AsyncWriteExt::write_all(
+            &mut client,
+            b"GET / HTTP/1.1\r\nContent-Length: 1000000000\r\n\r\n",
+        )
+        .await
+        .unwrap();
+        let err = Request::read_limited(dummy_ip(), server, usize::MAX, None, false)
+            .await
+            .expect_err("body not allowed");
+        assert!(matches!(err, Error::MalformedRequest("body not allowed")));
+        assert_eq!(err.code(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let read = |id: String| async move {
+            let request = format!("GET / HTTP/1.1\r\nX-Request-Id: {}\r\n\r\n", id);
+            Request::read(dummy_ip(), request.as_bytes()).await.unwrap()
+        };
+
+        assert_eq!(read("lb-1234".into()).await.id(), "lb-1234");
+
+        // Ignored, a random one is used instead.
+        for id in ["abc status=500".to_string(), "é".into(), "a".repeat(129)] {
+            let request = read(id.clone()).await;
+            assert_ne!(request.id(), id);
+            assert!(Uuid::parse_str(request.id()).is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_extensions() {
+        #[derive(Debug, PartialEq)]
+        struct CurrentUser {
+            id: i64,
+        }
+
+        let mut request = dummy_request().await.unwrap();
+        assert!(request.extensions().is_empty());
+
+        request.extensions_mut().insert(CurrentUser { id: 5 });
+        request.extensions_mut().insert(String::from("tenant"));
+
+        // Clones share the values.
+        let clone = request.clone();
+        assert_eq!(
+            clone.extensions().get::<CurrentUser>(),
+            Some(&CurrentUser { id: 5 })
+        );
+        assert_eq!(
+            clone.extensions().get::<String>().map(|s| s.as_str()),
+            Some("tenant")
+        );
+        assert!(clone.extensions().get::<i64>().is_none());
+
+        // One value per type.
+        request.extensions_mut().insert(CurrentUser { id: 6 });
+        assert_eq!(request.extensions().len(), 2);
+        let user = request.extensions().get_arc::<CurrentUser>().unwrap();
+        assert_eq!(user.id, 6);
+
+        assert!(request.extensions_mut().remove::<String>());
+        assert!(!request.extensions().contains::<String>());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_parameter() {
+        let route = Path::parse("/items/:id")
+            .unwrap()
+            .with_regex(PathType::Route)
+            .unwrap();
+        let req = "GET /items/apples HTTP/1.1\r\nContent-Length: 0\r\n\r\n";
+        let req = Request::read(dummy_ip(), req.as_bytes())
+            .await
+            .unwrap()
+            .with_params(route.params());
+
+        assert_eq!(
+            req.parameter::<String>("id").unwrap(),
+            Some("apples".into())
+        );
+
+        let err = req
+            .parameter::<i64>("id")
+            .expect_err("id is not an integer");
+        assert!(matches!(err, Error::InvalidParameter(ref name) if name == "id"));
+        assert_eq!(err.code(), 400);
+        assert_eq!(err.to_string(), "invalid parameter: id");
+    }
+
+    #[tokio::test]
+    async fn test_verify_signature() {
+        let expires_at = time::OffsetDateTime::now_utc() + time::Duration::minutes(5);
+        let signed = Path::parse("/confirm?email=a%40example.com")
+            .unwrap()
+            .sign(expires_at)
+            .unwrap();
+
+        let req = format!("GET {} HTTP/1.1\r\nContent-Length: 0\r\n\r\n", signed);
+        let req = Request::read(dummy_ip(), req.as_bytes()).await.unwrap();
+        assert!(req.verify_signature());
+
+        let req = format!(
+            "GET {} HTTP/1.1\r\nContent-Length: 0\r\n\r\n",
+            signed
+                .to_string()
+                .replace("a%40example.com", "b%40example.com")
+        );
+        let req = Request::read(dummy_ip(), req.as_bytes()).await.unwrap();
+        assert!(!req.verify_signature());
+    }
+
     #[tokio::test]
     async fn test_login_logout() {
         let req = "GET / HTTP/1.1\r\nContent-Length: 0\r\n\r\n";
@@ -18,7 +18,8 @@ use std::marker::Unpin;
 use time::OffsetDateTime;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-use super::{head::Version, Body, Cookie, Cookies, Error, Headers, Request};
+use super::session_store::{get_session_store, save_session};
+use super::{head::Version, Body, Cookie, Cookies, Error, FormError, Headers, Request};
 use crate::view::{Template, TurboStream};
 use crate::{config::get_config, controller::Session};
 
@@ -104,6 +105,20 @@ impl Status {
     pub fn ok(&self) -> bool {
         self.code() < 300
     }
+
+    /// Can a response with this status have a body?
+    ///
+    /// Informational (1xx), `204 - No Content` and `304 - Not Modified` responses
+    /// never have one.
+    pub fn has_body(&self) -> bool {
+        !self.informational() && !matches!(self.code(), 204 | 304)
+    }
+
+    /// Informational (1xx) status, e.g. `100 - Continue`. Responses with this status
+    /// are interim and are followed by the final response on the same connection.
+    pub fn informational(&self) -> bool {
+        (100..200).contains(&self.code())
+    }
 }
 impl From<u16> for Status {
     fn from(code: u16) -> Self {
@@ -224,25 +239,75 @@ impl Response {
         }
     }
 
+    /// Create an interim (1xx) response, e.g. `100 - Continue`.
+    ///
+    /// Interim responses don't have a body, cookies or any of the default headers.
+    /// They are sent before the final response on the same connection.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// Response::interim(Status::Continue).send(&mut stream).await?;
+    /// Response::new().text("uploaded").send(&mut stream).await?;
+    /// ```
+    pub fn interim(status: Status) -> Self {
+        Self {
+            code: status.code(),
+            headers: Headers::new(),
+            ..Self::new()
+        }
+    }
+
+    /// Create `103 - Early Hints` response with `Link` headers, so the client can start
+    /// preloading resources while the final response is prepared.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::http::Response;
+    /// let hints = Response::early_hints(&["</style.css>; rel=preload; as=style"]);
+    /// assert_eq!(hints.status().code(), 103);
+    /// ```
+    pub fn early_hints(links: &[&str]) -> Self {
+        Self::interim(Status::EarlyHints).header("link", links.join(", "))
+    }
+
     /// Create a response from a request. *This is used internally automatically.*
     ///
     /// This makes sure a valid session cookie is set on all responses.
-    pub fn from_request(mut self, request: &Request) -> Result<Self, Error> {
+    pub async fn from_request(mut self, request: &Request) -> Result<Self, Error> {
+        let store = get_session_store();
+        let store = store.as_deref();
+
         // Session set manually on the request already.
         if let Some(ref session) = self.session {
-            self.cookies.add_session(&session)?;
+            save_session(
+                store,
+                request.session_key(),
+                request.session(),
+                session,
+                &mut self.cookies,
+            )
+            .await?;
         } else {
             let session = request.session();
 
             if session.should_renew() || request.renew_session() {
-                let session = session
+                let renewed = session
                     .clone()
                     .renew(get_config().general.session_duration());
-                self.cookies.add_session(&session)?;
+                save_session(
+                    store,
+                    request.session_key(),
+                    session,
+                    &renewed,
+                    &mut self.cookies,
+                )
+                .await?;
 
                 // Set the session on the response, so it can be
                 // passed down in handle_stream.
-                self.session = Some(session);
+                self.session = Some(renewed);
             }
         }
 
@@ -284,6 +349,23 @@ impl Response {
         self
     }
 
+    /// Set response status.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rwf::http::{Response, response::Status};
+    /// use rwf::controller::Session;
+    ///
+    /// let response = Response::new()
+    ///     .set_session(Session::anonymous())
+    ///     .with_status(Status::Created);
+    /// assert_eq!(response.status().code(), 201);
+    /// ```
+    pub fn with_status(self, status: Status) -> Self {
+        self.code(status.code())
+    }
+
     /// Create a response with a JSON body serialized from a Rust type.
     ///
     /// # Example
@@ -351,17 +433,38 @@ impl Response {
     }
 
     /// Send the response to a stream, serialized as bytes.
-    pub async fn send(mut self, mut stream: impl AsyncWrite + Unpin) -> Result<(), std::io::Error> {
+    ///
+    /// Returns the number of bytes written, including the response head.
+    pub async fn send(
+        mut self,
+        mut stream: impl AsyncWrite + Unpin,
+    ) -> Result<usize, std::io::Error> {
+        if !self.status().has_body() {
+            // Content-Length isn't allowed either.
+            self.body = Body::bytes(vec![]);
+            self.headers.remove("content-length");
+            self.headers.remove("content-type");
+        } else if self.headers.get("content-length").is_none()
+            && self.headers.get("transfer-encoding").is_none()
+        {
+            self.headers
+                .insert("content-length", self.body.len().to_string());
+        }
+
         let mut response = format!("{} {}\r\n", self.version, self.code)
             .as_bytes()
             .to_vec();
 
         response.extend_from_slice(&self.headers.to_bytes());
-        response.extend_from_slice(&self.cookies.to_headers());
+        if !self.status().informational() {
+            response.extend_from_slice(&self.cookies.to_headers());
+        }
         response.extend_from_slice(b"\r\n");
 
         stream.write_all(&response).await?;
-        self.body.send(stream).await
+        self.body.send(stream).await?;
+
+        Ok(response.len() + self.body.len())
     }
 
     /// Mutable reference to response cookies. Used to set cookies on the response.
@@ -478,29 +581,111 @@ impl Response {
         Self::error_pretty("403 - Forbidden", "").code(403)
     }
 
+    /// Create `400 - Bad Request` response for a URL parameter
+    /// that couldn't be parsed.
+    pub fn invalid_parameter(name: &str) -> Self {
+        Self::error_pretty(
+            "400 - Bad Request",
+            &format!("Parameter \"{}\" is not valid.", name),
+        )
+        .code(400)
+    }
+
+    /// Create `204 - No Content` response, e.g. after a `DELETE`.
+    ///
+    /// The response has no body, and no `Content-Type` or `Content-Length` headers.
+    pub fn no_content() -> Self {
+        Self::new().code(204)
+    }
+
+    /// Create `415 - Unsupported Media Type` response.
+    pub fn unsupported_media_type() -> Self {
+        Self::error_pretty("415 - Unsupported Media Type", "").code(415)
+    }
+
+    /// Create `422 - Unprocessable Entity` response for a form or query
+    /// parameter that couldn't be deserialized.
+    ///
+    /// The field name is always shown. The reason can contain the submitted value,
+    /// so it's shown to the client only if `debug` is `true`.
+    pub fn unprocessable_entity(err: &FormError, debug: bool) -> Self {
+        let message = match (&err.field, debug) {
+            (Some(field), true) => format!("Field \"{}\" is not valid: {}.", field, err.reason),
+            (Some(field), false) => format!("Field \"{}\" is not valid.", field),
+            (None, true) => err.reason.clone(),
+            (None, false) => String::new(),
+        };
+
+        Self::error_pretty("422 - Unprocessable Entity", &message).code(422)
+    }
+
+    /// Create `408 - Request Timeout` response.
+    pub fn request_timeout() -> Self {
+        Self::error_pretty("408 - Request Timeout", "").code(408)
+    }
+
+    /// Create `412 - Precondition Failed` response.
+    pub fn precondition_failed() -> Self {
+        Self::error_pretty("412 - Precondition Failed", "").code(412)
+    }
+
     /// Create `413 - Content Too Large` response.
     pub fn content_too_large() -> Self {
         Self::error_pretty("413 - Content Too Large", "").code(413)
     }
 
+    /// Create `505 - HTTP Version Not Supported` response.
+    pub fn version_not_supported() -> Self {
+        Self::error_pretty("505 - HTTP Version Not Supported", "").code(505)
+    }
+
     /// Create `500 - Internal Server Error` response.
     ///
     /// Requires the error that was returned for debugging purposes.
-    /// The error is shown in development (debug) and hidden in production (release).
+    /// The error is shown to the client only if [`crate::config::General::debug_errors`] is enabled,
+    /// so internal details like SQL queries don't leak in production.
     pub fn internal_error(err: impl std::error::Error) -> Self {
-        // TODO:
-        #[cfg(debug_assertions)]
-        let err = format!("{}", err);
-
-        #[cfg(not(debug_assertions))]
-        let err = {
-            let _ = err;
-            ""
+        Self::internal_error_debug(err, get_config().general.debug_errors)
+    }
+
+    /// Create `500 - Internal Server Error` response, showing the error
+    /// to the client only if `debug` is `true`.
+    pub fn internal_error_debug(err: impl std::error::Error, debug: bool) -> Self {
+        let err = if debug {
+            err.to_string()
+        } else {
+            String::new()
         };
 
         Self::error_pretty("500 - Internal Server Error", &err)
     }
 
+    /// Create an error response with the status code matching the error.
+    ///
+    /// The error is shown to the client only if `debug` is `true`. Use [`From<Error>`]
+    /// to follow [`crate::config::General::debug_errors`] instead.
+    pub fn from_error(err: Error, debug: bool) -> Self {
+        match err {
+            Error::InvalidParameter(ref name) => Self::invalid_parameter(name),
+            Error::Forbidden => Self::forbidden(),
+            Error::Timeout(_) => Self::request_timeout(),
+            Error::PreconditionFailed => Self::precondition_failed(),
+            Error::ContentTooLarge(_) => Self::content_too_large(),
+            Error::UnsupportedMediaType(_) => Self::unsupported_media_type(),
+            Error::VersionNotSupported(_) => Self::version_not_supported(),
+            Error::Form(ref err) => Self::unprocessable_entity(err, debug),
+            err if err.code() == 400 => {
+                let message = if debug {
+                    err.to_string()
+                } else {
+                    String::new()
+                };
+                Self::error_pretty("400 - Bad Request", &message).code(400)
+            }
+            err => Self::internal_error_debug(err, debug),
+        }
+    }
+
     /// Use the internal template to render a better looking error page.
     /// Returns `500 - Internal Server Error` response.
     pub fn error_pretty(title: &str, message: &str) -> Self {
@@ -555,6 +740,12 @@ impl Response {
     }
 }
 
+impl From<Error> for Response {
+    fn from(err: Error) -> Self {
+        Self::from_error(err, get_config().general.debug_errors)
+    }
+}
+
 impl From<serde_json::Value> for Response {
     fn from(value: serde_json::Value) -> Response {
         Response::new().json(value).unwrap()
@@ -578,3 +769,172 @@ impl From<Vec<TurboStream>> for Response {
         Response::new().turbo_stream(&value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::{CookieBuilder, Head};
+
+    #[tokio::test]
+    async fn test_internal_error_debug() {
+        let err = std::io::Error::other("relation users does not exist");
+
+        let mut body = vec![];
+        Response::internal_error_debug(&err, true)
+            .send(&mut body)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with("HTTP/1.1 500"));
+        assert!(body.contains("relation users does not exist"));
+
+        let mut body = vec![];
+        Response::internal_error_debug(&err, false)
+            .send(&mut body)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with("HTTP/1.1 500"));
+        assert!(body.contains("500 - Internal Server Error"));
+        assert!(!body.contains("users"));
+    }
+
+    #[tokio::test]
+    async fn test_unprocessable_entity() {
+        let err = FormError {
+            field: Some("age".into()),
+            reason: "invalid value: string \"old\", expected i64".into(),
+        };
+
+        let mut body = vec![];
+        Response::from_error(Error::Form(err.clone()), true)
+            .send(&mut body)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with("HTTP/1.1 422"));
+        assert!(body.contains("age"));
+        assert!(body.contains("expected i64"));
+
+        let mut body = vec![];
+        Response::from_error(Error::Form(err), false)
+            .send(&mut body)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with("HTTP/1.1 422"));
+        assert!(body.contains("age"));
+        assert!(!body.contains("expected i64"));
+    }
+
+    #[test]
+    fn test_with_status() {
+        let session = Session::new_authenticated("payload", 5).unwrap();
+        let response = Response::new()
+            .set_session(session)
+            .with_status(Status::Created);
+
+        assert_eq!(response.status().code(), 201);
+        assert_eq!(
+            response.session().as_ref().unwrap().session_id.user_id(),
+            Some(5)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_content() {
+        let mut body = vec![];
+        Response::no_content().send(&mut body).await.unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with("HTTP/1.1 204\r\n"));
+        assert!(body.ends_with("\r\n\r\n"));
+        assert!(!body.contains("content-type"));
+        assert!(!body.contains("content-length"));
+
+        // Body is dropped, even if set.
+        let mut body = vec![];
+        Response::new()
+            .text("hello")
+            .code(204)
+            .send(&mut body)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.ends_with("\r\n\r\n"));
+        assert!(!body.contains("hello"));
+
+        // Other empty responses have a zero length.
+        let mut body = vec![];
+        Response::new().send(&mut body).await.unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.contains("content-length: 0\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_interim_response() {
+        let mut stream = vec![];
+        Response::early_hints(&["</style.css>; rel=preload; as=style"])
+            .cookie(CookieBuilder::new().name("hint").value("1").build())
+            .send(&mut stream)
+            .await
+            .unwrap();
+        Response::new()
+            .text("hello")
+            .send(&mut stream)
+            .await
+            .unwrap();
+
+        let stream = String::from_utf8(stream).unwrap();
+        let (interim, last) = stream.split_once("\r\n\r\n").unwrap();
+        assert_eq!(
+            interim,
+            "HTTP/1.1 103\r\nlink: </style.css>; rel=preload; as=style"
+        );
+        assert!(last.starts_with("HTTP/1.1 200\r\n"));
+        assert!(last.contains("connection: keep-alive\r\n"));
+        assert!(last.ends_with("\r\n\r\nhello"));
+
+        let mut stream = vec![];
+        Response::interim(Status::Continue)
+            .send(&mut stream)
+            .await
+            .unwrap();
+        assert_eq!(stream, b"HTTP/1.1 100\r\n\r\n");
+        assert!(Status::Continue.informational());
+        assert!(!Status::Ok.informational());
+    }
+
+    #[tokio::test]
+    async fn test_from_error() {
+        let head = Head::read("GET / HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        let timeout = tokio::time::timeout(std::time::Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+
+        for (err, code) in [
+            (Error::MalformedRequest("missing method"), 400),
+            (Error::MissingParameter, 400),
+            (Error::InvalidParameter("id".into()), 400),
+            (Error::Forbidden, 403),
+            (Error::Timeout(timeout), 408),
+            (Error::PreconditionFailed, 412),
+            (Error::ContentTooLarge(head), 413),
+            (Error::UnsupportedMediaType("text/plain".into()), 415),
+            (Error::UserIdIsNull, 500),
+        ] {
+            assert_eq!(Response::from(err).status().code(), code);
+        }
+
+        let response = Response::from_error(Error::MalformedRequest("missing method"), true);
+        let mut body = vec![];
+        response.send(&mut body).await.unwrap();
+        assert!(String::from_utf8(body).unwrap().contains("missing method"));
+
+        let response = Response::from_error(Error::MalformedRequest("missing method"), false);
+        let mut body = vec![];
+        response.send(&mut body).await.unwrap();
+        assert!(!String::from_utf8(body).unwrap().contains("missing method"));
+    }
+}
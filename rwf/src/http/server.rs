@@ -4,20 +4,25 @@
 //! If no handler is matched, return `404 - Not Found`.
 //!
 //! The server is using Tokio and can support millions of concurrent clients.
-use super::{Error, Handler, Request, Response, Router};
+use super::{AccessLog, Error, Handler, Request, Response, Router};
 
 use crate::colors::MaybeColorize;
 use crate::config::get_config;
 
+use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter,
+};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::select;
 use tokio::signal::ctrl_c;
 use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
 use tracing::{debug, error, info};
 
 /// Type of TCP connection used by the client.
@@ -25,17 +30,82 @@ use tracing::{debug, error, info};
 pub enum Stream<'a> {
     /// Plain text (not encrypted).
     Plain(&'a mut BufReader<BufWriter<TcpStream>>),
+    /// Encrypted with TLS.
+    Tls(&'a mut BufReader<BufWriter<TlsStream<TcpStream>>>),
 }
 
+/// A stream that can be read from and written to.
+pub trait ReadWrite: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ReadWrite for T {}
+
 impl<'a> Stream<'a> {
     /// Get the underlying TCP stream reader & writer.
-    pub fn stream(&'a mut self) -> impl AsyncRead + AsyncWrite + 'a {
+    pub fn stream(&'a mut self) -> &'a mut dyn ReadWrite {
         match self {
-            Stream::Plain(stream) => stream,
+            Stream::Plain(stream) => *stream,
+            Stream::Tls(stream) => *stream,
         }
     }
 }
 
+/// Buffered connection to the client.
+trait Connection: ReadWrite + AsyncBufRead {
+    fn to_stream(&mut self) -> Stream<'_>;
+
+    /// The client sent more data than we've read so far.
+    fn buffered(&self) -> bool;
+
+    /// The connection is encrypted with TLS.
+    fn tls(&self) -> bool;
+}
+
+impl Connection for BufReader<BufWriter<TcpStream>> {
+    fn to_stream(&mut self) -> Stream<'_> {
+        Stream::Plain(self)
+    }
+
+    fn buffered(&self) -> bool {
+        !self.buffer().is_empty()
+    }
+
+    fn tls(&self) -> bool {
+        false
+    }
+}
+
+impl Connection for BufReader<BufWriter<TlsStream<TcpStream>>> {
+    fn to_stream(&mut self) -> Stream<'_> {
+        Stream::Tls(self)
+    }
+
+    fn buffered(&self) -> bool {
+        !self.buffer().is_empty()
+    }
+
+    fn tls(&self) -> bool {
+        true
+    }
+}
+
+/// Maximum number of pipelined requests handled concurrently on one connection.
+const MAX_PIPELINED: usize = 16;
+
+/// Pipelined request, either already running or waiting for the requests before it to finish.
+enum Pipelined {
+    Running(JoinHandle<Dispatched>),
+    Waiting(Box<Request>),
+}
+
+/// Request handled by a controller, waiting for its turn to be sent to the client.
+struct Dispatched {
+    request: Request,
+    response: Response,
+    controller_name: &'static str,
+    duration: Duration,
+    start: Instant,
+}
+
 /// HTTP server.
 pub struct Server {
     handlers: Arc<Router>,
@@ -55,7 +125,11 @@ impl Server {
     /// Launch the server. This blocks until the server is shut down (`SIGINT`/Ctrl-C).
     pub async fn launch(self) -> Result<(), Error> {
         let config = get_config();
-        let addr = format!("{}:{}", config.general.host, config.general.port);
+
+        // Don't start with defaults (or a random key) if the config is broken.
+        config.check()?;
+
+        let addr = config.general.bind_address();
         info!(
             "Starting {} {} {}",
             "Rwf".green(),
@@ -65,9 +139,20 @@ impl Server {
 
         self.handlers.log_routes();
 
+        let _key_rotation = crate::crypto::spawn_key_rotation();
+
+        let tls = match config.tls {
+            Some(ref tls) => Some(tls.acceptor()?),
+            None => None,
+        };
+
         let listener = TcpListener::bind(addr).await?;
 
-        info!("Listening on {}", listener.local_addr().unwrap());
+        info!(
+            "Listening on {}{}",
+            listener.local_addr().unwrap(),
+            if tls.is_some() { " (TLS)" } else { "" }
+        );
 
         loop {
             select! {
@@ -79,9 +164,10 @@ impl Server {
                 result = listener.accept()  => {
                     if let Ok((stream, peer_addr)) = result {
                         let handlers = self.handlers.clone();
+                        let tls = tls.clone();
 
                         tokio::spawn(async move {
-                            match Self::handle_connection(handlers, stream, peer_addr).await {
+                            match Self::handle_connection(handlers, stream, peer_addr, tls).await {
                                 Ok(_) => (),
                                 Err(_) => {
                                     error!("panic detected, this is a bug; controllers should return an error instead");
@@ -98,109 +184,240 @@ impl Server {
         handlers: Arc<Router>,
         stream: TcpStream,
         peer_addr: SocketAddr,
+        tls: Option<TlsAcceptor>,
     ) -> JoinHandle<()> {
-        let mut stream = BufReader::new(BufWriter::new(stream));
-
         tokio::spawn(async move {
             debug!("{} new connection from {:?}", "http".purple(), peer_addr);
 
+            match tls {
+                Some(acceptor) => match acceptor.accept(stream).await {
+                    Ok(stream) => {
+                        let stream = BufReader::new(BufWriter::new(stream));
+                        Self::serve(handlers, stream, peer_addr).await
+                    }
+
+                    Err(err) => debug!(
+                        "{} client {:?} tls handshake failed: {}",
+                        "http".purple(),
+                        peer_addr,
+                        err
+                    ),
+                },
+
+                None => {
+                    let stream = BufReader::new(BufWriter::new(stream));
+                    Self::serve(handlers, stream, peer_addr).await
+                }
+            }
+        })
+    }
+
+    async fn serve(handlers: Arc<Router>, mut stream: impl Connection, peer_addr: SocketAddr) {
+        let keep_alive_timeout = get_config().general.keep_alive_timeout().unsigned_abs();
+
+        loop {
+            // Wait for the next request, closing idle connections.
+            match timeout(keep_alive_timeout, stream.fill_buf()).await {
+                Ok(Ok(buf)) if !buf.is_empty() => (),
+                _ => {
+                    debug!(
+                        "{} client {:?} disconnected or idle",
+                        "http".purple(),
+                        peer_addr
+                    );
+                    return;
+                }
+            }
+
+            // Read all pipelined requests and run the controllers of safe ones concurrently.
+            // Any other request waits for the ones before it to finish and ends the batch,
+            // so requests with side effects run in the order they were received.
+            let mut pending = VecDeque::new();
+            let mut error = None;
+
             loop {
-                let request = match Request::read(peer_addr, &mut stream).await {
-                    Ok(request) => request,
-                    Err(ref err) => {
-                        match err {
-                            Error::ContentTooLarge(head) => {
-                                let response = Response::content_too_large();
-                                let _ = Self::send_response(&mut stream, response).await;
-
-                                info!(
-                                    "{} {} {} 413",
-                                    head.method().to_string().purple(),
-                                    head.path().base().purple(),
-                                    std::any::type_name::<Self>().green(),
-                                );
-                            }
+                match Request::read(peer_addr, &mut stream).await {
+                    Ok(request) => {
+                        let request = request.with_tls(stream.tls());
+                        let safe = request.method().safe();
+                        let more = safe && request.keep_alive() && !request.upgrade_websocket();
+
+                        if safe || pending.is_empty() {
+                            pending.push_back(Pipelined::Running(Self::dispatch(
+                                handlers.clone(),
+                                request,
+                            )));
+                        } else {
+                            pending.push_back(Pipelined::Waiting(Box::new(request)));
+                        }
 
-                            _ => (),
+                        if !more || !stream.buffered() || pending.len() >= MAX_PIPELINED {
+                            break;
                         }
-                        debug!(
-                            "{} client {:?} disconnected: {}",
-                            "http".purple(),
-                            peer_addr,
-                            err
-                        );
-                        return;
                     }
+
+                    Err(err) => {
+                        error = Some(err);
+                        break;
+                    }
+                }
+            }
+
+            // Responses are sent in the same order the requests were received,
+            // no matter which controller finished first.
+            for pipelined in pending {
+                let dispatched = match pipelined {
+                    Pipelined::Running(dispatched) => dispatched,
+                    Pipelined::Waiting(request) => Self::dispatch(handlers.clone(), *request),
                 };
 
-                let start = Instant::now();
+                let Dispatched {
+                    request,
+                    response,
+                    controller_name,
+                    duration,
+                    start,
+                } = match dispatched.await {
+                    Ok(dispatched) => dispatched,
+                    Err(_) => {
+                        error!("panic detected, this is a bug; controllers should return an error instead");
+                        return;
+                    }
+                };
 
-                match handlers.find(request.path()) {
-                    Some(handler) => {
-                        // Set the matching regex to extract parameters.
-                        let request = request.with_params(handler.path_with_regex().params());
-
-                        // Pass the request to the controller to get a response.
-                        let response = match handler.handle_internal(request.clone()).await {
-                            Ok(response) => response,
-                            Err(err) => {
-                                error!("{}", err);
-                                Response::internal_error(err)
-                            }
-                        };
+                let keep_alive = request.keep_alive();
 
-                        // Set the session on the request before we pass it down
-                        // to the stream handler.
-                        let request = match response.session().clone() {
-                            Some(session) => request.set_session(session),
-                            None => request,
-                        };
-                        let ok = response.status().ok();
+                // Set the session on the request before we pass it down
+                // to the stream handler.
+                let request = match response.session().clone() {
+                    Some(session) => request.set_session(session),
+                    None => request,
+                };
+                let ok = response.status().ok();
+                let code = response.status().code();
 
-                        // Calculate duration.
-                        // We include the time to find the handler in the duration.
-                        let duration = start.elapsed();
+                // Log request.
+                Self::log(&request, controller_name, &response, duration);
 
-                        // Log request.
-                        Self::log(&request, handler.controller_name(), &response, duration);
+                match Self::send_response(&mut stream, response).await {
+                    Ok(bytes) => Self::access_log(&request, code, bytes, start.elapsed()),
+                    Err(err) => {
+                        debug!("{} error {:?}", peer_addr, err);
+                        return;
+                    }
+                }
 
-                        if let Err(err) = Self::send_response(&mut stream, response).await {
-                            debug!("{} error {:?}", peer_addr, err);
-                            break;
-                        }
+                match handlers.find(request.path()) {
+                    Some(handler) if ok => {
+                        match handler.handle_stream(&request, stream.to_stream()).await {
+                            Ok(true) => continue,
+                            _ => return,
+                        };
+                    }
 
-                        if ok {
-                            match handler
-                                .handle_stream(&request, Stream::Plain(&mut stream))
-                                .await
-                            {
-                                Ok(true) => continue,
-                                _ => break,
-                            };
+                    _ => {
+                        if !keep_alive {
+                            return;
                         }
                     }
+                }
+            }
 
-                    None => {
-                        // Log duration of search.
-                        let duration = Instant::now() - start;
+            if let Some(err) = error {
+                if let Error::ContentTooLarge(ref head) = err {
+                    info!(
+                        "{} {} {} 413",
+                        head.method().to_string().purple(),
+                        head.path().base().purple(),
+                        std::any::type_name::<Self>().green(),
+                    );
+                }
+                debug!(
+                    "{} client {:?} disconnected: {}",
+                    "http".purple(),
+                    peer_addr,
+                    err
+                );
+
+                // Tell the client what's wrong with the request, unless it's gone already.
+                // The rest of the stream can't be trusted, so the connection is closed.
+                if !matches!(err, Error::Io(_)) {
+                    let response = Response::from_error(err, false).header("connection", "close");
+                    let _ = Self::send_response(&mut stream, response).await;
+                }
 
-                        // Generate default not found response.
-                        let response = Response::not_found();
+                return;
+            }
+        }
+    }
 
-                        // Log the response.
-                        Self::log(&request, std::any::type_name::<Self>(), &response, duration);
+    /// Run the controller for the request in the background.
+    fn dispatch(handlers: Arc<Router>, request: Request) -> JoinHandle<Dispatched> {
+        tokio::spawn(async move {
+            let start = Instant::now();
+            let keep_alive = request.keep_alive();
+
+            let allowed_hosts = &get_config().general.allowed_hosts;
+
+            let (request, response, controller_name) = match handlers.find(request.path()) {
+                // Reject requests for hosts we don't serve, e.g. Host header injection.
+                _ if !request.host_allowed(allowed_hosts) => {
+                    debug!(
+                        "{} host {:?} is not allowed",
+                        "http".purple(),
+                        request.authority()
+                    );
+                    (
+                        request,
+                        Response::bad_request(),
+                        std::any::type_name::<Self>(),
+                    )
+                }
 
-                        // Send reply to client.
-                        if let Err(err) = Self::send_response(&mut stream, response).await {
-                            debug!("{} error {:?}", peer_addr, err);
-                            break;
+                Some(handler) => {
+                    // Set the matching regex to extract parameters.
+                    let request = request.with_params(handler.path_with_regex().params());
+
+                    // Pass the request to the controller to get a response.
+                    let response = match handler.handle_internal(request.clone()).await {
+                        Ok(response) => response,
+                        Err(err) => {
+                            error!("{}", err);
+                            Response::internal_error(err)
                         }
-                    }
+                    };
+
+                    (request, response, handler.controller_name())
                 }
+
+                // Generate default not found response.
+                None => (
+                    request,
+                    Response::not_found(),
+                    std::any::type_name::<Self>(),
+                ),
+            };
+
+            Dispatched {
+                request,
+                response: Self::connection(response, keep_alive),
+                controller_name,
+                // We include the time to find the handler in the duration.
+                duration: start.elapsed(),
+                start,
             }
         })
     }
 
+    /// Tell the client we're closing the connection after this response.
+    fn connection(response: Response, keep_alive: bool) -> Response {
+        if keep_alive {
+            response
+        } else {
+            response.header("connection", "close")
+        }
+    }
+
     fn log(request: &Request, controller_name: &str, response: &Response, duration: Duration) {
         let method = request.method().to_string();
         let path = request.path().path();
@@ -217,13 +434,278 @@ impl Server {
         );
     }
 
+    fn access_log(request: &Request, code: u16, bytes: usize, duration: Duration) {
+        if get_config().general.access_log {
+            info!("{}", AccessLog::new(request, code, bytes, duration));
+        }
+    }
+
     async fn send_response(
         mut stream: impl AsyncWrite + Unpin,
         response: Response,
-    ) -> Result<(), Error> {
-        response.send(&mut stream).await?;
+    ) -> Result<usize, Error> {
+        let bytes = response.send(&mut stream).await?;
         stream.flush().await?;
 
-        Ok(())
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::async_trait;
+    use crate::config::TlsConfig;
+    use crate::controller::{Controller, Error as ControllerError};
+
+    use tempdir::TempDir;
+    use tokio::io::AsyncReadExt;
+    use tokio::time::timeout;
+    use tokio_rustls::rustls::{
+        crypto::ring::default_provider, pki_types::ServerName, ClientConfig, RootCertStore,
+    };
+    use tokio_rustls::TlsConnector;
+
+    struct Hello;
+
+    #[async_trait]
+    impl Controller for Hello {
+        async fn handle(&self, request: &Request) -> Result<Response, ControllerError> {
+            if request.path().path().starts_with("/slow") {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+
+            Ok(Response::new().text(format!("hello {}", request.path().path())))
+        }
+    }
+
+    static EVENTS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(vec![]);
+
+    struct Ordered;
+
+    #[async_trait]
+    impl Controller for Ordered {
+        async fn handle(&self, request: &Request) -> Result<Response, ControllerError> {
+            let name = format!("{} {}", request.method(), request.path().path());
+            EVENTS.lock().unwrap().push(format!("start {}", name));
+            if request.path().path().starts_with("/slow") {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            EVENTS.lock().unwrap().push(format!("end {}", name));
+
+            Ok(Response::new().text(format!("hello {}", request.path().path())))
+        }
+
+        fn skip_csrf(&self) -> bool {
+            true
+        }
+    }
+
+    fn serve_one() -> SocketAddr {
+        serve(Hello.wildcard("/"))
+    }
+
+    fn serve(handler: Handler) -> SocketAddr {
+        let router = Arc::new(Router::new(vec![handler]).unwrap());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let listener = TcpListener::from_std(listener).unwrap();
+
+        tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            Server::handle_connection(router, stream, peer_addr, None)
+                .await
+                .unwrap();
+        });
+
+        addr
+    }
+
+    async fn read_until(stream: &mut (impl AsyncRead + Unpin), text: &str) -> String {
+        let mut response = vec![];
+        let mut buf = [0u8; 1024];
+        while !String::from_utf8_lossy(&response).contains(text) {
+            let n = timeout(Duration::from_secs(5), stream.read(&mut buf))
+                .await
+                .expect("response")
+                .unwrap();
+            assert!(n > 0, "connection closed before response");
+            response.extend(&buf[..n]);
+        }
+
+        String::from_utf8_lossy(&response).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive() {
+        let mut stream = TcpStream::connect(serve_one()).await.unwrap();
+
+        // Pipelined.
+        stream
+            .write_all(b"GET /one HTTP/1.1\r\n\r\nGET /two HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let response = read_until(&mut stream, "hello /two").await;
+        assert!(response.contains("hello /one"));
+        assert_eq!(response.matches("HTTP/1.1 200").count(), 2);
+
+        // Sequential, on the same connection.
+        stream
+            .write_all(b"GET /three HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let response = read_until(&mut stream, "hello /three").await;
+        assert!(response.contains("connection: close"));
+
+        // Server closed the connection.
+        let mut buf = vec![];
+        let n = timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+            .await
+            .expect("close")
+            .unwrap();
+        assert_eq!(n, 0);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_encoding_and_content_length() {
+        let mut stream = TcpStream::connect(serve_one()).await.unwrap();
+
+        // Framed differently by proxies which prefer Content-Length, so the next request is never served.
+        stream
+            .write_all(
+                b"POST /one HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 5\r\n\r\n0\r\n\r\n\
+                GET /two HTTP/1.1\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let response = read_until(&mut stream, "</html>").await;
+        assert!(response.starts_with("HTTP/1.1 400"));
+        assert!(response.contains("connection: close"));
+
+        let mut buf = vec![];
+        timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+            .await
+            .expect("close")
+            .unwrap();
+        let response = response + &String::from_utf8_lossy(&buf);
+        assert!(!response.contains("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_pipelining_order() {
+        let mut stream = TcpStream::connect(serve_one()).await.unwrap();
+
+        // The second request finishes first.
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\n\r\nGET /fast HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let response = read_until(&mut stream, "hello /fast").await;
+        let response = if response.contains("hello /slow") {
+            response
+        } else {
+            response + &read_until(&mut stream, "hello /slow").await
+        };
+
+        assert!(response.find("hello /slow").unwrap() < response.find("hello /fast").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pipelining_unsafe_serialized() {
+        let mut stream = TcpStream::connect(serve(Ordered.wildcard("/")))
+            .await
+            .unwrap();
+
+        stream
+            .write_all(
+                b"POST /slow HTTP/1.1\r\nContent-Length: 0\r\n\r\n\
+                DELETE /fast HTTP/1.1\r\n\r\n\
+                GET /last HTTP/1.1\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        while !response.contains("hello /last") {
+            response += &read_until(&mut stream, "hello").await;
+        }
+
+        assert_eq!(
+            *EVENTS.lock().unwrap(),
+            vec![
+                "start POST /slow",
+                "end POST /slow",
+                "start DELETE /fast",
+                "end DELETE /fast",
+                "start GET /last",
+                "end GET /last",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_version_not_supported() {
+        let mut stream = TcpStream::connect(serve_one()).await.unwrap();
+
+        stream
+            .write_all(b"GET /one HTTP/9.9\r\n\r\n")
+            .await
+            .unwrap();
+        let response = read_until(&mut stream, "</html>").await;
+        assert!(response.starts_with("HTTP/1.1 505"));
+        assert!(response.contains("connection: close"));
+        assert!(!response.contains("hello /one"));
+    }
+
+    #[tokio::test]
+    async fn test_tls() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let dir = TempDir::new("tls").unwrap();
+        let certificate = dir.path().join("cert.pem");
+        let private_key = dir.path().join("key.pem");
+        std::fs::write(&certificate, cert.cert.pem()).unwrap();
+        std::fs::write(&private_key, cert.key_pair.serialize_pem()).unwrap();
+
+        let acceptor = TlsConfig {
+            certificate,
+            private_key,
+        }
+        .acceptor()
+        .unwrap();
+
+        let router = Arc::new(Router::new(vec![Hello.route("/")]).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            Server::handle_connection(router, stream, peer_addr, Some(acceptor))
+                .await
+                .unwrap();
+        });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.cert.der().clone()).unwrap();
+        let mut config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .expect("tls handshake");
+        assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"http/1.1"[..]));
+
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+
+        let response = read_until(&mut stream, "hello /").await;
+        assert!(response.starts_with("HTTP/1.1 200"));
     }
 }
@@ -0,0 +1,309 @@
+//! Server-side session storage.
+//!
+//! By default, sessions are encrypted and stored in the `rwf_session` cookie. Large sessions,
+//! or sessions which must be revocable, can be stored on the server instead, e.g. in the database
+//! or Redis, by implementing [`SessionStore`] and setting `session_store = "server"` in the configuration.
+//! The client then only receives a random session identifier, stored in an encrypted cookie.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use super::{CookieBuilder, Cookies, Error};
+use crate::config::{get_config, SessionStorage};
+use crate::controller::Session;
+
+static STORE: OnceCell<Arc<dyn SessionStore>> = OnceCell::new();
+
+/// Name of the cookie holding the server-side session identifier.
+const SESSION_ID_COOKIE: &str = "rwf_sid";
+
+/// Storage for server-side sessions.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// struct RedisStore { /* ... */ }
+///
+/// #[async_trait]
+/// impl SessionStore for RedisStore {
+///     async fn load(&self, id: &str) -> Result<Option<Session>, Error> { /* GET */ }
+///     async fn save(&self, id: &str, session: &Session) -> Result<(), Error> { /* SET */ }
+///     async fn destroy(&self, id: &str) -> Result<(), Error> { /* DEL */ }
+/// }
+///
+/// set_session_store(RedisStore::new());
+/// ```
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Load the session with this identifier, if it exists.
+    async fn load(&self, id: &str) -> Result<Option<Session>, Error>;
+
+    /// Create or update the session with this identifier.
+    async fn save(&self, id: &str, session: &Session) -> Result<(), Error>;
+
+    /// Delete the session with this identifier.
+    async fn destroy(&self, id: &str) -> Result<(), Error>;
+}
+
+/// Sessions stored in memory. Sessions are lost when the server restarts
+/// and aren't shared between multiple instances of the application.
+#[derive(Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored sessions.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().len()
+    }
+
+    /// No sessions are stored.
+    pub fn is_empty(&self) -> bool {
+        self.sessions.lock().is_empty()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemoryStore {
+    async fn load(&self, id: &str) -> Result<Option<Session>, Error> {
+        Ok(self.sessions.lock().get(id).cloned())
+    }
+
+    async fn save(&self, id: &str, session: &Session) -> Result<(), Error> {
+        self.sessions.lock().insert(id.to_string(), session.clone());
+        Ok(())
+    }
+
+    async fn destroy(&self, id: &str) -> Result<(), Error> {
+        self.sessions.lock().remove(id);
+        Ok(())
+    }
+}
+
+/// Set the store used for server-side sessions. Can only be set once,
+/// before the server starts. Returns `false` if a store is already set.
+pub fn set_session_store(store: impl SessionStore + 'static) -> bool {
+    STORE.set(Arc::new(store)).is_ok()
+}
+
+/// Get the store used for sessions. If sessions are stored in cookies, `None` is returned.
+///
+/// If sessions are stored on the server but no store has been set with
+/// [`set_session_store`], a [`MemoryStore`] is used.
+pub fn get_session_store() -> Option<Arc<dyn SessionStore>> {
+    match get_config().general.session_store {
+        SessionStorage::Cookie => None,
+        SessionStorage::Server => Some(STORE.get_or_init(|| Arc::new(MemoryStore::new())).clone()),
+    }
+}
+
+/// Load the session referenced by the request cookies, along with its server-side
+/// identifier, if any.
+///
+/// Identifiers which aren't in the store, e.g. made up by the client, or whose session expired,
+/// are discarded, so a new one is issued when the session is saved.
+pub(crate) async fn load_session(
+    store: Option<&dyn SessionStore>,
+    cookies: &Cookies,
+) -> Result<(Option<Session>, Option<String>), Error> {
+    let store = match store {
+        Some(store) => store,
+        None => return Ok((cookies.get_session()?, None)),
+    };
+
+    let id = match cookies.get_private(SESSION_ID_COOKIE)? {
+        Some(cookie) => cookie.value().to_string(),
+        None => return Ok((None, None)),
+    };
+
+    match store.load(&id).await? {
+        Some(session) if session.expired() => {
+            store.destroy(&id).await?;
+            Ok((None, None))
+        }
+
+        Some(session) => Ok((Some(session), Some(id))),
+        None => Ok((None, None)),
+    }
+}
+
+/// Save the session and set the cookie the client needs to send it back.
+///
+/// If the user changed, e.g. after logging in or out, the session previously stored under `id`
+/// is destroyed and the session is saved under a new identifier, preventing session fixation.
+pub(crate) async fn save_session(
+    store: Option<&dyn SessionStore>,
+    id: Option<&str>,
+    previous: &Session,
+    session: &Session,
+    cookies: &mut Cookies,
+) -> Result<(), Error> {
+    let store = match store {
+        Some(store) => store,
+        None => return cookies.add_session(session),
+    };
+
+    let id = match id {
+        Some(id) if previous.session_id.user_id() == session.session_id.user_id() => id.to_string(),
+
+        id => {
+            if let Some(id) = id {
+                store.destroy(id).await?;
+            }
+
+            Uuid::new_v4().to_string()
+        }
+    };
+
+    store.save(&id, session).await?;
+
+    cookies.add_private(
+        CookieBuilder::new()
+            .name(SESSION_ID_COOKIE)
+            .value(id)
+            .expiration(OffsetDateTime::from_unix_timestamp(session.expiration)?)
+            .build(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::controller::SessionId;
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryStore::new();
+        let session =
+            Session::new_authenticated(serde_json::json!({"cart": [1, 2, 3]}), 5).unwrap();
+
+        // Nothing is stored until the session is saved.
+        let (loaded, id) = load_session(Some(&store), &Cookies::new()).await.unwrap();
+        assert!(loaded.is_none());
+        assert!(id.is_none());
+
+        let mut cookies = Cookies::new();
+        save_session(Some(&store), None, &session, &session, &mut cookies)
+            .await
+            .unwrap();
+        assert_eq!(store.len(), 1);
+        assert!(cookies.get("rwf_session").is_none());
+
+        // The client only gets the encrypted identifier.
+        let cookie = cookies.get(SESSION_ID_COOKIE).unwrap();
+        let cookies = Cookies::parse(&format!("{}={}", SESSION_ID_COOKIE, cookie.value()));
+        let (loaded, id) = load_session(Some(&store), &cookies).await.unwrap();
+        assert_eq!(loaded, Some(session.clone()));
+        let id = id.unwrap();
+
+        // Saving again with the same identifier updates the session in place.
+        let mut updated = session.clone();
+        updated.payload = serde_json::json!({"cart": [4]});
+        save_session(
+            Some(&store),
+            Some(&id),
+            &session,
+            &updated,
+            &mut Cookies::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.load(&id).await.unwrap(), Some(updated));
+
+        // Expired sessions are destroyed.
+        let mut expired = session.clone();
+        expired.expiration = 0;
+        store.save(&id, &expired).await.unwrap();
+        let (loaded, id) = load_session(Some(&store), &cookies).await.unwrap();
+        assert!(loaded.is_none());
+        assert!(id.is_none());
+        assert!(store.is_empty());
+    }
+
+    fn sid_cookie(cookies: &Cookies) -> Cookies {
+        let cookie = cookies.get(SESSION_ID_COOKIE).unwrap();
+        Cookies::parse(&format!("{}={}", SESSION_ID_COOKIE, cookie.value()))
+    }
+
+    #[tokio::test]
+    async fn test_session_rotation() {
+        let store = MemoryStore::new();
+        let guest = Session::anonymous();
+
+        let mut cookies = Cookies::new();
+        save_session(Some(&store), None, &guest, &guest, &mut cookies)
+            .await
+            .unwrap();
+        let (_, guest_id) = load_session(Some(&store), &sid_cookie(&cookies))
+            .await
+            .unwrap();
+        let guest_id = guest_id.unwrap();
+
+        // Logging in issues a new identifier and forgets the old one.
+        let user = Session::new_authenticated(serde_json::json!({}), 5).unwrap();
+        let mut cookies = Cookies::new();
+        save_session(Some(&store), Some(&guest_id), &guest, &user, &mut cookies)
+            .await
+            .unwrap();
+        assert_eq!(store.len(), 1);
+        assert!(store.load(&guest_id).await.unwrap().is_none());
+
+        let (loaded, user_id) = load_session(Some(&store), &sid_cookie(&cookies))
+            .await
+            .unwrap();
+        assert_eq!(loaded.unwrap().session_id, SessionId::Authenticated(5));
+        let user_id = user_id.unwrap();
+        assert_ne!(user_id, guest_id);
+
+        // Logging out destroys the user's session on the server.
+        save_session(
+            Some(&store),
+            Some(&user_id),
+            &user,
+            &Session::anonymous(),
+            &mut Cookies::new(),
+        )
+        .await
+        .unwrap();
+        assert!(store.load(&user_id).await.unwrap().is_none());
+
+        // Identifiers the server didn't issue aren't accepted.
+        let mut forged = Cookies::new();
+        forged
+            .add_private(
+                CookieBuilder::new()
+                    .name(SESSION_ID_COOKIE)
+                    .value("attacker-chosen")
+                    .build(),
+            )
+            .unwrap();
+        let (loaded, id) = load_session(Some(&store), &sid_cookie(&forged))
+            .await
+            .unwrap();
+        assert!(loaded.is_none());
+        assert!(id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cookie_store() {
+        let session = Session::new("payload").unwrap();
+        let mut cookies = Cookies::new();
+        save_session(None, None, &session, &session, &mut cookies)
+            .await
+            .unwrap();
+        assert!(cookies.get("rwf_session").is_some());
+        assert!(cookies.get(SESSION_ID_COOKIE).is_none());
+    }
+}
@@ -1,7 +1,8 @@
 //! WebSocket protocol implementation.
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
-use super::Error;
+use super::{Error, Request, Response};
+use crate::config::get_config;
 use crate::view::TurboStream;
 
 use std::marker::Unpin;
@@ -31,6 +32,31 @@ impl Headers {
             version: version.to_string(),
         })
     }
+
+    /// Value of the `Sec-WebSocket-Accept` header sent back to the client.
+    pub fn accept(&self) -> String {
+        use base64::{engine::general_purpose, Engine as _};
+        use sha1::{Digest, Sha1};
+
+        let accept = self.key.clone() + "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+        let digest = Sha1::digest(accept.as_bytes());
+        general_purpose::STANDARD.encode(digest)
+    }
+}
+
+/// Respond to the WebSocket upgrade request with `101 - Switching Protocols`.
+///
+/// If the request isn't a valid WebSocket upgrade, `400 - Bad Request` is returned instead.
+pub fn handshake(request: &Request) -> Response {
+    if !request.upgrade_websocket() {
+        return Response::bad_request();
+    }
+
+    match Headers::from_http_request(request) {
+        Ok(headers) => Response::switching_protocols("websocket")
+            .header("sec-websocket-accept", headers.accept()),
+        Err(_) => Response::bad_request(),
+    }
 }
 
 /// WebSocket message container.
@@ -41,11 +67,31 @@ pub struct DataFrame {
     message: Option<Message>,
 }
 
+/// Close code sent when the message is larger than allowed.
+pub const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+
 impl DataFrame {
-    /// Read a WebSocket message from the TCP stream.
+    /// Read a WebSocket frame from the TCP stream.
+    ///
+    /// Frames larger than [`crate::config::WebsocketConfig::max_message_size`] are rejected
+    /// with [`Error::MessageTooBig`].
     pub async fn read(stream: &mut (impl AsyncRead + Unpin)) -> Result<Self, Error> {
+        Self::read_with_limit(stream, get_config().websocket.max_message_size).await
+    }
+
+    /// Read a WebSocket frame from the TCP stream, rejecting frames
+    /// larger than `max_len` bytes before reading them into memory.
+    pub async fn read_with_limit(
+        stream: &mut (impl AsyncRead + Unpin),
+        max_len: usize,
+    ) -> Result<Self, Error> {
         let header = Header::read(stream).await?;
         let meta = Meta::read(stream).await?;
+
+        if meta.len() > max_len {
+            return Err(Error::MessageTooBig);
+        }
+
         let message = Message::read(&header, &meta, stream).await?;
 
         Ok(Self {
@@ -61,7 +107,7 @@ impl DataFrame {
         self.meta.send(stream).await?;
 
         if let Some(message) = self.message {
-            message.send(stream).await?;
+            message.send_payload(stream).await?;
         }
 
         Ok(())
@@ -85,6 +131,28 @@ impl DataFrame {
         self.header.is_ping()
     }
 
+    /// The client is closing the connection.
+    pub fn is_close(&self) -> bool {
+        self.header.op_code == OpCode::Close
+    }
+
+    /// Create new close message with the status code, e.g. [`CLOSE_MESSAGE_TOO_BIG`].
+    pub fn new_close(code: u16) -> Self {
+        let message = Message::Binary(code.to_be_bytes().to_vec());
+
+        Self {
+            header: Header {
+                fin: true,
+                op_code: OpCode::Close,
+            },
+            meta: Meta {
+                len: message.len(),
+                mask: None,
+            },
+            message: Some(message),
+        }
+    }
+
     /// Create new pong message.
     pub fn new_pong(ping: DataFrame) -> Self {
         let meta = Meta {
@@ -121,11 +189,79 @@ impl DataFrame {
     }
 }
 
+/// Reassembles messages split into multiple frames.
+///
+/// The total size of the message is limited, so clients can't exhaust
+/// the server memory by sending an endless stream of fragments.
+#[derive(Debug)]
+pub struct Fragments {
+    op_code: Option<OpCode>,
+    buf: Vec<u8>,
+    max_message_size: usize,
+}
+
+impl Fragments {
+    /// Create new reassembly buffer, accepting messages up to `max_message_size` bytes.
+    pub fn new(max_message_size: usize) -> Self {
+        Self {
+            op_code: None,
+            buf: vec![],
+            max_message_size,
+        }
+    }
+
+    /// Add a data frame. Returns the message once all of its frames have been received.
+    ///
+    /// If the message is larger than allowed, [`Error::MessageTooBig`] is returned
+    /// and the connection should be closed with [`CLOSE_MESSAGE_TOO_BIG`].
+    pub fn push(&mut self, frame: DataFrame) -> Result<Option<Message>, Error> {
+        let op_code = match (frame.header.op_code, self.op_code.is_some()) {
+            (OpCode::Continuation, true) => None,
+            (OpCode::Continuation, false) => {
+                return Err(Error::MalformedRequest("websocket continuation"))
+            }
+            (_, true) => return Err(Error::MalformedRequest("websocket fragment")),
+            (op_code, false) => Some(op_code),
+        };
+
+        let bytes = match frame.message {
+            Some(Message::Text(text)) => text.into_bytes(),
+            Some(Message::Binary(bytes)) => bytes,
+            None => vec![],
+        };
+
+        if self.buf.len() + bytes.len() > self.max_message_size {
+            self.op_code = None;
+            self.buf.clear();
+            return Err(Error::MessageTooBig);
+        }
+
+        if op_code.is_some() {
+            self.op_code = op_code;
+        }
+        self.buf.extend(bytes);
+
+        if !frame.header.fin {
+            return Ok(None);
+        }
+
+        let buf = std::mem::take(&mut self.buf);
+
+        match self.op_code.take() {
+            Some(OpCode::Text) => Ok(Some(Message::Text(
+                String::from_utf8_lossy(&buf).to_string(),
+            ))),
+            _ => Ok(Some(Message::Binary(buf))),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum OpCode {
     Continuation,
     Text,
     Binary,
+    Close,
     Ping,
     Pong,
 }
@@ -147,6 +283,7 @@ impl Header {
             0 => OpCode::Continuation,
             0x1 => OpCode::Text,
             0x2 => OpCode::Binary,
+            0x8 => OpCode::Close,
             0x9 => OpCode::Ping,
             0xA => OpCode::Pong,
             _ => return Err(Error::MalformedRequest("websocket control code")),
@@ -160,6 +297,7 @@ impl Header {
             OpCode::Continuation => 0,
             OpCode::Text => 0x1,
             OpCode::Binary => 0x2,
+            OpCode::Close => 0x8,
             OpCode::Ping => 0x9,
             OpCode::Pong => 0xA,
         };
@@ -298,6 +436,19 @@ impl Message {
         Message::Text(turbo_stream.render())
     }
 
+    /// Create a text message containing the value serialized to JSON.
+    pub fn from_json(value: &impl serde::Serialize) -> Result<Self, serde_json::Error> {
+        Ok(Message::Text(serde_json::to_string(value)?))
+    }
+
+    /// Deserialize the message from JSON.
+    pub fn to_json<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        Ok(match self {
+            Self::Text(text) => serde_json::from_str(text)?,
+            Self::Binary(bytes) => serde_json::from_slice(bytes)?,
+        })
+    }
+
     /// Get message length.
     pub fn len(&self) -> usize {
         match self {
@@ -328,7 +479,8 @@ impl Message {
             }
         }
 
-        if header.text() {
+        // Fragments of a text message are reassembled before decoding.
+        if header.text() && header.fin {
             Ok(Self::Text(String::from_utf8_lossy(&msg).to_string()))
         } else {
             Ok(Self::Binary(msg))
@@ -349,14 +501,19 @@ impl Message {
 
         header.send(stream).await?;
         meta.send(stream).await?;
+        self.send_payload(stream).await?;
+
+        stream.flush().await?;
 
+        Ok(())
+    }
+
+    async fn send_payload(&self, stream: &mut (impl AsyncWrite + Unpin)) -> Result<(), Error> {
         match self {
             Self::Text(text) => stream.write_all(text.as_bytes()).await?,
             Self::Binary(bytes) => stream.write_all(bytes.as_slice()).await?,
         };
 
-        stream.flush().await?;
-
         Ok(())
     }
 }
@@ -402,3 +559,87 @@ impl ToMessage for TurboStream {
         Message::Text(self.render())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![first, payload.len() as u8];
+        frame.extend(payload);
+        frame
+    }
+
+    async fn read_message(
+        mut stream: &[u8],
+        max_message_size: usize,
+    ) -> Result<Option<Message>, Error> {
+        let mut fragments = Fragments::new(max_message_size);
+
+        loop {
+            let frame = DataFrame::read_with_limit(&mut stream, max_message_size).await?;
+            if let Some(message) = fragments.push(frame)? {
+                return Ok(Some(message));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fragments() {
+        // Text, continuation, final continuation.
+        let mut stream = frame(0x01, b"hello ");
+        stream.extend(frame(0x00, "w\u{f6}".as_bytes()));
+        stream.extend(frame(0x80, b"rld"));
+
+        match read_message(&stream, 32).await.unwrap() {
+            Some(Message::Text(text)) => assert_eq!(text, "hello w\u{f6}rld"),
+            message => panic!("unexpected message: {:?}", message),
+        }
+
+        // Continuation without a first frame.
+        let stream = frame(0x80, b"rld");
+        assert!(read_message(&stream, 32).await.is_err());
+    }
+
+    #[test]
+    fn test_json() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct Move {
+            x: i64,
+            y: i64,
+        }
+
+        let message = Message::from_json(&Move { x: 1, y: 2 }).unwrap();
+        assert!(matches!(message, Message::Text(ref text) if text == r#"{"x":1,"y":2}"#));
+        assert_eq!(message.to_json::<Move>().unwrap(), Move { x: 1, y: 2 });
+
+        assert!(Message::Text(r#"{"x":1}"#.into())
+            .to_json::<Move>()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_message_too_big() {
+        let mut stream = frame(0x02, b"12345");
+        stream.extend(frame(0x00, b"12345"));
+        stream.extend(frame(0x80, b"12345"));
+
+        assert!(read_message(&stream, 15).await.unwrap().is_some());
+
+        // Each frame is within the limit, the message isn't.
+        let err = read_message(&stream, 12).await.unwrap_err();
+        assert!(matches!(err, Error::MessageTooBig));
+
+        // Single frame over the limit.
+        let stream = frame(0x82, b"123456789");
+        let err = read_message(&stream, 8).await.unwrap_err();
+        assert!(matches!(err, Error::MessageTooBig));
+
+        let mut close = vec![];
+        DataFrame::new_close(CLOSE_MESSAGE_TOO_BIG)
+            .flush(&mut close)
+            .await
+            .unwrap();
+        assert_eq!(close, vec![0x88, 0x02, 0x03, 0xF1]);
+    }
+}
@@ -0,0 +1,114 @@
+//! Implements aggregate functions, e.g. `COUNT("users"."id")` or `SUM("orders"."amount")`.
+use super::{Column, ToSql, ToValue, Value};
+
+/// Aggregate function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateFunction {
+    /// `COUNT`, number of rows where the column isn't `NULL`.
+    Count,
+    /// `SUM`, sum of the column values.
+    Sum,
+    /// `AVG`, average of the column values.
+    Avg,
+    /// `MIN`, smallest column value.
+    Min,
+    /// `MAX`, largest column value.
+    Max,
+}
+
+impl ToSql for AggregateFunction {
+    fn to_sql(&self) -> String {
+        use AggregateFunction::*;
+
+        match self {
+            Count => "COUNT",
+            Sum => "SUM",
+            Avg => "AVG",
+            Min => "MIN",
+            Max => "MAX",
+        }
+        .to_string()
+    }
+}
+
+/// Aggregate function computed over a column, e.g. `SUM("orders"."amount")`.
+///
+/// Add it to the selected columns with [`crate::model::Query::column`], using an alias,
+/// so it can be read back with [`crate::model::FromRow`].
+///
+/// # Example
+///
+/// ```
+/// # use rwf::model::{Column, ToSql};
+/// let total = Column::new("orders", "amount").sum();
+/// assert_eq!(total.to_sql(), r#"SUM("orders"."amount")"#);
+///
+/// let total = total.alias("total");
+/// assert_eq!(total.to_sql(), r#"SUM("orders"."amount") AS "total""#);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    function: AggregateFunction,
+    column: Column,
+}
+
+impl Aggregate {
+    /// Create an aggregate function over the column.
+    pub fn new(function: AggregateFunction, column: Column) -> Self {
+        Self { function, column }
+    }
+
+    /// Aggregate function.
+    pub fn function(&self) -> AggregateFunction {
+        self.function
+    }
+
+    /// Name the result of the aggregate function, so it can be selected as a column.
+    pub fn alias(self, name: impl ToString) -> Column {
+        Column::name(name).as_value(self)
+    }
+}
+
+impl ToSql for Aggregate {
+    fn to_sql(&self) -> String {
+        format!("{}({})", self.function.to_sql(), self.column.to_sql())
+    }
+}
+
+impl ToValue for Aggregate {
+    fn to_value(&self) -> Value {
+        Value::Aggregate(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{Row, Select};
+
+    #[test]
+    fn test_aggregates() {
+        let column = Column::new("t", "id");
+
+        assert_eq!(column.clone().count().to_sql(), r#"COUNT("t"."id")"#);
+        assert_eq!(column.clone().sum().to_sql(), r#"SUM("t"."id")"#);
+        assert_eq!(column.clone().avg().to_sql(), r#"AVG("t"."id")"#);
+        assert_eq!(column.clone().min().to_sql(), r#"MIN("t"."id")"#);
+        assert_eq!(column.clone().max().to_sql(), r#"MAX("t"."id")"#);
+        assert_eq!(column.count().function(), AggregateFunction::Count);
+    }
+
+    #[test]
+    fn test_aggregate_alias() {
+        let total = Column::new("orders", "amount").sum().alias("total");
+        assert_eq!(total.to_sql(), r#"SUM("orders"."amount") AS "total""#);
+
+        let select = Select::<Row>::new("orders", "id")
+            .select_additional(total)
+            .select_additional(Column::new("orders", "id").count().alias("orders"));
+        assert_eq!(
+            select.to_sql(),
+            r#"SELECT *, SUM("orders"."amount") AS "total", COUNT("orders"."id") AS "orders" FROM "orders""#
+        );
+    }
+}
@@ -1,9 +1,10 @@
 //! Represents the database table column.
-use super::{Escape, ToSql, ToValue, Value};
+use super::{Aggregate, AggregateFunction, EscapeIdentifier, ToSql, ToValue, Value};
 
 /// PostgreSQL table column.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Column {
+    schema_name: String,
     table_name: String,
     column_name: String,
     as_value: Option<Box<Value>>,
@@ -24,13 +25,21 @@ impl ToSql for Column {
         };
 
         if self.table_name.is_empty() {
-            format!(r#"{}"{}""#, as_value, self.column_name.escape())
+            format!(r#"{}"{}""#, as_value, self.column_name.escape_identifier())
+        } else if !self.schema_name.is_empty() {
+            format!(
+                r#"{}"{}"."{}"."{}""#,
+                as_value,
+                self.schema_name.escape_identifier(),
+                self.table_name.escape_identifier(),
+                self.column_name.escape_identifier(),
+            )
         } else {
             format!(
                 r#"{}"{}"."{}""#,
                 as_value,
-                self.table_name.escape(),
-                self.column_name.escape(),
+                self.table_name.escape_identifier(),
+                self.column_name.escape_identifier(),
             )
         }
     }
@@ -43,12 +52,34 @@ impl Column {
     /// to avoid ambiguous errors.
     pub fn new(table_name: impl ToString, column_name: impl ToString) -> Self {
         Self {
+            schema_name: String::new(),
             table_name: table_name.to_string(),
             column_name: column_name.to_string(),
             as_value: None,
         }
     }
 
+    /// Create new table column, qualified with the schema and the table name,
+    /// e.g. `"tenant_1"."users"."id"`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Column, ToSql};
+    /// let column = Column::with_schema("tenant_1", "users", "id");
+    /// assert_eq!(column.to_sql(), r#""tenant_1"."users"."id""#);
+    /// ```
+    pub fn with_schema(
+        schema_name: impl ToString,
+        table_name: impl ToString,
+        column_name: impl ToString,
+    ) -> Self {
+        Self {
+            schema_name: schema_name.to_string(),
+            ..Self::new(table_name, column_name)
+        }
+    }
+
     /// Create new table column, given the column name.
     ///
     /// Not fully qualified, so use with care, or you'll get
@@ -68,6 +99,7 @@ impl Column {
     }
 
     pub fn unqualify(mut self) -> Self {
+        self.schema_name.clear();
         self.table_name.clear();
         self
     }
@@ -76,6 +108,31 @@ impl Column {
         self.as_value = Some(Box::new(value.to_value()));
         self
     }
+
+    /// `COUNT` of the column values which aren't `NULL`.
+    pub fn count(self) -> Aggregate {
+        Aggregate::new(AggregateFunction::Count, self)
+    }
+
+    /// `SUM` of the column values.
+    pub fn sum(self) -> Aggregate {
+        Aggregate::new(AggregateFunction::Sum, self)
+    }
+
+    /// `AVG` of the column values.
+    pub fn avg(self) -> Aggregate {
+        Aggregate::new(AggregateFunction::Avg, self)
+    }
+
+    /// `MIN` of the column values.
+    pub fn min(self) -> Aggregate {
+        Aggregate::new(AggregateFunction::Min, self)
+    }
+
+    /// `MAX` of the column values.
+    pub fn max(self) -> Aggregate {
+        Aggregate::new(AggregateFunction::Max, self)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -151,7 +208,7 @@ impl ToSql for Columns {
 
             if self.columns.is_empty() || self.all {
                 if let Some(ref table_name) = self.table_name {
-                    columns.push(format!(r#""{}".*"#, table_name));
+                    columns.push(format!(r#""{}".*"#, table_name.escape_identifier()));
                 } else {
                     columns.push("*".to_string());
                 }
@@ -185,3 +242,41 @@ impl ToColumn for Column {
         self.clone()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_with_schema() {
+        let column = Column::with_schema("tenant_1", "users", "email");
+        assert_eq!(column.to_sql(), r#""tenant_1"."users"."email""#);
+        assert!(column.qualified());
+
+        let column = Column::with_schema("ten'ant", "us'ers", "em'ail");
+        assert_eq!(column.to_sql(), r#""ten'ant"."us'ers"."em'ail""#);
+
+        let column = Column::with_schema("tenant_1", "users", "email").unqualify();
+        assert_eq!(column.to_sql(), r#""email""#);
+
+        assert_eq!(Column::new("users", "email").to_sql(), r#""users"."email""#);
+    }
+
+    #[test]
+    fn test_escape_identifiers() {
+        let column = Column::new("t", "name\"; DROP TABLE users; --");
+        assert_eq!(column.to_sql(), r#""t"."name""; DROP TABLE users; --""#);
+
+        let column = Column::new("t\".\"x", "name");
+        assert_eq!(column.to_sql(), r#""t"".""x"."name""#);
+
+        let column = Column::name("\"");
+        assert_eq!(column.to_sql(), "\"\"\"\"");
+
+        let column = "na\"me".to_column().qualify("us\"ers");
+        assert_eq!(column.to_sql(), r#""us""ers"."na""me""#);
+
+        let columns = Columns::default().table_name("us\"ers");
+        assert_eq!(columns.to_sql(), r#""us""ers".*"#);
+    }
+}
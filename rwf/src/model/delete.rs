@@ -0,0 +1,108 @@
+//! Implements the `DELETE` statement.
+use super::{Error, EscapeIdentifier, FromRow, Model, Placeholders, Select, ToSql, WhereClause};
+use std::marker::PhantomData;
+
+/// `DELETE` statement.
+///
+/// To avoid deleting all rows in the table by mistake, a statement without a filter
+/// can't be executed unless [`Delete::all`] is called.
+///
+/// # Example
+///
+/// ```ignore
+/// let deleted = User::filter("banned", true)
+///     .delete()
+///     .fetch_all(&mut conn)
+///     .await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Delete<T> {
+    table_name: String,
+    pub placeholders: Placeholders,
+    where_clause: WhereClause,
+    all: bool,
+    returning: bool,
+    marker: PhantomData<T>,
+}
+
+impl<T: Model> Delete<T> {
+    /// Delete rows from the model's table. A filter must be added with [`Self::filter`],
+    /// or [`Self::all`] called, before the statement can be executed.
+    pub fn new() -> Self {
+        Self {
+            table_name: T::table_name().to_string(),
+            placeholders: Placeholders::new(),
+            where_clause: WhereClause::default(),
+            all: false,
+            returning: true,
+            marker: PhantomData,
+        }
+    }
+
+    /// Only delete rows matching the filter. Values in the filter must be
+    /// registered in [`Self::placeholders`].
+    pub fn filter(mut self, where_clause: WhereClause) -> Self {
+        self.where_clause.merge(where_clause);
+        self
+    }
+
+    /// Allow deleting all rows in the table if there is no filter.
+    pub fn all(mut self) -> Self {
+        self.all = true;
+        self
+    }
+
+    /// Don't return the deleted rows. Used when only the number
+    /// of affected rows is needed.
+    pub fn without_returning(mut self) -> Self {
+        self.returning = false;
+        self
+    }
+}
+
+impl<T: Model> Default for Delete<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Delete<T> {
+    /// Check the statement is safe to execute, i.e. it has a filter or deleting all rows
+    /// is explicitly allowed.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.where_clause.is_empty() && !self.all {
+            Err(Error::ValueError(
+                "delete",
+                format!(
+                    "refusing to delete all rows from \"{}\" without a filter, call .all() to allow it",
+                    self.table_name
+                ),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<T: Model> From<Select<T>> for Delete<T> {
+    fn from(select: Select<T>) -> Delete<T> {
+        let mut delete = Delete::new();
+        delete.where_clause = select.where_clause;
+        delete.placeholders = select.placeholders;
+
+        delete
+    }
+}
+
+impl<T: FromRow> ToSql for Delete<T> {
+    fn to_sql(&self) -> String {
+        let returning = if self.returning { " RETURNING *" } else { "" };
+
+        format!(
+            r#"DELETE FROM "{}"{}{}"#,
+            self.table_name.escape_identifier(),
+            self.where_clause.to_sql(),
+            returning,
+        )
+    }
+}
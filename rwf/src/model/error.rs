@@ -25,6 +25,12 @@ pub enum Error {
     #[error("pool timeout")]
     PoolTimeout,
 
+    #[error("lock timeout")]
+    LockTimeout,
+
+    #[error("query must run inside a transaction")]
+    TransactionRequired,
+
     #[error("pool not configured")]
     PoolNotConfigured,
 
@@ -50,9 +56,54 @@ pub enum Error {
 }
 
 impl Error {
+    /// Postgres gave up waiting for a lock, e.g. because of `lock_timeout`.
+    pub(crate) fn lock_not_available(&self) -> bool {
+        match self {
+            Error::DatabaseError(err) => err
+                .code()
+                .map(|code| code.code() == "55P03")
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
     pub fn boxed(self) -> Box<Self> {
         Box::new(self)
     }
+
+    /// The error is likely temporary and the query can be retried,
+    /// e.g. the connection was lost or the transaction failed to serialize.
+    pub fn transient(&self) -> bool {
+        match self {
+            Error::DatabaseError(err) => {
+                if err.is_closed() {
+                    return true;
+                }
+
+                match err.code() {
+                    Some(code) => {
+                        let code = code.code();
+                        // serialization_failure, deadlock_detected, connection_exception class,
+                        // admin_shutdown, crash_shutdown, cannot_connect_now.
+                        matches!(code, "40001" | "40P01" | "57P01" | "57P02" | "57P03")
+                            || code.starts_with("08")
+                    }
+
+                    // Errors without a SQLSTATE don't come from the database, e.g. connection reset.
+                    None => {
+                        use std::error::Error as _;
+                        err.source()
+                            .map(|source| source.is::<std::io::Error>())
+                            .unwrap_or(false)
+                    }
+                }
+            }
+
+            Error::IoError(_) => true,
+
+            _ => false,
+        }
+    }
 }
 
 static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#""(.*)""#).unwrap());
@@ -53,3 +53,26 @@ impl Escape for &str {
         self.to_string().escape()
     }
 }
+
+/// Escape an identifier, e.g. a table or column name, to be placed
+/// between double quotes.
+///
+/// Unlike [`Escape`], only double quotes are doubled, so single quotes
+/// remain part of the name.
+///
+/// # Example
+///
+/// ```
+/// use rwf::model::EscapeIdentifier;
+///
+/// assert_eq!(r#"ten'ant"users"#.escape_identifier(), r#"ten'ant""users"#);
+/// ```
+pub trait EscapeIdentifier {
+    fn escape_identifier(&self) -> String;
+}
+
+impl EscapeIdentifier for str {
+    fn escape_identifier(&self) -> String {
+        self.replace('"', "\"\"")
+    }
+}
@@ -1,5 +1,29 @@
 //! Implements the `WHERE` clause for `SELECT`, `UPDATE`, and `DELETE` statements.
-use super::{Column, ToSql, ToValue, Value};
+use super::{Column, Error, ToSql, ToValue, Value};
+
+/// Types values can be cast to with [`Filter::add_typed`].
+const CASTS: &[&str] = &[
+    "bigint",
+    "boolean",
+    "bytea",
+    "cidr",
+    "date",
+    "double precision",
+    "inet",
+    "integer",
+    "interval",
+    "json",
+    "jsonb",
+    "numeric",
+    "real",
+    "smallint",
+    "text",
+    "time",
+    "timestamp",
+    "timestamptz",
+    "uuid",
+    "varchar",
+];
 
 /// The WHERE clause of a SQL query.
 #[derive(Debug, Default, Clone)]
@@ -7,7 +31,7 @@ pub struct WhereClause {
     filter: Filter,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 enum Comparison {
     /// x = 1
     Equal((Column, Value)),
@@ -15,10 +39,16 @@ enum Comparison {
     In((Column, Value)),
     /// X NOT IN (1, 2, 3)
     NotIn((Column, Value)),
+    /// x IN (1, 2, 3), using standard SQL syntax
+    InList((Column, Vec<Value>)),
+    /// x NOT IN (1, 2, 3), using standard SQL syntax
+    NotInList((Column, Vec<Value>)),
     /// x <> 1
     NotEqual((Column, Value)),
     /// (x = 1 AND y = 2)
     Filter(Filter),
+    /// NOT (x = 1 OR y = 2)
+    Not(Filter),
     /// x > 1
     GreaterThan((Column, Value)),
     /// x < 1
@@ -27,22 +57,88 @@ enum Comparison {
     GreaterEqualThan((Column, Value)),
     /// x <= 1
     LesserEqualThan((Column, Value)),
+    /// a.x = b.y
+    ColumnEqual((Column, Column)),
+    /// a.x <> b.y
+    ColumnNotEqual((Column, Column)),
+    /// x BETWEEN 1 AND 5
+    Between((Column, Value, Value)),
+    /// x NOT BETWEEN 1 AND 5
+    NotBetween((Column, Value, Value)),
+    /// x IS NULL
+    IsNull(Column),
+    /// x IS NOT NULL
+    IsNotNull(Column),
+    /// x LIKE '%foo%'
+    Like((Column, Value)),
+    /// x ILIKE '%foo%'
+    ILike((Column, Value)),
+    /// x NOT LIKE '%foo%'
+    NotLike((Column, Value)),
+    /// (x, y) > (1, 2)
+    Row((Vec<Column>, &'static str, Vec<Value>)),
+    /// x ? 'key'
+    JsonHasKey((Column, Value)),
+    /// x ->> 'key' = 'value'
+    JsonPathEqual((Column, Value, Value)),
+    /// x = '2024-01-01'::text::date
+    TypedEqual((Column, Value, String)),
+    /// Always matches.
+    True,
+    /// Never matches, e.g. OR of no predicates.
+    False,
 }
 
 impl Comparison {
-    fn placeholder(&self) -> bool {
+    fn placeholders(&self) -> usize {
+        self.clone()
+            .values_mut()
+            .iter()
+            .filter(|v| v.placeholder())
+            .count()
+    }
+
+    fn values_mut(&mut self) -> Vec<&mut Value> {
         use Comparison::*;
 
         match self {
-            Equal((_, v)) => v.placeholder(),
-            In((_, v)) => v.placeholder(),
-            NotIn((_, v)) => v.placeholder(),
-            NotEqual((_, v)) => v.placeholder(),
-            GreaterThan((_, v)) => v.placeholder(),
-            LesserThan((_, v)) => v.placeholder(),
-            GreaterEqualThan((_, v)) => v.placeholder(),
-            LesserEqualThan((_, v)) => v.placeholder(),
-            _ => false,
+            Equal((_, v))
+            | In((_, v))
+            | NotIn((_, v))
+            | NotEqual((_, v))
+            | GreaterThan((_, v))
+            | LesserThan((_, v))
+            | GreaterEqualThan((_, v))
+            | LesserEqualThan((_, v))
+            | Like((_, v))
+            | ILike((_, v))
+            | NotLike((_, v))
+            | JsonHasKey((_, v))
+            | TypedEqual((_, v, _)) => vec![v],
+            Between((_, low, high)) | NotBetween((_, low, high)) => vec![low, high],
+            JsonPathEqual((_, path, value)) => vec![path, value],
+            Row((_, _, values)) | InList((_, values)) | NotInList((_, values)) => {
+                values.iter_mut().collect()
+            }
+            Filter(_) | Not(_) | ColumnEqual(_) | ColumnNotEqual(_) | IsNull(_) | IsNotNull(_)
+            | True | False => vec![],
+        }
+    }
+
+    /// Highest placeholder number used in the predicate, or 0 if none are used.
+    fn max_placeholder(&self) -> i32 {
+        match self {
+            Comparison::Filter(filter) | Comparison::Not(filter) => filter.max_placeholder(),
+            op => op
+                .clone()
+                .values_mut()
+                .into_iter()
+                .map(|value| match value {
+                    Value::Placeholder(number) => *number,
+                    _ => 0,
+                })
+                .max()
+                .unwrap_or(0),
         }
     }
 }
@@ -60,7 +156,17 @@ impl ToSql for Comparison {
                 }
             }
             In((column, value)) => format!("{} = ANY({})", column.to_sql(), value.to_sql()),
-            NotIn((column, value)) => format!("{} <> ANY({})", column.to_sql(), value.to_sql()),
+            NotIn((column, value)) => format!("{} <> ALL({})", column.to_sql(), value.to_sql()),
+            // IN () is a syntax error. An empty list matches nothing, so NOT IN
+            // an empty list matches everything.
+            InList((_, values)) if values.is_empty() => "false".to_string(),
+            NotInList((_, values)) if values.is_empty() => "true".to_string(),
+            InList((column, values)) => {
+                format!("{} IN ({})", column.to_sql(), list_to_sql(values))
+            }
+            NotInList((column, values)) => {
+                format!("{} NOT IN ({})", column.to_sql(), list_to_sql(values))
+            }
             NotEqual((column, value)) => {
                 if value.is_null() {
                     format!("{} IS NOT NULL", column.to_sql())
@@ -69,6 +175,7 @@ impl ToSql for Comparison {
                 }
             }
             Filter(filter) => format!("({})", filter.to_sql()),
+            Not(filter) => format!("NOT ({})", filter.to_sql()),
             GreaterThan((column, value)) => format!("{} > {}", column.to_sql(), value.to_sql()),
             LesserThan((column, value)) => format!("{} < {}", column.to_sql(), value.to_sql()),
             GreaterEqualThan((column, value)) => {
@@ -77,11 +184,99 @@ impl ToSql for Comparison {
             LesserEqualThan((column, value)) => {
                 format!("{} <= {}", column.to_sql(), value.to_sql())
             }
+            ColumnEqual((left, right)) => format!("{} = {}", left.to_sql(), right.to_sql()),
+            ColumnNotEqual((left, right)) => format!("{} <> {}", left.to_sql(), right.to_sql()),
+            Between((column, low, high)) => format!(
+                "{} BETWEEN {} AND {}",
+                column.to_sql(),
+                low.to_sql(),
+                high.to_sql()
+            ),
+            NotBetween((column, low, high)) => format!(
+                "{} NOT BETWEEN {} AND {}",
+                column.to_sql(),
+                low.to_sql(),
+                high.to_sql()
+            ),
+            IsNull(column) => format!("{} IS NULL", column.to_sql()),
+            IsNotNull(column) => format!("{} IS NOT NULL", column.to_sql()),
+            Like((column, value)) => format!("{} LIKE {}", column.to_sql(), value.to_sql()),
+            ILike((column, value)) => format!("{} ILIKE {}", column.to_sql(), value.to_sql()),
+            NotLike((column, value)) => {
+                format!("{} NOT LIKE {}", column.to_sql(), value.to_sql())
+            }
+            Row((columns, op, values)) => format!(
+                "({}) {} ({})",
+                columns
+                    .iter()
+                    .map(|column| column.to_sql())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                op,
+                values
+                    .iter()
+                    .map(|value| value.to_sql())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            JsonHasKey((column, key)) => format!("{} ? {}", column.to_sql(), key.to_sql()),
+            JsonPathEqual((column, path, value)) => format!(
+                "{} ->> {} = {}",
+                column.to_sql(),
+                path.to_sql(),
+                value.to_sql()
+            ),
+            // NULL never equals anything, whatever its type.
+            TypedEqual((column, value, _)) if value.is_null() => {
+                format!("{} IS NULL", column.to_sql())
+            }
+            TypedEqual((column, value, cast)) => {
+                format!("{} = {}::text::{}", column.to_sql(), value.to_sql(), cast)
+            }
+            True => "true".to_string(),
+            False => "false".to_string(),
         }
     }
 }
 
+fn list_to_sql(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_sql())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl WhereClause {
+    /// Build equality predicates from URL query parameters, e.g. `?status=active&country=CA`.
+    ///
+    /// Only parameters matching one of the allowed columns are used, all others are ignored.
+    /// This allows clients to filter list endpoints without giving them access to sensitive
+    /// or unindexed columns. Values are escaped and inlined into the query, so the database can convert them to
+    /// the column type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{WhereClause, ToSql};
+    /// # use rwf::http::Query;
+    /// let query = Query::parse("status=active&password=hunter2");
+    /// let where_clause = WhereClause::from_query(&query, &["status", "country"]);
+    ///
+    /// assert_eq!(where_clause.to_sql(), r#" WHERE "status" = 'active'"#);
+    /// ```
+    pub fn from_query(query: &crate::http::Query, allowed_columns: &[&str]) -> Self {
+        let mut where_clause = Self::default();
+
+        for (name, value) in query.clone().into_iter() {
+            if allowed_columns.contains(&name.as_str()) {
+                where_clause.add(Column::name(&name), value.as_str());
+            }
+        }
+
+        where_clause
+    }
+
     /// Add predicates to the WHERE clause using OR operator.
     pub fn or(&mut self, filter: Filter) {
         self.filter = self.filter.or(filter);
@@ -102,12 +297,72 @@ impl WhereClause {
         self.filter.gt(column, value);
     }
 
+    /// Add a >= predicate.
+    pub fn gte(&mut self, column: Column, value: impl ToValue) {
+        self.filter.gte(column, value);
+    }
+
+    /// Add a < predicate.
+    pub fn lt(&mut self, column: Column, value: impl ToValue) {
+        self.filter.lt(column, value);
+    }
+
+    /// Add a <= predicate.
+    pub fn lte(&mut self, column: Column, value: impl ToValue) {
+        self.filter.lte(column, value);
+    }
+
     /// Append all predicates of the filter into the current WHERE clause, e.g.
     /// (x = 1) "concat" (y = 2 AND z = 3) becomes (x = 1 AND y = 2 AND z = 3).
     pub fn concat(&mut self, filter: Filter) {
         self.filter = self.filter.concat(filter);
     }
 
+    /// Combine another WHERE clause with this one using the AND operator.
+    ///
+    /// This allows several layers, e.g. middleware, to each add their own filters to the same query.
+    /// Placeholders in the other clause are renumbered to follow the ones already in this clause,
+    /// so their values should be bound after ours.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{WhereClause, Column, ToSql};
+    /// let mut tenant = WhereClause::default();
+    /// tenant.add(Column::name("tenant_id"), 5);
+    ///
+    /// let mut active = WhereClause::default();
+    /// active.add(Column::name("status"), "active");
+    ///
+    /// tenant.merge(active);
+    /// assert_eq!(tenant.to_sql(), r#" WHERE "tenant_id" = 5 AND "status" = 'active'"#);
+    /// ```
+    pub fn merge(&mut self, other: WhereClause) {
+        let mut other = other.filter;
+        other.shift_placeholders(self.filter.max_placeholder());
+        self.filter = self.filter.merge(other);
+    }
+
+    /// Renumber all placeholders by the offset, e.g. `$1` becomes `$3` with an offset of 2.
+    pub fn shift_placeholders(&mut self, offset: i32) {
+        self.filter.shift_placeholders(offset);
+    }
+
+    /// Fold constant predicates, see [`Filter::simplify`]. If the WHERE clause
+    /// always matches, it's removed entirely.
+    pub fn simplify(&mut self) {
+        self.filter = std::mem::take(&mut self.filter).simplify();
+
+        if self.filter.clauses == [Comparison::True] {
+            self.clear();
+        }
+    }
+
+    /// The WHERE clause has no predicates.
+    pub fn is_empty(&self) -> bool {
+        self.filter.is_empty()
+    }
+
     /// Remove all predicates.
     pub fn clear(&mut self) {
         self.filter.clauses.clear();
@@ -170,7 +425,7 @@ impl ToSql for JoinOp {
 /// WHERE x = 1 AND b = 2
 /// ```
 ///
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Filter {
     clauses: Vec<Comparison>,
     op: JoinOp,
@@ -193,20 +448,267 @@ impl Filter {
         self.clauses.is_empty()
     }
 
+    /// Negate the whole filter, e.g. `NOT (x = 1 OR y = 2)`. The result can be combined
+    /// with other filters like any other filter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql};
+    /// let mut banned = Filter::default();
+    /// banned.add(Column::name("status"), "banned");
+    ///
+    /// let mut active = Filter::default();
+    /// active.add(Column::name("active"), "yes");
+    ///
+    /// assert_eq!(
+    ///     active.and(banned.negate()).to_sql(),
+    ///     r#"("active" = 'yes') AND (NOT ("status" = 'banned'))"#
+    /// );
+    /// ```
+    pub fn negate(&self) -> Self {
+        if self.is_empty() {
+            return self.clone();
+        }
+
+        Filter {
+            clauses: vec![Comparison::Not(self.clone())],
+            op: JoinOp::And,
+        }
+    }
+
     /// Add a predicate to the filter, using the AND operator.
+    ///
+    /// Comparing to [`Value::Null`] renders as `IS NULL`, since `= NULL` never matches,
+    /// and comparing to a [`Value::Range`] renders as `BETWEEN`.
+    ///
+    /// A [`Value::Record`] checks membership, i.e. `= ANY(...)`, while a [`Value::List`] is compared
+    /// to the column as a whole array. Use [`Value::into_record`] and [`Value::into_list`] to choose.
     pub fn add(&mut self, column: Column, value: impl ToValue) {
         let value = value.to_value();
         match value {
             Value::Record(value) => {
                 self.clauses.push(Comparison::In((column, *value)));
             }
+            Value::Range((low, high)) => {
+                self.clauses
+                    .push(Comparison::Between((column, *low, *high)));
+            }
             value => {
                 self.clauses.push(Comparison::Equal((column, value)));
             }
         }
     }
 
+    /// Add a predicate matching any of the values, e.g. `(x = 1 OR x = 2)`, using the AND operator.
+    ///
+    /// This is equivalent to `x = ANY(...)`, but each value is compared separately,
+    /// which is useful when mixing with other operators.
+    /// If no values are given, the predicate matches nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.any_of(Column::name("status"), ["active", "pending"]);
+    ///
+    /// assert_eq!(filter.to_sql(), r#"("status" = 'active' OR "status" = 'pending')"#);
+    /// ```
+    pub fn any_of<T: ToValue>(&mut self, column: Column, values: impl IntoIterator<Item = T>) {
+        let clauses = values
+            .into_iter()
+            .map(|value| Comparison::Equal((column.clone(), value.to_value())))
+            .collect::<Vec<_>>();
+
+        if clauses.is_empty() {
+            self.clauses.push(Comparison::False);
+        } else {
+            self.clauses.push(Comparison::Filter(Filter {
+                clauses,
+                op: JoinOp::Or,
+            }));
+        }
+    }
+
+    /// Add an `IN` predicate, using the AND operator.
+    ///
+    /// Unlike `x = ANY(...)`, which compares against a Postgres array, this uses the standard
+    /// SQL syntax with each value rendered separately, e.g. `x IN (1, 2)`. If no values are given, the predicate matches nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.in_list(Column::name("id"), [1_i64, 2, 3]);
+    ///
+    /// assert_eq!(filter.to_sql(), r#""id" IN (1, 2, 3)"#);
+    /// ```
+    pub fn in_list<T: ToValue>(&mut self, column: Column, values: impl IntoIterator<Item = T>) {
+        self.clauses.push(Comparison::InList((
+            column,
+            values.into_iter().map(|value| value.to_value()).collect(),
+        )));
+    }
+
+    /// Add a `NOT IN` predicate, using the AND operator. If no values are given,
+    /// the predicate matches everything.
+    pub fn not_in_list<T: ToValue>(&mut self, column: Column, values: impl IntoIterator<Item = T>) {
+        self.clauses.push(Comparison::NotInList((
+            column,
+            values.into_iter().map(|value| value.to_value()).collect(),
+        )));
+    }
+
+    /// Add a predicate comparing two columns, e.g. `"orders"."user_id" = "users"."id"`,
+    /// using the AND operator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.column_eq(Column::new("orders", "user_id"), Column::new("users", "id"));
+    ///
+    /// assert_eq!(filter.to_sql(), r#""orders"."user_id" = "users"."id""#);
+    /// ```
+    pub fn column_eq(&mut self, left: Column, right: Column) {
+        self.clauses.push(Comparison::ColumnEqual((left, right)));
+    }
+
+    /// Add a predicate checking two columns are different, using the AND operator.
+    pub fn column_ne(&mut self, left: Column, right: Column) {
+        self.clauses.push(Comparison::ColumnNotEqual((left, right)));
+    }
+
+    /// Add a `BETWEEN` predicate, using the AND operator. Both bounds are inclusive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.between(Column::name("age"), 18, 65);
+    ///
+    /// assert_eq!(filter.to_sql(), r#""age" BETWEEN 18 AND 65"#);
+    /// ```
+    pub fn between(&mut self, column: Column, low: impl ToValue, high: impl ToValue) {
+        self.clauses.push(Comparison::Between((
+            column,
+            low.to_value(),
+            high.to_value(),
+        )));
+    }
+
+    /// Add a `NOT BETWEEN` predicate, using the AND operator.
+    pub fn not_between(&mut self, column: Column, low: impl ToValue, high: impl ToValue) {
+        self.clauses.push(Comparison::NotBetween((
+            column,
+            low.to_value(),
+            high.to_value(),
+        )));
+    }
+
+    /// Add a constant predicate, `true` or `false`, using the AND operator.
+    /// Use [`Self::simplify`] to fold it into the rest of the filter.
+    pub fn constant(&mut self, value: bool) {
+        self.clauses.push(if value {
+            Comparison::True
+        } else {
+            Comparison::False
+        });
+    }
+
+    /// Fold constant predicates, so the database doesn't evaluate predicates
+    /// which can't change the result:
+    ///
+    /// * `false AND x` becomes `false`, `true AND x` becomes `x`
+    /// * `true OR x` becomes `true`, `false OR x` becomes `x`
+    ///
+    /// Nested filters are simplified first, so constants propagate up. Predicates
+    /// comparing to placeholders are never removed, since their values are already bound to the query.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.add(Column::name("active"), "yes");
+    /// filter.any_of(Column::name("id"), Vec::<i64>::new()); // Matches nothing.
+    ///
+    /// assert_eq!(filter.simplify().to_sql(), "false");
+    /// ```
+    pub fn simplify(self) -> Self {
+        let (absorbing, identity) = match self.op {
+            JoinOp::And => (Comparison::False, Comparison::True),
+            JoinOp::Or => (Comparison::True, Comparison::False),
+        };
+
+        if self.clauses.is_empty() {
+            return self;
+        }
+
+        let mut clauses = vec![];
+        let mut absorbed = false;
+
+        for clause in self.clauses {
+            let clause = match clause {
+                Comparison::Filter(filter) => {
+                    let filter = filter.simplify();
+                    match filter.clauses.as_slice() {
+                        [Comparison::True] => Comparison::True,
+                        [Comparison::False] => Comparison::False,
+                        _ => Comparison::Filter(filter),
+                    }
+                }
+                Comparison::Not(filter) => {
+                    let filter = filter.simplify();
+                    match filter.clauses.as_slice() {
+                        [Comparison::True] => Comparison::False,
+                        [Comparison::False] => Comparison::True,
+                        _ => Comparison::Not(filter),
+                    }
+                }
+                clause => clause,
+            };
+
+            if clause == absorbing {
+                absorbed = true;
+            } else if clause != identity {
+                clauses.push(clause);
+            }
+        }
+
+        if absorbed {
+            // Dropping a placeholder would leave its value bound without a matching `$n`.
+            clauses.retain(|clause| clause.max_placeholder() > 0);
+            clauses.push(absorbing);
+        } else if clauses.is_empty() {
+            // All predicates were equal to the identity, e.g. `true AND true`.
+            clauses.push(identity);
+        }
+
+        Filter {
+            clauses,
+            op: self.op,
+        }
+    }
+
+    /// Add an `IS NULL` predicate, using the AND operator.
+    pub fn is_null(&mut self, column: Column) {
+        self.clauses.push(Comparison::IsNull(column));
+    }
+
+    /// Add an `IS NOT NULL` predicate, using the AND operator.
+    pub fn is_not_null(&mut self, column: Column) {
+        self.clauses.push(Comparison::IsNotNull(column));
+    }
+
     /// Add a negated predicate to the filter, using the AND operator.
+    ///
+    /// Comparing to [`Value::Null`] renders as `IS NOT NULL`. A [`Value::Record`] renders as `<> ALL(...)`,
+    /// i.e. the column matches none of its values, and like [`Filter::add`], a [`Value::List`] is compared as a whole array.
     pub fn add_not(&mut self, column: Column, value: impl ToValue) {
         let value = value.to_value();
         match value {
@@ -239,6 +741,169 @@ impl Filter {
             .push(Comparison::LesserEqualThan((column, value.to_value())));
     }
 
+    /// Add a `LIKE` predicate, using the AND operator. `%` and `_` in the pattern
+    /// are wildcards.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.like(Column::name("name"), "%foo%");
+    ///
+    /// assert_eq!(filter.to_sql(), r#""name" LIKE '%foo%'"#);
+    /// ```
+    pub fn like(&mut self, column: Column, pattern: impl ToValue) {
+        self.clauses
+            .push(Comparison::Like((column, pattern.to_value())));
+    }
+
+    /// Add a case-insensitive `ILIKE` predicate, using the AND operator.
+    pub fn ilike(&mut self, column: Column, pattern: impl ToValue) {
+        self.clauses
+            .push(Comparison::ILike((column, pattern.to_value())));
+    }
+
+    /// Add a `NOT LIKE` predicate, using the AND operator.
+    pub fn not_like(&mut self, column: Column, pattern: impl ToValue) {
+        self.clauses
+            .push(Comparison::NotLike((column, pattern.to_value())));
+    }
+
+    /// Add a predicate checking a JSONB column has the top-level key, e.g. `"data" ? 'email'`,
+    /// using the AND operator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, Value, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.json_has_key(Column::name("data"), Value::Placeholder(1));
+    ///
+    /// assert_eq!(filter.to_sql(), r#""data" ? $1"#);
+    /// ```
+    pub fn json_has_key(&mut self, column: Column, key: impl ToValue) {
+        self.clauses
+            .push(Comparison::JsonHasKey((column, key.to_value())));
+    }
+
+    /// Add a predicate comparing the value of a key in a JSONB column, extracted as text,
+    /// e.g. `"data" ->> 'plan' = 'pro'`, using the AND operator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, Value, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.json_path_eq(Column::name("data"), Value::Placeholder(1), Value::Placeholder(2));
+    ///
+    /// assert_eq!(filter.to_sql(), r#""data" ->> $1 = $2"#);
+    /// ```
+    pub fn json_path_eq(&mut self, column: Column, path: impl ToValue, value: impl ToValue) {
+        self.clauses.push(Comparison::JsonPathEqual((
+            column,
+            path.to_value(),
+            value.to_value(),
+        )));
+    }
+
+    /// Add an equality predicate casting the value to a Postgres type, e.g. `"due_on" = $1::text::date`,
+    /// using the AND operator. Useful for types [`Value`] doesn't support yet.
+    ///
+    /// The type must be a built-in scalar type, e.g. `date` or `numeric`, or an array of one,
+    /// e.g. `date[]`. Other types return an error.
+    ///
+    /// The value is sent as text and converted by Postgres, so it must be a string,
+    /// e.g. `"2024-01-01"`, or a placeholder bound to one. `NULL` becomes `IS NULL`, like [`Filter::add`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.add_typed(Column::name("due_on"), "2024-01-01", "date").unwrap();
+    ///
+    /// assert_eq!(filter.to_sql(), r#""due_on" = '2024-01-01'::text::date"#);
+    /// assert!(filter.add_typed(Column::name("due_on"), "2024-01-01", "date; DROP TABLE users").is_err());
+    /// assert!(filter.add_typed(Column::name("amount"), 5, "numeric").is_err());
+    /// ```
+    pub fn add_typed(
+        &mut self,
+        column: Column,
+        value: impl ToValue,
+        cast: &str,
+    ) -> Result<(), Error> {
+        let value = Self::typed_value(value.to_value())?;
+        let cast = cast.trim().to_lowercase();
+        let base = cast.strip_suffix("[]").unwrap_or(&cast);
+
+        if !CASTS.contains(&base) {
+            return Err(Error::ValueError("cast", cast));
+        }
+
+        self.clauses
+            .push(Comparison::TypedEqual((column, value, cast)));
+        Ok(())
+    }
+
+    /// Check the value can be cast from text by [`Filter::add_typed`].
+    pub(crate) fn typed_value(value: Value) -> Result<Value, Error> {
+        match value {
+            Value::Optional(value) => match *value {
+                Some(value) => Self::typed_value(value),
+                None => Ok(Value::Null),
+            },
+            Value::String(_) | Value::Placeholder(_) | Value::Null => Ok(value),
+            value => Err(Error::ValueError("typed value", format!("{:?}", value))),
+        }
+    }
+
+    /// Add a row constructor comparison, e.g. `("created_at", "id") > ($1, $2)`,
+    /// using the AND operator.
+    ///
+    /// Rows are compared column by column, left to right, which makes this
+    /// the correct predicate for keyset pagination over composite keys. Each column
+    /// is paired with its value, so both rows always have the same size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, Value, ToSql};
+    /// let mut filter = Filter::default();
+    /// filter.row_gt(&[
+    ///     (Column::new("posts", "created_at"), Value::Placeholder(1)),
+    ///     (Column::new("posts", "id"), Value::Placeholder(2)),
+    /// ]);
+    ///
+    /// assert_eq!(filter.to_sql(), r#"("posts"."created_at", "posts"."id") > ($1, $2)"#);
+    /// ```
+    pub fn row_gt(&mut self, row: &[(Column, impl ToValue)]) {
+        self.row(row, ">");
+    }
+
+    /// Add a row constructor comparison using `>=`, see [`Self::row_gt`].
+    pub fn row_gte(&mut self, row: &[(Column, impl ToValue)]) {
+        self.row(row, ">=");
+    }
+
+    /// Add a row constructor comparison using `<`, see [`Self::row_gt`].
+    pub fn row_lt(&mut self, row: &[(Column, impl ToValue)]) {
+        self.row(row, "<");
+    }
+
+    /// Add a row constructor comparison using `<=`, see [`Self::row_gt`].
+    pub fn row_lte(&mut self, row: &[(Column, impl ToValue)]) {
+        self.row(row, "<=");
+    }
+
+    fn row(&mut self, row: &[(Column, impl ToValue)], op: &'static str) {
+        self.clauses.push(Comparison::Row((
+            row.iter().map(|(column, _)| column.clone()).collect(),
+            op,
+            row.iter().map(|(_, value)| value.to_value()).collect(),
+        )));
+    }
+
     /// Append all predicates of the filter into the current filter.
     pub fn concat(&self, filter: Filter) -> Self {
         // Concatenating filters with different operations, e.g. AND and OR
@@ -259,14 +924,8 @@ impl Filter {
         self.clauses
             .iter()
             .map(|op| match op {
-                Comparison::Filter(filter) => filter.placeholders(),
-                op => {
-                    if op.placeholder() {
-                        1
-                    } else {
-                        0
-                    }
-                }
+                Comparison::Filter(filter) | Comparison::Not(filter) => filter.placeholders(),
+                op => op.placeholders(),
             })
             .sum()
     }
@@ -291,6 +950,118 @@ impl Filter {
         (columns, values)
     }
 
+    /// Combine two filters with the AND operator, without nesting
+    /// filters that are already joined with AND.
+    fn merge(&self, filter: Filter) -> Self {
+        if filter.is_empty() {
+            return self.clone();
+        } else if self.is_empty() {
+            return filter;
+        }
+
+        let clauses = |filter: Filter| {
+            if filter.op == JoinOp::And || filter.clauses.len() == 1 {
+                filter.clauses
+            } else {
+                vec![Comparison::Filter(filter)]
+            }
+        };
+
+        let mut merged = clauses(self.clone());
+        merged.extend(clauses(filter));
+
+        Filter {
+            clauses: merged,
+            op: JoinOp::And,
+        }
+    }
+
+    /// Highest placeholder number used in the filter, or 0 if none are used.
+    fn max_placeholder(&self) -> i32 {
+        self.clauses
+            .iter()
+            .map(|op| op.max_placeholder())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Replace all literal values in the filter with placeholders, numbered in order
+    /// starting at `starting_id`, e.g. `$3`, `$4`, etc. The replaced values are appended to `values`,
+    /// so they can be bound to the placeholders.
+    ///
+    /// Returns the rewritten filter and the next free placeholder number.
+    /// `NULL`s, columns, functions and existing placeholders are left as-is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Filter, Column, ToSql, Value};
+    /// let mut filter = Filter::default();
+    /// filter.add(Column::name("email"), "test@test.com");
+    /// filter.gt(Column::name("age"), 18);
+    ///
+    /// let mut values = vec![];
+    /// let (filter, next) = filter.rewrite_placeholders(3, &mut values);
+    ///
+    /// assert_eq!(filter.to_sql(), r#""email" = $3 AND "age" > $4"#);
+    /// assert_eq!(next, 5);
+    /// assert_eq!(values, vec![Value::String("test@test.com".into()), Value::Int(18)]);
+    /// ```
+    pub fn rewrite_placeholders(
+        mut self,
+        starting_id: i32,
+        values: &mut Vec<Value>,
+    ) -> (Self, i32) {
+        let mut id = starting_id;
+
+        for op in self.clauses.iter_mut() {
+            match op {
+                Comparison::Filter(filter) | Comparison::Not(filter) => {
+                    let (rewritten, next) = std::mem::take(filter).rewrite_placeholders(id, values);
+                    *filter = rewritten;
+                    id = next;
+                }
+
+                op => {
+                    for value in op.values_mut() {
+                        match value {
+                            Value::Placeholder(_)
+                            | Value::Column(_)
+                            | Value::Function(_)
+                            | Value::Window(_)
+                            | Value::Aggregate(_) => (),
+                            value if value.is_null() => (),
+                            value => {
+                                values.push(std::mem::replace(value, Value::Placeholder(id)));
+                                id += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        (self, id)
+    }
+
+    /// Renumber all placeholders in the filter by the offset.
+    pub(crate) fn shift_placeholders(&mut self, offset: i32) {
+        for op in self.clauses.iter_mut() {
+            match op {
+                Comparison::Filter(filter) | Comparison::Not(filter) => {
+                    filter.shift_placeholders(offset)
+                }
+                op => {
+                    for value in op.values_mut() {
+                        if let Value::Placeholder(number) = value {
+                            *number += offset;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     fn join(&self, op: JoinOp, filter: Filter) -> Self {
         if self.is_empty() {
             filter
@@ -347,10 +1118,423 @@ mod test {
         let sql = filter.to_sql();
         assert_eq!(
             sql,
-            r#""table_name"."column_a" = 'value' AND "table_name"."column_b" <> 42 AND ("table_x"."column_y" <> ANY({56, 67}) OR "table_y"."column_x" = 'hello')"#
+            r#""table_name"."column_a" = 'value' AND "table_name"."column_b" <> 42 AND ("table_x"."column_y" <> ALL({56, 67}) OR "table_y"."column_x" = 'hello')"#
+        );
+    }
+
+    #[test]
+    fn test_negate() {
+        let mut a = Filter::default();
+        a.add(Column::name("x"), Value::Placeholder(1));
+
+        let mut b = Filter::default();
+        b.add(Column::name("y"), Value::Placeholder(2));
+        b.gt(Column::name("z"), Value::Placeholder(3));
+
+        let mut c = Filter::default();
+        c.add(Column::name("w"), Value::Placeholder(4));
+
+        // NOT (x = 1 OR (y = 2 AND z > 3))
+        let negated = a.or(b).negate();
+        assert_eq!(
+            negated.to_sql(),
+            r#"NOT (("x" = $1) OR ("y" = $2 AND "z" > $3))"#
+        );
+        assert_eq!(negated.placeholders(), 3);
+
+        let filter = c.and(negated);
+        assert_eq!(
+            filter.to_sql(),
+            r#"("w" = $4) AND (NOT (("x" = $1) OR ("y" = $2 AND "z" > $3)))"#
+        );
+        assert_eq!(filter.max_placeholder(), 4);
+
+        let mut shifted = filter.clone();
+        shifted.shift_placeholders(1);
+        assert_eq!(
+            shifted.to_sql(),
+            r#"("w" = $5) AND (NOT (("x" = $2) OR ("y" = $3 AND "z" > $4)))"#
+        );
+
+        // Negating a constant folds it.
+        let mut always = Filter::default();
+        always.constant(true);
+        assert_eq!(always.negate().simplify().to_sql(), "false");
+        assert!(Filter::default().negate().is_empty());
+    }
+
+    #[test]
+    fn test_range() {
+        let mut filter = Filter::default();
+        filter.add(Column::new("products", "category"), Value::Placeholder(1));
+        filter.gt(Column::new("products", "price"), Value::Placeholder(2));
+        filter.lte(Column::new("products", "created_at"), Value::Placeholder(3));
+        assert_eq!(
+            filter.to_sql(),
+            r#""products"."category" = $1 AND "products"."price" > $2 AND "products"."created_at" <= $3"#
+        );
+
+        let mut where_clause = WhereClause::default();
+        where_clause.gte(Column::name("price"), 100);
+        where_clause.add(Column::name("in_stock"), "yes");
+        where_clause.lt(Column::name("price"), 500);
+        assert_eq!(
+            where_clause.to_sql(),
+            r#" WHERE "price" >= 100 AND "in_stock" = 'yes' AND "price" < 500"#
         );
     }
 
+    #[test]
+    fn test_row() {
+        let mut filter = Filter::default();
+        filter.add(Column::new("posts", "author_id"), Value::Placeholder(1));
+        filter.row_gt(&[
+            (Column::new("posts", "created_at"), Value::Placeholder(2)),
+            (Column::new("posts", "id"), Value::Placeholder(3)),
+        ]);
+        assert_eq!(
+            filter.to_sql(),
+            r#""posts"."author_id" = $1 AND ("posts"."created_at", "posts"."id") > ($2, $3)"#
+        );
+        assert_eq!(filter.placeholders(), 3);
+        assert_eq!(filter.max_placeholder(), 3);
+
+        filter.shift_placeholders(2);
+        assert_eq!(
+            filter.to_sql(),
+            r#""posts"."author_id" = $3 AND ("posts"."created_at", "posts"."id") > ($4, $5)"#
+        );
+    }
+
+    #[test]
+    fn test_column_compare() {
+        let mut filter = Filter::default();
+        filter.column_eq(Column::new("orders", "user_id"), Column::new("users", "id"));
+        filter.column_ne(
+            Column::new("users", "id"),
+            Column::new("users", "invited_by"),
+        );
+        filter.add(Column::new("users", "name"), "users.id");
+
+        assert_eq!(
+            filter.to_sql(),
+            r#""orders"."user_id" = "users"."id" AND "users"."id" <> "users"."invited_by" AND "users"."name" = 'users.id'"#
+        );
+        assert_eq!(filter.placeholders(), 0);
+
+        let mut values = vec![];
+        let (filter, next) = filter.rewrite_placeholders(1, &mut values);
+        assert_eq!(
+            filter.to_sql(),
+            r#""orders"."user_id" = "users"."id" AND "users"."id" <> "users"."invited_by" AND "users"."name" = $1"#
+        );
+        assert_eq!(next, 2);
+    }
+
+    #[test]
+    fn test_between() {
+        let mut filter = Filter::default();
+        filter.between(Column::new("users", "age"), 65, 18);
+        filter.not_between(Column::new("users", "name"), "a'", "m");
+        filter.add(Column::new("users", "score"), 10_i64..=20);
+
+        // Bounds are rendered in the order they were given.
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."age" BETWEEN 65 AND 18 AND "users"."name" NOT BETWEEN 'a''' AND 'm' AND "users"."score" BETWEEN 10 AND 20"#
+        );
+
+        let mut values = vec![];
+        let (filter, _) = filter.rewrite_placeholders(1, &mut values);
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."age" BETWEEN $1 AND $2 AND "users"."name" NOT BETWEEN $3 AND $4 AND "users"."score" BETWEEN $5 AND $6"#
+        );
+        assert_eq!(values[0], Value::Int(65));
+        assert_eq!(values[1], Value::Int(18));
+    }
+
+    #[test]
+    fn test_simplify() {
+        let active = || {
+            let mut filter = Filter::default();
+            filter.add(Column::name("active"), "yes");
+            filter
+        };
+        let constant = |value| {
+            let mut filter = Filter::default();
+            filter.constant(value);
+            filter
+        };
+
+        // false AND x = false
+        let mut filter = active();
+        filter.constant(false);
+        assert_eq!(filter.simplify().to_sql(), "false");
+
+        // true AND x = x
+        let mut filter = active();
+        filter.constant(true);
+        assert_eq!(filter.simplify().to_sql(), r#""active" = 'yes'"#);
+
+        // true OR x = true
+        assert_eq!(constant(true).or(active()).simplify().to_sql(), "true");
+
+        // false OR x = x
+        assert_eq!(
+            constant(false).or(active()).simplify().to_sql(),
+            r#"("active" = 'yes')"#
+        );
+
+        // true AND true = true
+        let mut filter = constant(true);
+        filter.constant(true);
+        assert_eq!(filter.simplify().to_sql(), "true");
+
+        // Nested: x AND (false OR (true AND false)) = false
+        let nested = constant(false).or(constant(true).and(constant(false)));
+        assert_eq!(active().and(nested).simplify().to_sql(), "false");
+
+        // Nested: x AND (y OR (false AND z)) = x AND (y)
+        let nested = constant(false).and(active());
+        let filter = active().and(active().or(nested)).simplify();
+        assert_eq!(
+            filter.to_sql(),
+            r#"("active" = 'yes') AND (("active" = 'yes'))"#
+        );
+
+        // Bound values are kept: "active" = $1 AND false.
+        let mut filter = Filter::default();
+        filter.add(Column::name("active"), Value::Placeholder(1));
+        filter.constant(false);
+        filter.add(Column::name("name"), "test");
+        let filter = filter.simplify();
+        assert_eq!(filter.to_sql(), r#""active" = $1 AND false"#);
+        assert_eq!(filter.max_placeholder(), 1);
+
+        // Nested: (false AND "id" = $1) OR true keeps $1, moving the constant last.
+        let mut bound = constant(false);
+        bound.add(Column::name("id"), Value::Placeholder(1));
+        let filter = bound.or(constant(true)).simplify();
+        assert_eq!(filter.to_sql(), r#"("id" = $1 AND false) OR true"#);
+
+        // Nothing to simplify.
+        assert_eq!(active().simplify(), active());
+        assert!(Filter::default().simplify().is_empty());
+
+        // A WHERE clause which always matches is removed.
+        let mut where_clause = WhereClause::default();
+        where_clause.or(constant(true).or(active()));
+        where_clause.simplify();
+        assert_eq!(where_clause.to_sql(), "");
+
+        let mut where_clause = WhereClause::default();
+        where_clause.and(constant(false));
+        where_clause.simplify();
+        assert_eq!(where_clause.to_sql(), " WHERE false");
+    }
+
+    #[test]
+    fn test_null() {
+        let mut filter = Filter::default();
+        filter.is_null(Column::new("users", "deleted_at"));
+        filter.is_not_null(Column::new("users", "confirmed_at"));
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."deleted_at" IS NULL AND "users"."confirmed_at" IS NOT NULL"#
+        );
+        assert_eq!(filter.placeholders(), 0);
+
+        let mut filter = Filter::default();
+        filter.add(Column::new("users", "deleted_at"), Value::Null);
+        filter.add_not(Column::new("users", "confirmed_at"), None::<i64>);
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."deleted_at" IS NULL AND "users"."confirmed_at" IS NOT NULL"#
+        );
+    }
+
+    #[test]
+    fn test_like() {
+        let mut filter = Filter::default();
+        filter.like(Column::new("users", "name"), "%o'brien%");
+        filter.ilike(Column::new("users", "email"), "%@EXAMPLE.COM");
+        filter.not_like(Column::new("users", "name"), Value::Placeholder(1));
+
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."name" LIKE '%o''brien%' AND "users"."email" ILIKE '%@EXAMPLE.COM' AND "users"."name" NOT LIKE $1"#
+        );
+        assert_eq!(filter.placeholders(), 1);
+    }
+
+    #[test]
+    fn test_rewrite_placeholders() {
+        let mut admins = Filter::default();
+        admins.add(Column::new("users", "role"), "admin");
+        admins.add(Column::new("users", "deleted_at"), Value::Null);
+        let mut owners = Filter::default();
+        owners.add(Column::new("users", "role"), "owner");
+        owners.gte(Column::new("users", "level"), 3);
+
+        let mut filter = Filter::default();
+        filter.add(Column::new("users", "team_id"), 5);
+        let mut filter = filter.and(admins.or(owners));
+        filter.like(Column::new("users", "email"), "%@test.com");
+        filter.add(Column::new("users", "company_id"), Value::Placeholder(1));
+
+        let mut values = vec![];
+        let (filter, next) = filter.rewrite_placeholders(2, &mut values);
+
+        assert_eq!(
+            filter.to_sql(),
+            r#"("users"."team_id" = $2) AND (("users"."role" = $3 AND "users"."deleted_at" IS NULL) OR ("users"."role" = $4 AND "users"."level" >= $5)) AND "users"."email" LIKE $6 AND "users"."company_id" = $1"#
+        );
+        assert_eq!(next, 7);
+        assert_eq!(
+            values,
+            vec![
+                Value::Int(5),
+                Value::String("admin".into()),
+                Value::String("owner".into()),
+                Value::Int(3),
+                Value::String("%@test.com".into()),
+            ]
+        );
+        assert_eq!(filter.placeholders(), 6);
+
+        // Aggregates are SQL expressions, not bound values.
+        let mut filter = Filter::default();
+        filter.add(Column::new("orders", "user_id"), 5);
+        filter.gt(
+            Column::new("orders", "total"),
+            Column::new("orders", "amount").sum(),
+        );
+
+        let mut values = vec![];
+        let (filter, next) = filter.rewrite_placeholders(1, &mut values);
+
+        assert_eq!(
+            filter.to_sql(),
+            r#""orders"."user_id" = $1 AND "orders"."total" > SUM("orders"."amount")"#
+        );
+        assert_eq!(next, 2);
+        assert_eq!(values, vec![Value::Int(5)]);
+    }
+
+    #[test]
+    fn test_from_query() {
+        let query = crate::http::Query::parse("status=active&country=CA&password=hunter2");
+
+        let where_clause = WhereClause::from_query(&query, &["status", "country"]);
+        assert_eq!(
+            where_clause.to_sql(),
+            r#" WHERE "country" = 'CA' AND "status" = 'active'"#
+        );
+
+        let where_clause = WhereClause::from_query(&query, &["email"]);
+        assert_eq!(where_clause.to_sql(), "");
+
+        let query = crate::http::Query::parse("status=a'%20OR%20'x");
+        let where_clause = WhereClause::from_query(&query, &["status"]);
+        assert_eq!(where_clause.to_sql(), r#" WHERE "status" = 'a'' OR ''x'"#);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut tenant = WhereClause::default();
+        tenant.add(Column::name("tenant_id"), 5);
+
+        let mut soft_delete = WhereClause::default();
+        soft_delete.add(Column::name("deleted_at"), Value::Null);
+
+        let mut user = Filter::default();
+        user.add(Column::name("user_id"), 1);
+        let mut public = Filter::default();
+        public.add(Column::name("visibility"), "public");
+        let mut visible = WhereClause::default();
+        visible.or(user.or(public));
+
+        let mut where_clause = WhereClause::default();
+        where_clause.merge(tenant);
+        where_clause.merge(soft_delete);
+        where_clause.merge(visible);
+        where_clause.merge(WhereClause::default());
+
+        assert_eq!(
+            where_clause.to_sql(),
+            r#" WHERE "tenant_id" = 5 AND "deleted_at" IS NULL AND (("user_id" = 1) OR ("visibility" = 'public'))"#
+        );
+
+        // Placeholders are renumbered.
+        let mut a = WhereClause::default();
+        a.add(Column::name("a"), Value::Placeholder(1));
+        a.add(Column::name("b"), Value::Placeholder(2));
+        let mut b = WhereClause::default();
+        b.add(Column::name("c"), Value::Placeholder(1));
+        a.merge(b);
+
+        assert_eq!(a.to_sql(), r#" WHERE "a" = $1 AND "b" = $2 AND "c" = $3"#);
+        assert_eq!(a.placeholders(), 3);
+    }
+
+    #[test]
+    fn test_any_of() {
+        let mut filter = Filter::default();
+        filter.add(Column::new("users", "admin"), Value::Placeholder(1));
+        filter.any_of(
+            Column::new("users", "email"),
+            [Value::Placeholder(2), Value::Placeholder(3)],
+        );
+
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."admin" = $1 AND ("users"."email" = $2 OR "users"."email" = $3)"#
+        );
+        assert_eq!(filter.placeholders(), 3);
+
+        let mut filter = Filter::default();
+        filter.any_of(Column::name("id"), [1_i64]);
+        assert_eq!(filter.to_sql(), r#"("id" = 1)"#);
+
+        let mut filter = Filter::default();
+        filter.any_of(Column::name("id"), Vec::<i64>::new());
+        assert_eq!(filter.to_sql(), "false");
+    }
+
+    #[test]
+    fn test_in_list() {
+        let mut filter = Filter::default();
+        filter.in_list(
+            Column::new("users", "id"),
+            [Value::Placeholder(1), Value::Placeholder(2)],
+        );
+        filter.not_in_list(Column::new("users", "status"), ["banned", "deleted"]);
+
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."id" IN ($1, $2) AND "users"."status" NOT IN ('banned', 'deleted')"#
+        );
+        assert_eq!(filter.placeholders(), 2);
+
+        let mut values = vec![];
+        let (filter, next) = filter.rewrite_placeholders(3, &mut values);
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."id" IN ($1, $2) AND "users"."status" NOT IN ($3, $4)"#
+        );
+        assert_eq!(next, 5);
+
+        // Empty lists don't produce IN (), which is a syntax error.
+        let mut filter = Filter::default();
+        filter.in_list(Column::name("id"), Vec::<i64>::new());
+        assert_eq!(filter.to_sql(), "false");
+
+        let mut filter = Filter::default();
+        filter.not_in_list(Column::name("id"), Vec::<i64>::new());
+        assert_eq!(filter.to_sql(), "true");
+    }
+
     #[test]
     fn test_join() {
         let a = Filter {
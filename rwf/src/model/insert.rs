@@ -1,15 +1,26 @@
 //! Implements the `SELECT` query.
-use super::{Column, Escape, FromRow, Model, Placeholders, ToColumn, ToSql, ToValue};
+use super::{
+    Column, Error, EscapeIdentifier, FromRow, Model, Placeholders, ToColumn, ToSql, ToValue, Value,
+};
+use super::{ConnectionRequest, ToConnectionRequest};
 use std::marker::PhantomData;
+use std::time::Instant;
+
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tracing::info;
 
 #[derive(Debug, Clone)]
 pub struct Insert<T> {
     table_name: String,
     columns: Vec<Column>,
+    rows: Vec<Vec<Value>>,
     pub placeholders: Placeholders,
     marker: PhantomData<T>,
     no_conflict: bool,
     unique_by: Vec<Column>,
+    return_existing: bool,
+    returning: Vec<Column>,
+    update_only: Vec<Column>,
 }
 
 impl<T: Model> Insert<T> {
@@ -18,36 +29,105 @@ impl<T: Model> Insert<T> {
             .into_iter()
             .map(|column| Column::name(column))
             .collect();
-        let values = model.values();
         let mut placeholders = Placeholders::new();
-        for value in values {
-            placeholders.add(&value);
-        }
+        let values = model
+            .values()
+            .into_iter()
+            .map(|value| Self::value(&mut placeholders, value))
+            .collect();
 
         Self {
             table_name: T::table_name().to_string(),
             placeholders,
             columns,
+            rows: vec![values],
             marker: PhantomData,
             no_conflict: false,
             unique_by: vec![],
+            return_existing: false,
+            returning: vec![],
+            update_only: vec![],
         }
     }
 
     pub fn from_columns(columns: &[impl ToColumn], values: &[impl ToValue]) -> Self {
         let mut placeholders = Placeholders::new();
-        for value in values {
-            let value = value.to_value();
-            placeholders.add(&value);
-        }
+        let values = values
+            .iter()
+            .map(|value| Self::value(&mut placeholders, value.to_value()))
+            .collect();
 
         Insert {
             table_name: T::table_name().to_string(),
             columns: columns.iter().map(|c| c.to_column().unqualify()).collect(),
+            rows: vec![values],
             placeholders,
             marker: PhantomData,
             no_conflict: false,
             unique_by: vec![],
+            return_existing: false,
+            returning: vec![],
+            update_only: vec![],
+        }
+    }
+
+    /// Insert multiple records with one statement, e.g. `VALUES ($1, $2), ($3, $4)`.
+    ///
+    /// All records use the same columns, returned by [`Model::column_names`].
+    /// Returns an error if there are no records to insert.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let users = Query::Insert(Insert::many(&users)?)
+    ///     .fetch_all(&mut conn)
+    ///     .await?;
+    /// ```
+    pub fn many(models: &[T]) -> Result<Self, Error> {
+        if models.is_empty() {
+            return Err(Error::ValueError(
+                "insert",
+                "at least one record is required".into(),
+            ));
+        }
+
+        let columns = T::column_names()
+            .iter()
+            .copied()
+            .map(Column::name)
+            .collect();
+        let mut placeholders = Placeholders::new();
+        let rows = models
+            .iter()
+            .map(|model| {
+                model
+                    .values()
+                    .into_iter()
+                    .map(|value| Self::value(&mut placeholders, value))
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            table_name: T::table_name().to_string(),
+            placeholders,
+            columns,
+            rows,
+            marker: PhantomData,
+            no_conflict: false,
+            unique_by: vec![],
+            return_existing: false,
+            returning: vec![],
+            update_only: vec![],
+        })
+    }
+
+    // Database functions, e.g. `now()`, are evaluated by the database
+    // and don't need a placeholder.
+    fn value(placeholders: &mut Placeholders, value: Value) -> Value {
+        match value {
+            Value::Function(_) => value,
+            value => placeholders.add(&value),
         }
     }
 
@@ -56,10 +136,130 @@ impl<T: Model> Insert<T> {
         self
     }
 
+    /// Update the existing row if it conflicts with the one being inserted on these
+    /// unique columns, i.e. an upsert. All inserted columns except the unique ones are updated;
+    /// use [`Self::update_only`] to update fewer.
     pub fn unique_by(mut self, columns: &[impl ToColumn]) -> Self {
         self.unique_by = columns.iter().map(|c| c.to_column()).collect();
         self
     }
+
+    /// On conflict, only update these columns, instead of all inserted columns.
+    ///
+    /// Columns which aren't inserted are left out of the update, since `EXCLUDED`
+    /// has no value for them.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // INSERT ... ON CONFLICT ("email") DO UPDATE SET "name" = EXCLUDED."name"
+    /// let user = Insert::new(user)
+    ///     .unique_by(&["email"])
+    ///     .update_only(&["name"]);
+    /// ```
+    pub fn update_only(mut self, columns: &[impl ToColumn]) -> Self {
+        self.update_only = columns.iter().map(|c| c.to_column().unqualify()).collect();
+        self
+    }
+
+    /// Insert many records at once with `COPY ... FROM STDIN`, streaming rows
+    /// to the database in the binary format.
+    ///
+    /// Unlike `INSERT`, `COPY` isn't limited by the number of parameters in a statement,
+    /// and is much faster for large batches, e.g. tens of thousands of rows. Rows are not returned;
+    /// the number of rows copied is returned instead. Database functions, e.g. `now()`, can't be copied,
+    /// use column defaults instead.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let users = (0..50_000).map(|i| User::new(&format!("user-{}@test.com", i)));
+    /// let copied = Insert::bulk_copy(users, &mut conn).await?;
+    /// ```
+    pub async fn bulk_copy(
+        models: impl IntoIterator<Item = T>,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<u64, Error> {
+        let start = Instant::now();
+        let request = conn.to_connection_request()?;
+        let mut conn = match request {
+            ConnectionRequest::Pool(ref pool) => Some(pool.get().await?),
+            ConnectionRequest::Fulfilled(_) => None,
+        };
+
+        let client = match request {
+            ConnectionRequest::Fulfilled(conn) => conn.client(),
+            ConnectionRequest::Pool(_) => conn.as_mut().unwrap().client(),
+        };
+
+        let columns = T::column_names()
+            .iter()
+            .map(|column| Column::name(column).to_sql())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let table_name = T::table_name().escape_identifier();
+
+        // COPY doesn't describe the columns, so get their types from an empty SELECT.
+        let types = client
+            .prepare(&format!(
+                r#"SELECT {} FROM "{}" LIMIT 0"#,
+                columns, table_name
+            ))
+            .await?
+            .columns()
+            .iter()
+            .map(|column| column.type_().clone())
+            .collect::<Vec<_>>();
+
+        let copy = format!(
+            r#"COPY "{}" ({}) FROM STDIN (FORMAT binary)"#,
+            table_name, columns
+        );
+        let sink = client.copy_in(&copy).await?;
+        let writer = BinaryCopyInWriter::new(sink, &types);
+        tokio::pin!(writer);
+
+        for model in models {
+            let values = model.values();
+            let values = values
+                .iter()
+                .map(|value| value as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>();
+            writer.as_mut().write(&values).await?;
+        }
+
+        let copied = writer.finish().await?;
+
+        info!(
+            "{} copied {} rows ({:.3} ms) {}",
+            std::any::type_name::<T>(),
+            copied,
+            start.elapsed().as_secs_f64() * 1000.0,
+            copy
+        );
+
+        Ok(copied)
+    }
+
+    /// Return only these columns from the inserted row, instead of all of them, e.g.
+    /// `RETURNING "id", "created_at"`. Useful for tables with large columns.
+    ///
+    /// The returned row only contains these columns, so it must be read with a type
+    /// which doesn't need the others.
+    pub fn returning(mut self, columns: &[impl ToColumn]) -> Self {
+        self.returning = columns.iter().map(|c| c.to_column().unqualify()).collect();
+        self
+    }
+
+    /// If a row with the same values in the unique columns already exists,
+    /// return it unchanged instead of inserting a new one.
+    ///
+    /// Unlike [`Self::no_conflict`], a row is always returned, which makes it safe to retry.
+    pub fn return_existing(mut self, columns: &[impl ToColumn]) -> Self {
+        self = self.unique_by(columns);
+        self.return_existing = true;
+        self
+    }
 }
 
 impl<T: FromRow> ToSql for Insert<T> {
@@ -70,11 +270,18 @@ impl<T: FromRow> ToSql for Insert<T> {
             .map(|c| c.to_sql())
             .collect::<Vec<_>>()
             .join(", ");
-        let placeholders = self
-            .columns
+        let rows = self
+            .rows
             .iter()
-            .enumerate()
-            .map(|(i, _)| format!("${}", i + 1))
+            .map(|row| {
+                format!(
+                    "({})",
+                    row.iter()
+                        .map(|v| v.to_sql())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
             .collect::<Vec<_>>()
             .join(", ");
 
@@ -92,22 +299,63 @@ impl<T: FromRow> ToSql for Insert<T> {
                 .map(|c| c.to_sql())
                 .collect::<Vec<_>>()
                 .join(", ");
-            let update = columns
+            // Updating the column to its current value leaves the row unchanged,
+            // but unlike DO NOTHING, makes RETURNING include it.
+            let update = if self.return_existing {
+                columns
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{} = {}",
+                            c.to_sql(),
+                            c.clone().qualify(&self.table_name).to_sql()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            } else {
+                // Setting the conflict columns is a no-op, they are equal already.
+                let mut update = if self.update_only.is_empty() {
+                    &self.columns
+                } else {
+                    &self.update_only
+                }
                 .iter()
-                .map(|c| format!("{} = EXCLUDED.{}", c.to_sql(), c.to_sql()))
-                .collect::<Vec<_>>()
-                .join(", ");
+                .filter(|c| self.columns.contains(c) && !columns.contains(c))
+                .collect::<Vec<_>>();
+
+                // Nothing else to update, e.g. the only inserted columns are unique.
+                if update.is_empty() {
+                    update = columns.iter().collect();
+                }
+
+                update
+                    .into_iter()
+                    .map(|c| format!("{} = EXCLUDED.{}", c.to_sql(), c.to_sql()))
+                    .collect::<Vec<_>>()
+            }
+            .join(", ");
             format!("ON CONFLICT ({}) DO UPDATE SET {} ", on_conflict, update)
         } else {
             "".to_string()
         };
 
+        let returning = if self.returning.is_empty() {
+            "*".to_string()
+        } else {
+            self.returning
+                .iter()
+                .map(|c| c.to_sql())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
         format!(
-            r#"INSERT INTO "{}" ({}) VALUES ({}) {}RETURNING *"#,
-            self.table_name.escape(),
+            r#"INSERT INTO "{}" ({}) VALUES {} {}RETURNING {}"#,
+            self.table_name.escape_identifier(),
             columns,
-            placeholders,
+            rows,
             no_conflict,
+            returning,
         )
     }
 }
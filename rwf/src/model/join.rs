@@ -1,5 +1,5 @@
 //! Implements joining tables in a `SELECT` query.
-use super::{Column, Escape, Model, ToSql};
+use super::{Column, EscapeIdentifier, Filter, Model, ToSql};
 use std::marker::PhantomData;
 
 /// Type of relationship between models.
@@ -46,39 +46,46 @@ pub trait Association<T: Model>: Model {
         match Self::association_type() {
             // INNER JOIN "users" ON "users"."id" = "orders"."user_id"
             BelongsTo => {
-                let table_name = Self::table_name().to_string();
-                let table_column = Column::new(T::table_name(), T::primary_key());
-                let foreign_column = Column::new(Self::table_name(), T::foreign_key());
-                Join {
-                    kind: JoinKind::Inner,
-                    table_name,
-                    table_column,
-                    foreign_column,
-                }
+                let mut on = Filter::default();
+                on.column_eq(
+                    Column::new(T::table_name(), T::primary_key()),
+                    Column::new(Self::table_name(), T::foreign_key()),
+                );
+                Join::new(JoinKind::Inner, Self::table_name(), on)
             }
 
             // INNER JOIN "orders ON "orders"."user_id" = "users"."id"
             // HasOne is enforced by having a UNIQUE index on the foreign key.
             HasMany | HasOne => {
-                let table_name = Self::table_name().to_string();
-                let table_column = Column::new(T::table_name(), Self::foreign_key());
-                let foreign_column = Column::new(Self::table_name(), Self::primary_key());
-                Join {
-                    kind: JoinKind::Inner,
-                    table_name,
-                    table_column,
-                    foreign_column,
-                }
+                let mut on = Filter::default();
+                on.column_eq(
+                    Column::new(T::table_name(), Self::foreign_key()),
+                    Column::new(Self::table_name(), Self::primary_key()),
+                );
+                Join::new(JoinKind::Inner, Self::table_name(), on)
             }
         }
     }
 }
 
+/// Type of join.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum JoinKind {
+    /// `INNER JOIN`, only rows matching in both tables.
     Inner,
+    /// `LEFT JOIN`, all rows from the left table.
     Left,
-    Outer,
+    /// `RIGHT JOIN`, all rows from the right table.
+    Right,
+    /// `FULL JOIN`, all rows from both tables.
+    Full,
+}
+
+impl JoinKind {
+    /// `FULL JOIN`, all rows from both tables.
+    #[deprecated(note = "use `JoinKind::Full` instead")]
+    #[allow(non_upper_case_globals)]
+    pub const Outer: JoinKind = JoinKind::Full;
 }
 
 impl ToString for JoinKind {
@@ -86,28 +93,65 @@ impl ToString for JoinKind {
         match self {
             JoinKind::Inner => "INNER JOIN",
             JoinKind::Left => "LEFT JOIN",
-            JoinKind::Outer => "OUTER JOIN",
+            JoinKind::Right => "RIGHT JOIN",
+            JoinKind::Full => "FULL JOIN",
         }
         .to_string()
     }
 }
 
+/// Join a table, e.g. `INNER JOIN "orders" ON "users"."id" = "orders"."user_id"`.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::model::{Join, JoinKind, Filter, Column, ToSql};
+/// let mut on = Filter::default();
+/// on.column_eq(Column::new("users", "id"), Column::new("orders", "user_id"));
+///
+/// let join = Join::new(JoinKind::Left, "orders", on);
+/// assert_eq!(join.to_sql(), r#"LEFT JOIN "orders" ON "users"."id" = "orders"."user_id""#);
+/// ```
 #[derive(Debug, Clone)]
 pub struct Join {
     kind: JoinKind,
     table_name: String,
-    table_column: Column,
-    foreign_column: Column,
+    on: Filter,
+}
+
+impl Join {
+    /// Join the table on the rows matching the filter.
+    pub fn new(kind: JoinKind, table_name: impl ToString, on: Filter) -> Self {
+        Self {
+            kind,
+            table_name: table_name.to_string(),
+            on,
+        }
+    }
+
+    /// Type of join.
+    pub fn kind(&self) -> JoinKind {
+        self.kind
+    }
+
+    /// Name of the joined table.
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
+    /// Renumber all placeholders in the `ON` filter by the offset.
+    pub(crate) fn shift_placeholders(&mut self, offset: i32) {
+        self.on.shift_placeholders(offset);
+    }
 }
 
 impl ToSql for Join {
     fn to_sql(&self) -> String {
         format!(
-            r#"{} "{}" ON {} = {}"#,
+            r#"{} "{}" ON {}"#,
             self.kind.to_string(),
-            self.table_name.escape(),
-            self.table_column.to_sql(),
-            self.foreign_column.to_sql(),
+            self.table_name.escape_identifier(),
+            self.on.to_sql(),
         )
     }
 }
@@ -130,6 +174,13 @@ impl Joins {
     pub fn joins(&self) -> &[Join] {
         &self.joins
     }
+
+    /// Renumber all placeholders in the `ON` filters by the offset.
+    pub(crate) fn shift_placeholders(&mut self, offset: i32) {
+        for join in self.joins.iter_mut() {
+            join.shift_placeholders(offset);
+        }
+    }
 }
 
 impl ToSql for Joins {
@@ -181,3 +232,48 @@ impl<S: Model, T: Model> Into<Joins> for Joined<S, T> {
         self.joins
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn on(left: &str, right: &str) -> Filter {
+        let mut on = Filter::default();
+        on.column_eq(Column::new(left, "id"), Column::new(right, "parent_id"));
+        on
+    }
+
+    #[test]
+    fn test_join_kinds() {
+        for (kind, sql) in [
+            (JoinKind::Inner, "INNER JOIN"),
+            (JoinKind::Left, "LEFT JOIN"),
+            (JoinKind::Right, "RIGHT JOIN"),
+            (JoinKind::Full, "FULL JOIN"),
+            #[allow(deprecated)]
+            (JoinKind::Outer, "FULL JOIN"),
+        ] {
+            let join = Join::new(kind, "b", on("a", "b"));
+            assert_eq!(
+                join.to_sql(),
+                format!(r#"{} "b" ON "a"."id" = "b"."parent_id""#, sql)
+            );
+        }
+    }
+
+    #[test]
+    fn test_join_chain() {
+        let mut on_c = on("b", "c");
+        on_c.add(Column::new("c", "status"), "active");
+
+        let joins = Joins::new()
+            .add(Join::new(JoinKind::Inner, "b", on("a", "b")))
+            .add(Join::new(JoinKind::Left, "c", on_c));
+
+        assert_eq!(
+            joins.to_sql(),
+            r#" INNER JOIN "b" ON "a"."id" = "b"."parent_id" LEFT JOIN "c" ON "b"."id" = "c"."parent_id" AND "c"."status" = 'active'"#
+        );
+        assert_eq!(Joins::new().to_sql(), "");
+    }
+}
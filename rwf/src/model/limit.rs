@@ -14,7 +14,8 @@ impl ToSql for Limit {
             limit += format!(" LIMIT {}", rows).as_str();
         }
 
-        if let Some(ref offset) = self.offset {
+        // OFFSET 0 doesn't skip anything.
+        if let Some(offset) = self.offset.filter(|offset| *offset != 0) {
             limit += format!(" OFFSET {}", offset).as_str();
         }
 
@@ -30,8 +31,32 @@ impl Limit {
         }
     }
 
+    /// Set the maximum number of rows, keeping the offset.
+    pub fn limit(mut self, n: i64) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
     pub fn offset(mut self, offset: i64) -> Self {
         self.offset = Some(offset);
         self
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_limit_offset() {
+        assert_eq!(Limit::default().to_sql(), "");
+        assert_eq!(Limit::new(25).to_sql(), " LIMIT 25");
+        assert_eq!(Limit::default().offset(50).to_sql(), " OFFSET 50");
+        assert_eq!(Limit::new(25).offset(50).to_sql(), " LIMIT 25 OFFSET 50");
+        assert_eq!(Limit::new(25).offset(0).to_sql(), " LIMIT 25");
+        assert_eq!(
+            Limit::default().offset(50).limit(25).to_sql(),
+            " LIMIT 25 OFFSET 50"
+        );
+    }
+}
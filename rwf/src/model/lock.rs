@@ -1,10 +1,13 @@
 //! Implements `FOR UPDATE` SQL locking primitive.
+use std::time::Duration;
+
 use super::ToSql;
 
 #[derive(Debug, Default, Clone)]
 pub struct Lock {
     lock: bool,
     skip_locked: bool,
+    timeout: Option<Duration>,
 }
 
 impl Lock {
@@ -12,6 +15,7 @@ impl Lock {
         Self {
             lock: true,
             skip_locked: false,
+            timeout: None,
         }
     }
 
@@ -19,6 +23,28 @@ impl Lock {
         self.skip_locked = true;
         self
     }
+
+    /// Lock, waiting at most `timeout` for rows locked by other transactions.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.lock = true;
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Statement setting the lock timeout for the rest of the transaction, if any.
+    pub fn set_timeout(&self) -> Option<String> {
+        self.timeout.map(|timeout| {
+            format!(
+                "SET LOCAL lock_timeout = '{}ms'",
+                timeout.as_millis().max(1)
+            )
+        })
+    }
+
+    /// Statement restoring the lock timeout after the query, if it was set.
+    pub fn reset_timeout(&self) -> Option<&'static str> {
+        self.timeout.map(|_| "SET LOCAL lock_timeout = DEFAULT")
+    }
 }
 
 impl ToSql for Lock {
@@ -28,3 +54,33 @@ impl ToSql for Lock {
         format!("{}{}", lock, skip_locked)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lock_timeout() {
+        assert_eq!(Lock::new().set_timeout(), None);
+        assert_eq!(Lock::new().reset_timeout(), None);
+
+        let lock = Lock::default()
+            .skip_locked()
+            .timeout(Duration::from_millis(250));
+        assert_eq!(lock.to_sql(), " FOR UPDATE SKIP LOCKED");
+        assert_eq!(
+            lock.set_timeout().unwrap(),
+            "SET LOCAL lock_timeout = '250ms'"
+        );
+        assert_eq!(
+            lock.reset_timeout().unwrap(),
+            "SET LOCAL lock_timeout = DEFAULT"
+        );
+
+        // Zero disables the timeout in Postgres, so wait at least a millisecond.
+        assert_eq!(
+            Lock::new().timeout(Duration::ZERO).set_timeout().unwrap(),
+            "SET LOCAL lock_timeout = '1ms'"
+        );
+    }
+}
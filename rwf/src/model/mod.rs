@@ -4,12 +4,14 @@
 use crate::colors::MaybeColorize;
 use crate::config::get_config;
 
-use pool::ToConnectionRequest;
+use pool::{ConnectionRequest, Retry, ToConnectionRequest};
 use std::time::{Duration, Instant};
 use tracing::{error, info};
 
+pub mod aggregate;
 pub mod callbacks;
 pub mod column;
+pub mod delete;
 pub mod error;
 pub mod escape;
 pub mod exists;
@@ -21,27 +23,33 @@ pub mod limit;
 pub mod lock;
 pub mod migrations;
 pub mod order_by;
+pub mod page;
 pub mod picked;
 pub mod placeholders;
 pub mod pool;
 pub mod prelude;
 pub mod row;
+pub mod scalar;
 pub mod select;
 pub mod update;
 pub mod value;
+pub mod window;
 
+pub use aggregate::{Aggregate, AggregateFunction};
 pub use column::{Column, Columns, ToColumn};
+pub use delete::Delete;
 pub use error::Error;
-pub use escape::Escape;
+pub use escape::{Escape, EscapeIdentifier};
 pub use exists::Exists;
 pub use explain::Explain;
 pub use filter::{Filter, WhereClause};
 pub use insert::Insert;
-pub use join::{Association, AssociationType, Join, Joined, Joins};
+pub use join::{Association, AssociationType, Join, JoinKind, Joined, Joins};
 pub use limit::Limit;
 pub use lock::Lock;
 pub use migrations::{migrate, rollback, Migrations};
-pub use order_by::{OrderBy, OrderColumn, ToOrderBy};
+pub use order_by::{Direction, Nulls, OrderBy, OrderColumn, ToOrderBy};
+pub use page::Page;
 pub use picked::Picked;
 pub use placeholders::Placeholders;
 pub use pool::{get_connection, get_pool, start_transaction, Connection, ConnectionGuard, Pool};
@@ -49,6 +57,7 @@ pub use row::Row;
 pub use select::Select;
 pub use update::Update;
 pub use value::{ToValue, Value};
+pub use window::Window;
 
 /// Convert a PostgreSQL row to a Rust struct. Type conversions are handled by `tokio_postgres`. This only
 /// creates a mapping between columns and struct fields.
@@ -213,6 +222,8 @@ pub enum Query<T: FromRow + ?Sized = Row> {
     Update(Update<T>),
     /// Represents an `INSERT` statement.
     Insert(Insert<T>),
+    /// Represents a `DELETE` statement.
+    Delete(Delete<T>),
     /// Implements [`Model::find_or_create_by`] by building a `SELECT` and an `INSERT` query.
     InsertIfNotExists {
         select: Select<T>,
@@ -237,6 +248,7 @@ impl<T: FromRow> ToSql for Query<T> {
             Raw { query, .. } => query.clone(),
             Update(update) => update.to_sql(),
             Insert(insert) => insert.to_sql(),
+            Delete(delete) => delete.to_sql(),
             InsertIfNotExists { select, insert, .. } => {
                 format!("{}; {};", select.to_sql(), insert.to_sql())
             }
@@ -426,6 +438,110 @@ impl<T: Model> Query<T> {
         }
     }
 
+    /// Filter rows matching a `LIKE` pattern, e.g. `%foo%`.
+    pub fn filter_like(self, column: impl ToColumn, pattern: impl ToValue) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_like(column, pattern)),
+            _ => self,
+        }
+    }
+
+    /// Filter rows matching a case-insensitive `ILIKE` pattern, e.g. `%foo%`.
+    pub fn filter_ilike(self, column: impl ToColumn, pattern: impl ToValue) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_ilike(column, pattern)),
+            _ => self,
+        }
+    }
+
+    /// Filter rows by comparing multiple columns at once, e.g. `("created_at", "id") > ($1, $2)`.
+    /// Use it for keyset pagination over composite keys.
+    pub fn filter_row_gt(self, row: &[(impl ToColumn, impl ToValue)]) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_row_gt(row)),
+            _ => self,
+        }
+    }
+
+    /// Filter rows by comparing multiple columns at once, e.g. `("created_at", "id") < ($1, $2)`.
+    pub fn filter_row_lt(self, row: &[(impl ToColumn, impl ToValue)]) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_row_lt(row)),
+            _ => self,
+        }
+    }
+
+    /// Filter rows where the JSONB column has the top-level key.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let users = User::all()
+    ///     .filter_json_has_key("preferences", "theme")
+    ///     .fetch_all(&mut conn)
+    ///     .await?;
+    /// ```
+    pub fn filter_json_has_key(self, column: impl ToColumn, key: impl ToValue) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_json_has_key(column, key)),
+            _ => self,
+        }
+    }
+
+    /// Filter rows where the value of the key in the JSONB column, extracted as text,
+    /// is equal to the value.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let users = User::all()
+    ///     .filter_json_path_eq("preferences", "theme", "dark")
+    ///     .fetch_all(&mut conn)
+    ///     .await?;
+    /// ```
+    pub fn filter_json_path_eq(
+        self,
+        column: impl ToColumn,
+        path: impl ToValue,
+        value: impl ToValue,
+    ) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_json_path_eq(column, path, value)),
+            _ => self,
+        }
+    }
+
+    /// Filter rows where the column is equal to the value cast to a Postgres type,
+    /// e.g. `"tasks"."due_on" = $1::text::date`. Returns an error if the type or the value isn't supported,
+    /// see [`Filter::add_typed`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let tasks = Task::all()
+    ///     .filter_typed("due_on", "2024-01-01", "date")?
+    ///     .fetch_all(&mut conn)
+    ///     .await?;
+    /// ```
+    pub fn filter_typed(
+        self,
+        column: impl ToColumn,
+        value: impl ToValue,
+        cast: &str,
+    ) -> Result<Self, Error> {
+        use Query::*;
+        Ok(match self {
+            Select(select) => Select(select.filter_typed(column, value, cast)?),
+            _ => self,
+        })
+    }
+
     pub fn or(self, f: fn(Self) -> Self) -> Self {
         use Query::*;
         match self {
@@ -503,6 +619,72 @@ impl<T: Model> Query<T> {
         }
     }
 
+    /// Select a page of records.
+    ///
+    /// If the model declares a [`Model::cursor_column`] and the last value of that column
+    /// on the previous page is known, keyset pagination is used: only rows after the cursor are
+    /// fetched, ordered by the cursor column. Otherwise, the page is selected with `OFFSET`.
+    ///
+    /// When keyset pagination is used, any ordering already set on the query is replaced
+    /// by the cursor column, since rows after the cursor are only meaningful in that order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # #[cursor_column("id")]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let users = User::all().order("email").page_after(4, Some(75_i64), 25);
+    ///
+    /// assert_eq!(
+    ///     users.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."id" > $1 ORDER BY "users"."id" ASC LIMIT 25"#
+    /// );
+    ///
+    /// // Cursor unknown past the first page, falls back to OFFSET.
+    /// let users = User::all().page_after(3, None::<i64>, 25);
+    ///
+    /// assert_eq!(users.to_sql(), r#"SELECT * FROM "users" LIMIT 25 OFFSET 50"#);
+    /// ```
+    pub fn page_after(self, page: i64, after: Option<impl ToValue>, per_page: i64) -> Self {
+        let page = std::cmp::max(1, page);
+        let per_page = std::cmp::max(1, per_page);
+
+        match (T::cursor_column(), after) {
+            (Some(cursor), Some(after)) => {
+                if let Query::Select(mut select) = self {
+                    select.order_by = OrderBy::default();
+                    Query::Select(select)
+                        .filter_gt(cursor, after)
+                        .order(OrderBy::asc(Column::new(T::table_name(), cursor)))
+                        .limit(per_page)
+                } else {
+                    self
+                }
+            }
+
+            (Some(cursor), None) if page == 1 => {
+                if let Query::Select(mut select) = self {
+                    select.order_by = OrderBy::default();
+                    Query::Select(select)
+                        .order(OrderBy::asc(Column::new(T::table_name(), cursor)))
+                        .limit(per_page)
+                } else {
+                    self
+                }
+            }
+
+            _ => self
+                .limit(per_page)
+                .offset((page - 1).saturating_mul(per_page)),
+        }
+    }
+
     /// Join this relation with another relation directly related to it, either
     /// through a foreign key.
     ///
@@ -528,6 +710,26 @@ impl<T: Model> Query<T> {
         }
     }
 
+    /// Join a table using an explicit join condition.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut on = Filter::default();
+    /// on.column_eq(Column::new("users", "id"), Column::new("orders", "user_id"));
+    ///
+    /// let users = User::all()
+    ///     .add_join(Join::new(JoinKind::Left, "orders", on))
+    ///     .fetch_all(&mut conn)
+    ///     .await?;
+    /// ```
+    pub fn add_join(self, join: Join) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.join(join)),
+            _ => self,
+        }
+    }
+
     pub fn join_nested<F: Association<T>, G: Model>(self, joined: Joined<F, G>) -> Self {
         match self {
             Query::Select(select) => Query::Select(select.add_joins(joined.into())),
@@ -549,6 +751,29 @@ impl<T: Model> Query<T> {
         }
     }
 
+    /// Lock the selected rows with `FOR UPDATE`, waiting at most `timeout` for rows
+    /// locked by other transactions. Returns [`Error::LockTimeout`] if the timeout expires.
+    ///
+    /// The query must run inside a [`Transaction`](pool::Transaction), otherwise [`Error::TransactionRequired`]
+    /// is returned. The timeout only applies to this query; the rest of the transaction uses the default.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut transaction = Pool::begin().await?;
+    ///
+    /// let order = Order::find(5)
+    ///     .lock_timeout(Duration::from_millis(500))
+    ///     .fetch(&mut transaction)
+    ///     .await?;
+    /// ```
+    pub fn lock_timeout(self, timeout: Duration) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.lock_timeout(timeout)),
+            _ => self,
+        }
+    }
+
     pub fn find_or_create(self) -> Self {
         match self {
             Query::Select(select) => {
@@ -575,6 +800,35 @@ impl<T: Model> Query<T> {
         }
     }
 
+    /// Delete the rows matched by the query.
+    ///
+    /// If the query has no filters, the statement returns an error instead of deleting
+    /// all rows in the table. Use [`Query::delete_all`] for that.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let deleted = Session::filter_lt("expires_at", OffsetDateTime::now_utc())
+    ///     .delete()
+    ///     .execute_count(&mut conn)
+    ///     .await?;
+    /// ```
+    pub fn delete(self) -> Self {
+        match self {
+            Query::Select(select) => Query::Delete(Delete::from(select)),
+            _ => self,
+        }
+    }
+
+    /// Delete the rows matched by the query, including all rows in the table if
+    /// the query has no filters.
+    pub fn delete_all(self) -> Self {
+        match self.delete() {
+            Query::Delete(delete) => Query::Delete(delete.all()),
+            query => query,
+        }
+    }
+
     pub fn update_all(self, attributes: &[(impl ToColumn, impl ToValue)]) -> Self {
         match self {
             Query::Select(select) => {
@@ -612,11 +866,82 @@ impl<T: Model> Query<T> {
         }
     }
 
-    async fn execute_internal(
-        &self,
-        client: impl ToConnectionRequest<'_>,
-    ) -> Result<Vec<tokio_postgres::Row>, Error> {
-        let request = client.to_connection_request()?;
+    /// On conflict, only update these columns. See [`Insert::update_only`].
+    pub fn update_only(self, columns: &[impl ToColumn]) -> Self {
+        match self {
+            Query::Insert(insert) => Query::Insert(insert.update_only(columns)),
+            _ => self,
+        }
+    }
+
+    /// Return the existing row instead of failing if it conflicts with the one being inserted,
+    /// e.g. for get-or-create.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let user = User::create(&[("email", "test@test.com")])
+    ///     .return_existing(&["email"])
+    ///     .fetch(&mut conn)
+    ///     .await?;
+    /// ```
+    pub fn return_existing(self, columns: &[impl ToColumn]) -> Self {
+        match self {
+            Query::Insert(insert) => Query::Insert(insert.return_existing(columns)),
+            _ => self,
+        }
+    }
+
+    /// Skip the insert if the row conflicts with an existing one, i.e. `ON CONFLICT DO NOTHING`.
+    ///
+    /// Nothing is returned for a skipped row, so use [`Query::fetch_optional`] to get `None` instead of an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let subscriber = Subscriber::create(&[("email", "test@test.com")])
+    ///     .no_conflict()
+    ///     .fetch_optional(&mut conn)
+    ///     .await?;
+    /// ```
+    pub fn no_conflict(self) -> Self {
+        match self {
+            Query::Insert(insert) => Query::Insert(insert.no_conflict()),
+            _ => self,
+        }
+    }
+
+    /// Return only these columns from the inserted row. See [`Insert::returning`].
+    pub fn returning(self, columns: &[impl ToColumn]) -> Self {
+        match self {
+            Query::Insert(insert) => Query::Insert(insert.returning(columns)),
+            _ => self,
+        }
+    }
+
+    /// Execute the query and return the number of rows affected.
+    ///
+    /// `UPDATE` statements are executed without returning the updated rows,
+    /// so this is efficient for updating many rows at once, e.g.:
+    ///
+    /// ```ignore
+    /// let updated = Notification::filter("read", false)
+    ///     .update_all(&[("read", true)])
+    ///     .execute_count(&mut conn)
+    ///     .await?;
+    /// ```
+    pub async fn execute_count(self, conn: impl ToConnectionRequest<'_>) -> Result<u64, Error> {
+        let update = match self {
+            Query::Update(update) => Query::Update(update.without_returning()),
+            Query::Delete(delete) => {
+                delete.check()?;
+                Query::Delete(delete.without_returning())
+            }
+            query => return Ok(query.execute(conn).await?.len() as u64),
+        };
+
+        let start = Instant::now();
+        let request = conn.to_connection_request()?;
         let mut conn = request.get().await?;
 
         let client = match request.connection() {
@@ -624,12 +949,122 @@ impl<T: Model> Query<T> {
             None => conn.as_mut().unwrap(),
         };
 
+        let query = update.to_sql();
+        let values = match update {
+            Query::Update(ref update) => update.placeholders.values(),
+            Query::Delete(ref delete) => delete.placeholders.values(),
+            _ => unreachable!(),
+        };
+
+        match client.execute_cached(&query, &values).await {
+            Ok(count) => {
+                update.log(start.elapsed());
+                Ok(count)
+            }
+            Err(err) => {
+                update.log_error(&err);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<T: FromRow> Query<T> {
+    /// Raw SQL query. The rows can be mapped into any type implementing [`FromRow`],
+    /// including scalars and tuples.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Query, ToSql};
+    /// let query = Query::<(i64, String)>::raw("SELECT id, email FROM users WHERE id = $1", &[5.into()]);
+    /// assert_eq!(query.to_sql(), "SELECT id, email FROM users WHERE id = $1");
+    /// ```
+    pub fn raw(query: impl ToString, values: &[Value]) -> Self {
+        Query::Raw {
+            query: query.to_string(),
+            placeholders: values
+                .iter()
+                .map(|v| v.to_value())
+                .collect::<Vec<_>>()
+                .into(),
+        }
+    }
+
+    /// Queries that can be safely executed more than once.
+    fn idempotent(&self) -> bool {
+        matches!(self, Query::Select(_) | Query::Picked(_))
+    }
+
+    async fn execute_internal(
+        &self,
+        client: impl ToConnectionRequest<'_>,
+    ) -> Result<Vec<tokio_postgres::Row>, Error> {
+        match client.to_connection_request()? {
+            // Retrying inside a transaction won't help, it has to be restarted
+            // from the beginning.
+            ConnectionRequest::Fulfilled(client) => self.execute_on(client).await,
+
+            ConnectionRequest::Pool(pool) => {
+                let retry = if self.idempotent() || get_config().database.retry_writes {
+                    Retry::from_config()
+                } else {
+                    Retry::none()
+                };
+
+                retry
+                    .run(|| async {
+                        let mut client = pool.get().await?;
+                        let result = self.execute_on(&mut client).await;
+
+                        // Don't return a broken connection to the pool.
+                        if let Err(Error::DatabaseError(ref err)) = result {
+                            if err.is_closed() {
+                                client.close();
+                            }
+                        }
+
+                        result
+                    })
+                    .await
+            }
+        }
+    }
+
+    async fn execute_on(
+        &self,
+        client: &mut ConnectionGuard,
+    ) -> Result<Vec<tokio_postgres::Row>, Error> {
         let result = match self {
             Query::Select(select) => {
                 let query = self.to_sql();
                 let placeholdres = { select.placeholders() };
                 let values = placeholdres.values();
-                client.query_cached(&query, &values).await
+
+                let lock = select.locking();
+
+                match (lock.set_timeout(), lock.reset_timeout()) {
+                    (Some(set_timeout), Some(reset_timeout)) => {
+                        // SET LOCAL does nothing outside of a transaction.
+                        if !client.in_transaction() {
+                            return Err(Error::TransactionRequired);
+                        }
+
+                        client.query_cached(&set_timeout, &[]).await?;
+                        let rows = client.query_cached(&query, &values).await.map_err(|err| {
+                            if err.lock_not_available() {
+                                Error::LockTimeout
+                            } else {
+                                err
+                            }
+                        })?;
+                        // Don't let the timeout apply to the rest of the transaction.
+                        client.query_cached(reset_timeout, &[]).await?;
+
+                        Ok(rows)
+                    }
+                    _ => client.query_cached(&query, &values).await,
+                }
             }
 
             Query::Raw {
@@ -652,6 +1087,13 @@ impl<T: Model> Query<T> {
                 client.query_cached(&query, &values).await
             }
 
+            Query::Delete(delete) => {
+                delete.check()?;
+                let query = self.to_sql();
+                let values = delete.placeholders.values();
+                client.query_cached(&query, &values).await
+            }
+
             Query::InsertIfNotExists { select, insert, .. } => {
                 let query = select.to_sql();
                 let values = select.placeholders().values();
@@ -725,6 +1167,7 @@ impl<T: Model> Query<T> {
             Query::Select(select) => select.placeholders,
             Query::Update(update) => update.placeholders,
             Query::Insert(insert) => insert.placeholders,
+            Query::Delete(delete) => delete.placeholders,
             Query::Picked(picked) => picked.select.placeholders,
             _ => todo!("explain"),
         };
@@ -739,8 +1182,22 @@ impl<T: Model> Query<T> {
         }
     }
 
+    /// Check if any rows match the query. This is cheaper than counting all of them.
     pub async fn exists(self, conn: impl ToConnectionRequest<'_>) -> Result<bool, Error> {
-        Ok(self.count(conn).await? > 0)
+        let query = match self {
+            Query::Select(select) => Query::Select(select.select_exists()),
+            query => return Ok(query.count(conn).await? > 0),
+        };
+        let start = Instant::now();
+
+        let result = match query.execute_internal(conn).await?.pop() {
+            None => Ok(false),
+            Some(row) => Ok(row.try_get("exists")?),
+        };
+
+        query.log(start.elapsed());
+
+        result
     }
 
     pub async fn count(self, conn: impl ToConnectionRequest<'_>) -> Result<i64, Error> {
@@ -760,6 +1217,66 @@ impl<T: Model> Query<T> {
         result
     }
 
+    /// Fetch a page of records, along with the total number of records matching the query.
+    ///
+    /// Pages are numbered starting at 1. This executes two queries: one to count all matching
+    /// records and one to fetch the records on the requested page.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let page = User::all()
+    ///     .order("id")
+    ///     .paginate(2, 25, &mut conn)
+    ///     .await?;
+    ///
+    /// if page.has_next {
+    ///     // Show link to the next page.
+    /// }
+    /// ```
+    pub async fn paginate(
+        self,
+        page: i64,
+        per_page: i64,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Page<T>, Error> {
+        let page = std::cmp::max(1, page);
+        let per_page = std::cmp::max(1, per_page);
+        let (count, rows) = self.page_queries(page, per_page);
+
+        let request = conn.to_connection_request()?;
+        let mut conn = request.get().await?;
+
+        let conn = match request.connection() {
+            Some(conn) => conn,
+            None => conn.as_mut().unwrap(),
+        };
+
+        let total = count.count(&mut *conn).await?;
+        let rows = rows.fetch_all(conn).await?;
+
+        Ok(Page::new(rows, total, page, per_page))
+    }
+
+    // Count query and data query for a page of results.
+    fn page_queries(self, page: i64, per_page: i64) -> (Self, Self) {
+        match self {
+            Query::Select(select) => {
+                let mut count = select.clone();
+                count.order_by = OrderBy::default();
+                count.limit = Limit::default();
+
+                // Page numbers usually come from the client, so they can be arbitrarily large.
+                let rows = select
+                    .limit(per_page)
+                    .offset((page - 1).saturating_mul(per_page));
+
+                (Query::Select(count), Query::Select(rows))
+            }
+            query => (query.clone(), query),
+        }
+    }
+
     /// Execute a query and return an optional result.
     pub async fn execute(self, conn: impl ToConnectionRequest<'_>) -> Result<Vec<T>, Error> {
         let start = Instant::now();
@@ -789,6 +1306,7 @@ impl<T: Model> Query<T> {
             Query::Update(_) => "save",
             Query::Raw { .. } => "query",
             Query::Insert(_) => "save",
+            Query::Delete(_) => "delete",
             Query::InsertIfNotExists { .. } => "load/create",
         }
     }
@@ -971,6 +1489,14 @@ pub trait Model: FromRow {
         "id"
     }
 
+    /// Unique, sortable column used for keyset pagination by [`Query::page_after`], e.g. `"id"`.
+    ///
+    /// By default, no column is declared and pages are selected with `OFFSET`. Use
+    /// the `#[cursor_column("id")]` derive attribute to set it.
+    fn cursor_column() -> Option<&'static str> {
+        None
+    }
+
     /// Select one record from the table. The row returned is determined by the database.
     ///
     /// # Example
@@ -1152,14 +1678,7 @@ pub trait Model: FromRow {
     /// assert_eq!(query.to_sql(), r#"SELECT * FROM users WHERE email = ANY($1, $2) ORDER BY RANDOM()"#);
     /// ```
     fn find_by_sql(query: impl ToString, values: &[Value]) -> Query<Self> {
-        Query::Raw {
-            query: query.to_string(),
-            placeholders: values
-                .iter()
-                .map(|v| v.to_value())
-                .collect::<Vec<_>>()
-                .into(),
-        }
+        Query::raw(query, values)
     }
 
     /// Order records by a column. This method accepts any input type which implement
@@ -1572,7 +2091,11 @@ mod test {
             &["name"]
         }
 
-        fn values(&self) -> Vec<Value> {
+        fn cursor_column() -> Option<&'static str> {
+            Some("id")
+        }
+
+        fn values(&self) -> Vec<Value> {
             vec![self.name.to_value()]
         }
     }
@@ -1741,6 +2264,387 @@ mod test {
         assert_eq!(query, r#"SELECT * FROM "users""#);
     }
 
+    #[test]
+    fn test_now() {
+        let query = User::create(&[
+            ("email", "test@test.com".to_value()),
+            ("created_at", Value::now()),
+        ]);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"INSERT INTO "users" ("email", "created_at") VALUES ($1, "now"()) RETURNING *"#
+        );
+
+        let query = User::all().filter_lt("created_at", Value::now());
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."created_at" < "now"()"#
+        );
+    }
+
+    #[test]
+    fn test_page_queries() {
+        let (count, rows) = User::filter("email", "test@test.com")
+            .order("id")
+            .page_queries(3, 25);
+
+        assert_eq!(
+            count.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."email" = $1"#
+        );
+        assert_eq!(
+            rows.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."email" = $1 ORDER BY id LIMIT 25 OFFSET 50"#
+        );
+
+        let (_, rows) = User::all().page_queries(i64::MAX, 25);
+        assert_eq!(
+            rows.to_sql(),
+            format!(r#"SELECT * FROM "users" LIMIT 25 OFFSET {}"#, i64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let users = (1..=3)
+            .map(|i| User {
+                id: 0,
+                email: format!("user-{}@test.com", i),
+                password: "password".into(),
+            })
+            .collect::<Vec<_>>();
+
+        let insert = Insert::many(&users).unwrap();
+        assert_eq!(
+            insert.to_sql(),
+            r#"INSERT INTO "users" ("email", "password") VALUES ($1, $2), ($3, $4), ($5, $6) RETURNING *"#
+        );
+        assert_eq!(insert.placeholders.len(), 6);
+        assert_eq!(
+            insert.placeholders.get(5),
+            Some(&Value::String("user-3@test.com".into()))
+        );
+
+        let insert = Insert::many(&users).unwrap().unique_by(&["email"]);
+        assert_eq!(
+            insert.to_sql(),
+            r#"INSERT INTO "users" ("email", "password") VALUES ($1, $2), ($3, $4), ($5, $6) ON CONFLICT ("email") DO UPDATE SET "password" = EXCLUDED."password" RETURNING *"#
+        );
+
+        let insert = Insert::many(&users).unwrap().no_conflict();
+        assert!(insert
+            .to_sql()
+            .ends_with("($5, $6) ON CONFLICT DO NOTHING RETURNING *"));
+
+        assert!(Insert::<User>::many(&[]).is_err());
+    }
+
+    #[test]
+    fn test_insert_returning() {
+        let query = User::create(&[("email", "test@test.com")]);
+        assert_eq!(
+            query.to_sql(),
+            r#"INSERT INTO "users" ("email") VALUES ($1) RETURNING *"#
+        );
+
+        let query = User::create(&[("email", "test@test.com")])
+            .unique_by(&["email"])
+            .returning(&["id", "created_at"]);
+        assert_eq!(
+            query.to_sql(),
+            r#"INSERT INTO "users" ("email") VALUES ($1) ON CONFLICT ("email") DO UPDATE SET "email" = EXCLUDED."email" RETURNING "id", "created_at""#
+        );
+
+        // Columns are unqualified.
+        let query =
+            User::create(&[("email", "test@test.com")]).returning(&[Column::new("users", "id")]);
+        assert_eq!(
+            query.to_sql(),
+            r#"INSERT INTO "users" ("email") VALUES ($1) RETURNING "id""#
+        );
+    }
+
+    #[test]
+    fn test_upsert_set() {
+        let query = Order::create(&[("user_id", 1_i64.to_value()), ("amount", 5.0.to_value())])
+            .unique_by(&["user_id"]);
+        assert_eq!(
+            query.to_sql(),
+            r#"INSERT INTO "orders" ("user_id", "amount") VALUES ($1, $2) ON CONFLICT ("user_id") DO UPDATE SET "amount" = EXCLUDED."amount" RETURNING *"#
+        );
+
+        let query = OrderItem::create(&[("order_id", 1_i64), ("product_id", 2_i64)])
+            .unique_by(&["order_id"])
+            .update_only(&["product_id", "order_id"]);
+        assert_eq!(
+            query.to_sql(),
+            r#"INSERT INTO "order_items" ("order_id", "product_id") VALUES ($1, $2) ON CONFLICT ("order_id") DO UPDATE SET "product_id" = EXCLUDED."product_id" RETURNING *"#
+        );
+
+        // Columns which aren't inserted aren't updated either.
+        let query = Order::create(&[("user_id", 1_i64.to_value()), ("amount", 5.0.to_value())])
+            .unique_by(&["user_id"])
+            .update_only(&["amount", "status"]);
+        assert_eq!(
+            query.to_sql(),
+            r#"INSERT INTO "orders" ("user_id", "amount") VALUES ($1, $2) ON CONFLICT ("user_id") DO UPDATE SET "amount" = EXCLUDED."amount" RETURNING *"#
+        );
+
+        let insert = Insert::new(User::default())
+            .unique_by(&["email", "password"])
+            .update_only(&["password"]);
+        assert_eq!(
+            insert.to_sql(),
+            r#"INSERT INTO "users" ("email", "password") VALUES ($1, $2) ON CONFLICT ("email", "password") DO UPDATE SET "email" = EXCLUDED."email", "password" = EXCLUDED."password" RETURNING *"#
+        );
+
+        let query = User::create(&[("email", "test@test.com")]).no_conflict();
+        assert_eq!(
+            query.to_sql(),
+            r#"INSERT INTO "users" ("email") VALUES ($1) ON CONFLICT DO NOTHING RETURNING *"#
+        );
+    }
+
+    #[test]
+    fn test_delete() {
+        let query = User::filter("email", "test@test.com").delete();
+        assert_eq!(
+            query.to_sql(),
+            r#"DELETE FROM "users" WHERE "users"."email" = $1 RETURNING *"#
+        );
+
+        let delete = match query {
+            Query::Delete(delete) => delete,
+            _ => panic!("not a delete"),
+        };
+        assert!(delete.check().is_ok());
+        assert_eq!(delete.placeholders.len(), 1);
+
+        // No filter, guarded.
+        let delete = match User::all().delete() {
+            Query::Delete(delete) => delete,
+            _ => panic!("not a delete"),
+        };
+        assert_eq!(delete.to_sql(), r#"DELETE FROM "users" RETURNING *"#);
+        assert!(matches!(
+            delete.check(),
+            Err(Error::ValueError("delete", _))
+        ));
+        assert!(delete.all().check().is_ok());
+
+        let query = User::all().delete_all();
+        assert!(matches!(query, Query::Delete(ref delete) if delete.check().is_ok()));
+
+        let mut where_clause = WhereClause::default();
+        where_clause.lt(Column::name("amount"), Value::Placeholder(1));
+        let delete = Delete::<Order>::new()
+            .filter(where_clause)
+            .without_returning();
+        assert_eq!(
+            delete.to_sql(),
+            r#"DELETE FROM "orders" WHERE "amount" < $1"#
+        );
+        assert!(delete.check().is_ok());
+    }
+
+    #[test]
+    fn test_filter_json() {
+        let query = User::all()
+            .filter_json_has_key("email", "verified")
+            .filter_json_path_eq("password", "algorithm", "argon2")
+            .filter("id", 5_i64);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."email" ? $1 AND "users"."password" ->> $2 = $3 AND "users"."id" = $4"#
+        );
+
+        let select = match query {
+            Query::Select(select) => select,
+            _ => panic!("not a select"),
+        };
+        assert_eq!(
+            select.placeholders().get(1),
+            Some(&Value::String("verified".into()))
+        );
+        assert_eq!(
+            select.placeholders().get(2),
+            Some(&Value::String("algorithm".into()))
+        );
+        assert_eq!(
+            select.placeholders().get(3),
+            Some(&Value::String("argon2".into()))
+        );
+
+        let mut filter = Filter::default();
+        filter.json_has_key(Column::name("data"), "key");
+        filter.json_path_eq(Column::name("data"), "plan", "pro");
+        assert_eq!(
+            filter.to_sql(),
+            r#""data" ? 'key' AND "data" ->> 'plan' = 'pro'"#
+        );
+    }
+
+    #[test]
+    fn test_filter_typed() {
+        let query = User::all()
+            .filter("id", 5_i64)
+            .filter_typed("created_at", "2024-01-01", "DATE")
+            .unwrap();
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = $1 AND "users"."created_at" = $2::text::date"#
+        );
+        match query {
+            Query::Select(select) => assert_eq!(
+                select.placeholders().get(2),
+                Some(&Value::String("2024-01-01".into()))
+            ),
+            _ => panic!("not a select"),
+        }
+
+        let mut filter = Filter::default();
+        filter
+            .add_typed(Column::new("events", "days"), "{2024-01-01}", "date[]")
+            .unwrap();
+        filter
+            .add_typed(Column::name("amount"), Value::Placeholder(1), " numeric ")
+            .unwrap();
+        assert_eq!(
+            filter.to_sql(),
+            r#""events"."days" = '{2024-01-01}'::text::date[] AND "amount" = $1::text::numeric"#
+        );
+
+        // NULL isn't cast, it's compared like with `filter`.
+        let query = User::all()
+            .filter_typed("created_at", None::<String>, "date")
+            .unwrap();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."created_at" IS NULL"#
+        );
+
+        // Only text is cast, other values are already sent in their own type.
+        assert!(matches!(
+            User::all().filter_typed("id", 5_i64, "bigint"),
+            Err(Error::ValueError("typed value", _))
+        ));
+
+        for cast in ["regclass", "date; DROP TABLE users", "date[][]", ""] {
+            assert!(
+                matches!(
+                    User::all().filter_typed("created_at", "2024-01-01", cast),
+                    Err(Error::ValueError("cast", _))
+                ),
+                "{}",
+                cast
+            );
+        }
+    }
+
+    #[test]
+    fn test_add_join() {
+        let mut on = Filter::default();
+        on.column_eq(Column::new("users", "id"), Column::new("orders", "user_id"));
+        let mut on_items = Filter::default();
+        on_items.column_eq(
+            Column::new("orders", "id"),
+            Column::new("order_items", "order_id"),
+        );
+
+        let query = User::all()
+            .add_join(Join::new(JoinKind::Left, "orders", on))
+            .add_join(Join::new(JoinKind::Right, "order_items", on_items))
+            .filter(Column::new("orders", "amount"), 5.0);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "users".* FROM "users" LEFT JOIN "orders" ON "users"."id" = "orders"."user_id" RIGHT JOIN "order_items" ON "orders"."id" = "order_items"."order_id" WHERE "orders"."amount" = $1"#
+        );
+    }
+
+    #[test]
+    fn test_limit_offset() {
+        let query = User::all().limit(25).offset(50);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" LIMIT 25 OFFSET 50"#
+        );
+
+        // Order doesn't matter.
+        let query = User::all().offset(50).limit(25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" LIMIT 25 OFFSET 50"#
+        );
+
+        let query = User::all().offset(50);
+        assert_eq!(query.to_sql(), r#"SELECT * FROM "users" OFFSET 50"#);
+
+        let query = User::all().limit(25).offset(0);
+        assert_eq!(query.to_sql(), r#"SELECT * FROM "users" LIMIT 25"#);
+    }
+
+    #[test]
+    fn test_page_after() {
+        // No cursor column, OFFSET is used.
+        let query = User::all().order("id").page_after(3, Some(50_i64), 25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY id LIMIT 25 OFFSET 50"#
+        );
+
+        // Cursor column and previous page cursor, keyset is used.
+        let query = Product::filter("name", "shoes")
+            .order("name")
+            .page_after(3, Some(50_i64), 25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "products" WHERE "products"."name" = $1 AND "products"."id" > $2 ORDER BY "products"."id" ASC LIMIT 25"#
+        );
+
+        let query = Product::all().page_after(1, None::<i64>, 25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "products" ORDER BY "products"."id" ASC LIMIT 25"#
+        );
+
+        // Cursor unknown past the first page, falls back to OFFSET.
+        let query = Product::all().page_after(3, None::<i64>, 25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "products" LIMIT 25 OFFSET 50"#
+        );
+
+        // Page numbers and sizes from the client are kept in range.
+        let query = User::all().page_after(i64::MAX, None::<i64>, 0);
+        assert_eq!(
+            query.to_sql(),
+            format!(r#"SELECT * FROM "users" LIMIT 1 OFFSET {}"#, i64::MAX - 1)
+        );
+    }
+
+    #[test]
+    fn test_filter_interval() {
+        let query = User::all().filter_lt("session_length", time::Duration::days(7));
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."session_length" < $1"#
+        );
+
+        match query {
+            Query::Select(select) => assert_eq!(
+                select.placeholders.get(1),
+                Some(&Value::Interval(time::Duration::days(7)))
+            ),
+            _ => panic!("not a select"),
+        }
+    }
+
     #[test]
     fn test_filter() {
         let query = User::filter("email", "test@test.com")
@@ -1802,6 +2706,86 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_lock_wait_timeout() -> Result<(), Error> {
+        let pool = Pool::from_env();
+
+        {
+            let conn = pool.get().await?;
+            conn.client()
+                .batch_execute(
+                    "DROP TABLE IF EXISTS users CASCADE;
+                    CREATE TABLE users (id BIGINT PRIMARY KEY, email VARCHAR, password VARCHAR);
+                    INSERT INTO users VALUES (1, 'test@test.com', 'not_encrypted');",
+                )
+                .await?;
+        }
+
+        let mut holder = pool.transaction().await?;
+        User::find(1).lock().fetch(&mut holder).await?;
+
+        let mut waiter = pool.transaction().await?;
+        let query = User::find(1).lock_timeout(Duration::from_millis(50));
+        assert!(query.to_sql().ends_with("FOR UPDATE"));
+
+        let err = query.clone().fetch(&mut waiter).await.unwrap_err();
+        assert!(matches!(err, Error::LockTimeout));
+
+        holder.rollback().await?;
+
+        // The timeout is reset after the query.
+        let mut transaction = pool.transaction().await?;
+        query.clone().fetch(&mut transaction).await?;
+        let timeout = transaction
+            .client()
+            .query_one("SHOW lock_timeout", &[])
+            .await?
+            .get::<_, String>(0);
+        assert_eq!(timeout, "0");
+        transaction.rollback().await?;
+
+        // SET LOCAL has no effect outside of a transaction.
+        let mut conn = pool.get().await?;
+        let err = query.fetch(&mut conn).await.unwrap_err();
+        assert!(matches!(err, Error::TransactionRequired));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filter_typed_query() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut conn = pool.get().await?;
+
+        conn.client()
+            .batch_execute(
+                "DROP TABLE IF EXISTS users CASCADE;
+                CREATE TABLE users (id BIGINT PRIMARY KEY, email VARCHAR, password VARCHAR, created_at DATE);
+                INSERT INTO users VALUES (1, 'a@test.com', 'not_encrypted', '2024-01-01'), (2, 'b@test.com', 'not_encrypted', '2024-01-02');",
+            )
+            .await?;
+
+        let users = User::all()
+            .filter_typed("created_at", "2024-01-02", "date")?
+            .filter_typed("id", "2", "bigint")?
+            .fetch_all(&mut conn)
+            .await?;
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, 2);
+
+        conn.client()
+            .batch_execute("INSERT INTO users VALUES (3, 'c@test.com', 'not_encrypted', NULL)")
+            .await?;
+        let users = User::all()
+            .filter_typed("created_at", Value::Null, "date")?
+            .fetch_all(&mut conn)
+            .await?;
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, 3);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_explain() -> Result<(), Error> {
         let pool = Pool::from_env();
@@ -1848,6 +2832,109 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_exists() {
+        let query = User::filter("email", "test@test.com").order("id").limit(5);
+
+        let query = match query {
+            Query::Select(select) => select.select_exists(),
+            _ => panic!("not a select"),
+        };
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT EXISTS(SELECT 1 FROM "users" WHERE "users"."email" = $1) AS "exists""#
+        );
+        assert_eq!(
+            query.placeholders().get(1),
+            Some(&Value::String("test@test.com".into()))
+        );
+
+        // The old name still counts the matching rows.
+        let query = match User::filter("email", "test@test.com") {
+            Query::Select(select) => select.exists(),
+            _ => panic!("not a select"),
+        };
+        assert!(query.to_sql().starts_with("SELECT COUNT(*)"));
+    }
+
+    #[test]
+    fn test_update_all() {
+        let query = User::filter("email", "test@test.com").update_all(&[("password", "secret")]);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"UPDATE "users" SET "password" = $2 WHERE "users"."email" = $1 RETURNING *"#
+        );
+
+        match query {
+            Query::Update(update) => assert_eq!(
+                update.without_returning().to_sql(),
+                r#"UPDATE "users" SET "password" = $2 WHERE "users"."email" = $1"#
+            ),
+            _ => panic!("not an update"),
+        }
+    }
+
+    #[test]
+    fn test_update_placeholders() {
+        let update = Update::<User>::empty()
+            .set("email", "test@test.com")
+            .set("password", "secret")
+            .filter("id", 5_i64)
+            .filter("password", Value::Null);
+
+        assert_eq!(
+            update.to_sql(),
+            r#"UPDATE "users" SET "email" = $1, "password" = $2 WHERE "users"."id" = $3 AND "users"."password" IS NULL RETURNING *"#
+        );
+        assert_eq!(update.placeholders.len(), 3);
+        assert_eq!(update.placeholders.get(3), Some(&Value::Integer(5)));
+
+        // Filters added first are bound first.
+        let update = Update::<User>::empty()
+            .filter("id", 5_i64)
+            .set("email", "test@test.com");
+        assert_eq!(
+            update.to_sql(),
+            r#"UPDATE "users" SET "email" = $2 WHERE "users"."id" = $1 RETURNING *"#
+        );
+        assert_eq!(update.placeholders.get(1), Some(&Value::Integer(5)));
+    }
+
+    #[tokio::test]
+    async fn test_execute_count() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE users (id BIGINT, email VARCHAR, password VARCHAR)",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "INSERT INTO users VALUES (1, 'a@test.com', 'one'), (2, 'a@test.com', 'two'), (3, 'b@test.com', 'three')",
+                &[],
+            )
+            .await?;
+
+        let updated = User::filter("email", "a@test.com")
+            .update_all(&[("password", "secret")])
+            .execute_count(&mut transaction)
+            .await?;
+        assert_eq!(updated, 2);
+
+        Ok(())
+    }
+
     #[test]
     fn test_unique_by() {
         let query = User::create(&[("email", "test@test.com")])
@@ -1868,6 +2955,128 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_return_existing() {
+        let query = User::create(&[("email", "test@test.com"), ("password", "secret")])
+            .return_existing(&["email"])
+            .to_sql();
+        assert_eq!(
+            query,
+            r#"INSERT INTO "users" ("email", "password") VALUES ($1, $2) ON CONFLICT ("email") DO UPDATE SET "email" = "users"."email" RETURNING *"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_insert_return_existing() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL UNIQUE, password VARCHAR NOT NULL)", &[])
+            .await?;
+
+        let created = User::create(&[("email", "test@test.com"), ("password", "first")])
+            .return_existing(&["email"])
+            .fetch(&mut transaction)
+            .await?;
+        let existing = User::create(&[("email", "test@test.com"), ("password", "second")])
+            .return_existing(&["email"])
+            .fetch(&mut transaction)
+            .await?;
+
+        assert_eq!(existing.id, created.id);
+        assert_eq!(existing.password, "first");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upsert_returning() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL UNIQUE, password VARCHAR NOT NULL)", &[])
+            .await?;
+
+        let created = User::create(&[("email", "test@test.com"), ("password", "first")])
+            .unique_by(&["email"])
+            .fetch(&mut transaction)
+            .await?;
+
+        // DO UPDATE returns the row as it is after the update.
+        let updated = User::create(&[("email", "test@test.com"), ("password", "second")])
+            .unique_by(&["email"])
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(updated.id, created.id);
+        assert_eq!(updated.password, "second");
+
+        // DO NOTHING doesn't return the conflicting row.
+        let skipped = User::create(&[("email", "test@test.com"), ("password", "third")])
+            .no_conflict()
+            .fetch_optional(&mut transaction)
+            .await?;
+        assert!(skipped.is_none());
+
+        let inserted = User::create(&[("email", "new@test.com"), ("password", "third")])
+            .no_conflict()
+            .fetch_optional(&mut transaction)
+            .await?;
+        assert_eq!(inserted.map(|user| user.email), Some("new@test.com".into()));
+
+        let user = User::find_by("email", "test@test.com")
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(user.password, "second");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_copy() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL UNIQUE, password VARCHAR NOT NULL)", &[])
+            .await?;
+
+        // More rows than parameters allowed in a single INSERT.
+        let users = (0..70_000).map(|i| User {
+            id: 0,
+            email: format!("user-{}@test.com", i),
+            password: "password".into(),
+        });
+        let copied = Insert::bulk_copy(users, &mut transaction).await?;
+        assert_eq!(copied, 70_000);
+
+        let count = User::all().count(&mut transaction).await?;
+        assert_eq!(count, 70_000);
+
+        let user = User::filter("email", "user-69999@test.com")
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(user.password, "password");
+
+        Ok(())
+    }
+
     // #[test]
     // fn test_or() {
     //     let query = User::all()
@@ -1,11 +1,38 @@
 //! Implements the `ORDER BY` SQL primitive.
 use super::{Column, Escape, ToSql};
 
-#[derive(Debug, Clone)]
+/// Sort direction.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Where to put `NULL`s in the sort order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+impl ToSql for Nulls {
+    fn to_sql(&self) -> String {
+        match self {
+            Nulls::First => "NULLS FIRST",
+            Nulls::Last => "NULLS LAST",
+        }
+        .to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum OrderColumn {
     Asc(Column),
     Desc(Column),
     Raw(String),
+    /// Column with an explicit position for `NULL`s, e.g. `"a" DESC NULLS LAST`.
+    Nulls((Box<OrderColumn>, Nulls)),
 }
 
 impl ToSql for OrderColumn {
@@ -16,6 +43,7 @@ impl ToSql for OrderColumn {
             Asc(column) => format!("{} ASC", column.to_sql()),
             Desc(column) => format!("{} DESC", column.to_sql()),
             Raw(raw) => raw.clone(),
+            Nulls((column, nulls)) => format!("{} {}", column.to_sql(), nulls.to_sql()),
         }
     }
 }
@@ -50,6 +78,12 @@ impl ToOrderBy for (&str, &str) {
     }
 }
 
+impl ToOrderBy for OrderBy {
+    fn to_order_by(&self) -> OrderBy {
+        self.clone()
+    }
+}
+
 impl ToOrderBy for (Column, &str) {
     fn to_order_by(&self) -> OrderBy {
         OrderBy {
@@ -62,7 +96,7 @@ impl ToOrderBy for (Column, &str) {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct OrderBy {
     pub order_by: Vec<OrderColumn>,
 }
@@ -80,6 +114,43 @@ impl OrderBy {
         }
     }
 
+    /// Sort by another column, after the columns already in the list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{OrderBy, Column, ToSql, Direction, Nulls};
+    /// let order_by = OrderBy::default()
+    ///     .column(Column::new("users", "last_name"), Direction::Asc)
+    ///     .column(Column::new("users", "created_at"), Direction::Desc)
+    ///     .nulls(Nulls::Last);
+    ///
+    /// assert_eq!(
+    ///     order_by.to_sql(),
+    ///     r#" ORDER BY "users"."last_name" ASC, "users"."created_at" DESC NULLS LAST"#
+    /// );
+    /// ```
+    pub fn column(mut self, column: Column, direction: Direction) -> Self {
+        self.order_by.push(match direction {
+            Direction::Asc => OrderColumn::Asc(column),
+            Direction::Desc => OrderColumn::Desc(column),
+        });
+        self
+    }
+
+    /// Set where `NULL`s are sorted for the last column added.
+    pub fn nulls(mut self, nulls: Nulls) -> Self {
+        if let Some(column) = self.order_by.pop() {
+            let column = match column {
+                OrderColumn::Nulls((column, _)) => *column,
+                column => column,
+            };
+            self.order_by
+                .push(OrderColumn::Nulls((Box::new(column), nulls)));
+        }
+        self
+    }
+
     pub fn is_empty(&self) -> bool {
         self.order_by.is_empty()
     }
@@ -120,4 +191,22 @@ mod test {
         let _order_by = "created_at ASC".to_order_by();
         let _order_by = ["created_at", "ASC"].to_order_by();
     }
+
+    #[test]
+    fn test_order_by_columns() {
+        assert_eq!(OrderBy::default().to_sql(), "");
+
+        let order_by = OrderBy::default()
+            .column(Column::new("t", "a"), Direction::Asc)
+            .nulls(Nulls::First)
+            .column(Column::new("t", "b"), Direction::Desc)
+            .column(Column::new("t", "c"), Direction::default())
+            .nulls(Nulls::First)
+            .nulls(Nulls::Last);
+
+        assert_eq!(
+            order_by.to_sql(),
+            r#" ORDER BY "t"."a" ASC NULLS FIRST, "t"."b" DESC, "t"."c" ASC NULLS LAST"#
+        );
+    }
 }
@@ -0,0 +1,70 @@
+//! A page of query results, with pagination metadata.
+use serde::Serialize;
+
+/// A page of records returned by [`crate::model::Query::paginate`].
+///
+/// Pages are numbered starting at 1.
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    /// Records on this page.
+    pub rows: Vec<T>,
+    /// Total number of records matching the query, on all pages.
+    pub total: i64,
+    /// Current page number.
+    pub page: i64,
+    /// Maximum number of records per page.
+    pub per_page: i64,
+    /// There are more records on the next page.
+    pub has_next: bool,
+}
+
+impl<T> Page<T> {
+    /// Create a page of records and compute pagination metadata.
+    pub fn new(rows: Vec<T>, total: i64, page: i64, per_page: i64) -> Self {
+        Self {
+            rows,
+            total,
+            page,
+            per_page,
+            has_next: page.saturating_mul(per_page) < total,
+        }
+    }
+
+    /// Total number of pages.
+    pub fn pages(&self) -> i64 {
+        if self.per_page > 0 {
+            (self.total + self.per_page - 1) / self.per_page
+        } else {
+            0
+        }
+    }
+
+    /// There are records on the previous page.
+    pub fn has_previous(&self) -> bool {
+        self.page > 1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_middle_page() {
+        let page = Page::new(vec![11, 12, 13, 14, 15, 16, 17, 18, 19, 20], 25, 2, 10);
+
+        assert_eq!(page.total, 25);
+        assert_eq!(page.page, 2);
+        assert_eq!(page.per_page, 10);
+        assert!(page.has_next);
+        assert!(page.has_previous());
+        assert_eq!(page.pages(), 3);
+
+        let last = Page::new(vec![21, 22, 23, 24, 25], 25, 3, 10);
+        assert!(!last.has_next);
+
+        let past_end = Page::<i64>::new(vec![], 25, i64::MAX, 10);
+        assert!(!past_end.has_next);
+        assert!(past_end.has_previous());
+    }
+}
@@ -4,14 +4,29 @@ use super::Value;
 #[derive(Debug, Clone, Default)]
 pub struct Placeholders {
     values: Vec<Value>,
+    intern: bool,
 }
 
 impl Placeholders {
     pub fn new() -> Self {
-        Self { values: vec![] }
+        Self::default()
+    }
+
+    /// Bind identical values only once, reusing the same placeholder, e.g. `$1`,
+    /// every time the value is added. Useful for large values, like arrays,
+    /// used in multiple predicates.
+    pub fn intern(mut self) -> Self {
+        self.intern = true;
+        self
     }
 
     pub fn add(&mut self, value: &Value) -> Value {
+        if self.intern {
+            if let Some(index) = self.values.iter().position(|v| v == value) {
+                return Value::Placeholder(index as i32 + 1);
+            }
+        }
+
         let id = self.values.len() + 1;
         self.values.push(value.clone());
         Value::Placeholder(id as i32)
@@ -28,6 +43,21 @@ impl Placeholders {
             .collect()
     }
 
+    /// Number of placeholder values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// No placeholder values have been added.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Add all values from other placeholders, after ours.
+    pub fn append(&mut self, other: Placeholders) {
+        self.values.extend(other.values);
+    }
+
     pub fn id(&self) -> i32 {
         self.values().len() as i32 + 1
     }
@@ -35,6 +65,30 @@ impl Placeholders {
 
 impl From<Vec<Value>> for Placeholders {
     fn from(values: Vec<Value>) -> Self {
-        Placeholders { values }
+        Placeholders {
+            values,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_intern() {
+        let list = Value::List(vec![Value::Integer(1), Value::Integer(2)]);
+
+        let mut placeholders = Placeholders::new();
+        assert_eq!(placeholders.add(&list), Value::Placeholder(1));
+        assert_eq!(placeholders.add(&list), Value::Placeholder(2));
+        assert_eq!(placeholders.len(), 2);
+
+        let mut placeholders = Placeholders::new().intern();
+        assert_eq!(placeholders.add(&list), Value::Placeholder(1));
+        assert_eq!(placeholders.add(&Value::Integer(5)), Value::Placeholder(2));
+        assert_eq!(placeholders.add(&list), Value::Placeholder(1));
+        assert_eq!(placeholders.len(), 2);
     }
 }
@@ -44,6 +44,18 @@ impl Connection {
     /// * `database_url` - Postgres-style connection URL.
     ///
     pub async fn new(database_url: &str) -> Result<Self, Error> {
+        Self::connect(database_url, &[]).await
+    }
+
+    /// Create a new connection to the database and run the statements
+    /// in `on_connect`, in order, before it's used.
+    ///
+    /// # Arguments
+    ///
+    /// * `database_url` - Postgres-style connection URL.
+    /// * `on_connect` - Statements to execute on the new connection, e.g. `SET TIME ZONE 'UTC'`.
+    ///
+    pub async fn connect(database_url: &str, on_connect: &[String]) -> Result<Self, Error> {
         let (client, connection) = tokio_postgres::connect(database_url, NoTls).await?;
 
         let bad = AtomicBool::new(false);
@@ -72,6 +84,10 @@ impl Connection {
             }
         });
 
+        for statement in on_connect {
+            guard.client.batch_execute(statement).await?;
+        }
+
         let info = guard
             .query_cached("SELECT current_database()::text, current_user::text", &[])
             .await?;
@@ -95,29 +111,52 @@ impl Connection {
         query: &str,
         params: &[&(dyn ToSql + Sync)],
     ) -> Result<Vec<Row>, Error> {
-        let statement = if let Some(statement) = self.cache.get(query) {
-            statement
-        } else {
-            let statement = self.client().prepare(query).await?;
-            self.cache.insert(query.to_string(), statement);
-            &self.cache[query]
-        };
+        let statement = self.prepare_cached(query).await?;
 
-        match self.client().query(statement, &params).await {
+        match self.client().query(&statement, params).await {
             Ok(rows) => Ok(rows),
-            Err(err) => {
-                // If schema changed, we better close this connection entirely
-                // than evicting prepared statements one by one.
-                // TODO: find and use the error code instead of using the English
-                // error message which will be translated on databases running in other locales.
-                if let Some(db_error) = err.as_db_error() {
-                    if db_error.message() == "cached plan must not change result type" {
-                        self.inner.bad.store(true, Ordering::Relaxed);
-                    }
-                }
-                Err(Error::DatabaseError(err))
+            Err(err) => Err(self.statement_error(err)),
+        }
+    }
+
+    /// Execute a statement against the database, preparing it if we haven't seen it before
+    /// on this connection. Returns the number of rows affected.
+    pub async fn execute_cached(
+        &mut self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, Error> {
+        let statement = self.prepare_cached(query).await?;
+
+        match self.client().execute(&statement, params).await {
+            Ok(count) => Ok(count),
+            Err(err) => Err(self.statement_error(err)),
+        }
+    }
+
+    async fn prepare_cached(&mut self, query: &str) -> Result<Statement, Error> {
+        if let Some(statement) = self.cache.get(query) {
+            return Ok(statement.clone());
+        }
+
+        let statement = self.client().prepare(query).await?;
+        self.cache.insert(query.to_string(), statement.clone());
+
+        Ok(statement)
+    }
+
+    fn statement_error(&self, err: tokio_postgres::Error) -> Error {
+        // If schema changed, we better close this connection entirely
+        // than evicting prepared statements one by one.
+        // TODO: find and use the error code instead of using the English
+        // error message which will be translated on databases running in other locales.
+        if let Some(db_error) = err.as_db_error() {
+            if db_error.message() == "cached plan must not change result type" {
+                self.inner.bad.store(true, Ordering::Relaxed);
             }
         }
+
+        Error::DatabaseError(err)
     }
 
     /// Is the connection broken?
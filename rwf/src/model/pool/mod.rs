@@ -45,11 +45,13 @@ use once_cell::sync::OnceCell;
 use crate::config::get_config;
 
 pub mod connection;
+pub mod retry;
 pub mod transaction;
 
 use super::Error;
 
 pub use connection::Connection;
+pub use retry::Retry;
 pub use transaction::Transaction;
 
 static POOL: OnceCell<Pool> = OnceCell::new();
@@ -135,6 +137,7 @@ pub struct ConnectionGuard {
     pool: Pool,
     rollback: bool,
     leaked: bool,
+    transaction: bool,
 }
 
 impl ConnectionGuard {
@@ -151,6 +154,7 @@ impl ConnectionGuard {
             pool,
             rollback: false,
             leaked: false,
+            transaction: false,
         }
     }
 
@@ -158,6 +162,11 @@ impl ConnectionGuard {
         self.rollback = true;
     }
 
+    /// The connection is used by a [`Transaction`] which hasn't finished yet.
+    pub fn in_transaction(&self) -> bool {
+        self.transaction
+    }
+
     /// Get a reference to the underlying database connection.
     pub fn connection(&self) -> &Connection {
         self.connection.as_ref().unwrap()
@@ -241,6 +250,9 @@ pub struct PoolConfig {
 
     /// Maximum time a connection remains open and available while not in use.
     pub idle_timeout: Duration,
+
+    /// Statements executed on every new connection, in order.
+    pub on_connect: Vec<String>,
 }
 
 impl Default for PoolConfig {
@@ -249,6 +261,7 @@ impl Default for PoolConfig {
             pool_size: 10,
             checkout_timeout: Duration::from_secs(5),
             idle_timeout: Duration::from_secs(3600),
+            on_connect: vec![],
         }
     }
 }
@@ -337,6 +350,7 @@ impl Pool {
                 pool_size: config.pool_size,
                 idle_timeout: config.idle_timeout().unsigned_abs(),
                 checkout_timeout: config.checkout_timeout().unsigned_abs(),
+                on_connect: config.on_connect.clone(),
             },
         )
     }
@@ -431,7 +445,7 @@ impl Pool {
             };
 
             if need_more {
-                match Connection::new(&self.database_url).await {
+                match Connection::connect(&self.database_url, &self.config.on_connect).await {
                     Ok(connection) => return Ok(ConnectionGuard::new(connection, self.clone())),
                     Err(err) => {
                         {
@@ -556,6 +570,59 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_cached_plan_change() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut conn = pool.get().await?;
+
+        conn.client()
+            .batch_execute("CREATE TEMPORARY TABLE rwf_plan_change (id BIGINT)")
+            .await?;
+        conn.execute_cached("SELECT * FROM rwf_plan_change", &[])
+            .await?;
+        conn.client()
+            .batch_execute("ALTER TABLE rwf_plan_change ADD COLUMN name TEXT")
+            .await?;
+
+        // The connection is closed instead of failing the same way forever.
+        assert!(conn
+            .execute_cached("SELECT * FROM rwf_plan_change", &[])
+            .await
+            .is_err());
+        assert!(conn.bad());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_on_connect_statements() -> Result<(), Error> {
+        let config = get_config().database.clone();
+        let pool = Pool::new(
+            &config.database_url(),
+            PoolConfig {
+                on_connect: vec![
+                    "SET application_name = 'rwf_on_connect'".into(),
+                    "SET TIME ZONE 'UTC'".into(),
+                ],
+                ..Default::default()
+            },
+        );
+
+        let conn = pool.get().await?;
+        let rows = conn
+            .client()
+            .query(
+                "SELECT current_setting('application_name'), current_setting('TimeZone')",
+                &[],
+            )
+            .await?;
+
+        assert_eq!(rows[0].get::<_, String>(0), "rwf_on_connect");
+        assert_eq!(rows[0].get::<_, String>(1), "UTC");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_bad_pool() {
         env::set_var("RWF_DATABASE_CHECKOUT_TIMEOUT", "500");
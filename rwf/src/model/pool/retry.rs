@@ -0,0 +1,140 @@
+//! Retry queries on transient failures.
+//!
+//! When the database briefly goes away, e.g. during a failover or a restart, queries fail
+//! with connection errors. These, and serialization failures, usually succeed if retried
+//! a moment later. Retries use exponential backoff, starting at
+//! [`crate::config::DatabaseConfig::retry_backoff`].
+use std::future::Future;
+
+use tokio::time::{sleep, Duration};
+use tracing::warn;
+
+use super::Error;
+use crate::config::get_config;
+
+/// Retry policy.
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    /// How many times to retry a failed query.
+    pub max_retries: usize,
+    /// How long to wait before the first retry. Doubled on every retry.
+    pub backoff: Duration,
+}
+
+impl Retry {
+    /// Don't retry at all.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Retry policy from the global configuration.
+    pub fn from_config() -> Self {
+        let config = &get_config().database;
+
+        Self {
+            max_retries: config.max_retries,
+            backoff: config.retry_backoff().unsigned_abs(),
+        }
+    }
+
+    /// How long to wait before retrying, given the number of retries attempted so far.
+    pub fn delay(&self, attempt: usize) -> Duration {
+        self.backoff
+            .saturating_mul(2_u32.saturating_pow(attempt.min(16) as u32))
+    }
+
+    /// Run the operation, retrying it on transient errors.
+    ///
+    /// Errors which aren't transient, e.g. syntax errors or constraint violations,
+    /// are returned immediately.
+    pub async fn run<T, F, Fut>(&self, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match f().await {
+                Err(err) if attempt < self.max_retries && err.transient() => {
+                    let delay = self.delay(attempt);
+                    warn!(
+                        "transient database error, retrying in {:.3}s: {}",
+                        delay.as_secs_f64(),
+                        err
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn retry() -> Retry {
+        Retry {
+            max_retries: 3,
+            backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient() {
+        let attempts = AtomicUsize::new(0);
+
+        let result = retry()
+            .run(|| async {
+                match attempts.fetch_add(1, Ordering::Relaxed) {
+                    0 | 1 => Err(Error::IoError(std::io::ErrorKind::ConnectionReset.into())),
+                    _ => Ok(42),
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+
+        let attempts = AtomicUsize::new(0);
+        let result: Result<(), Error> = retry()
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(Error::IoError(std::io::ErrorKind::ConnectionReset.into()))
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_logic_error() {
+        let attempts = AtomicUsize::new(0);
+
+        let result: Result<(), Error> = retry()
+            .run(|| async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Err(Error::RecordNotFound)
+            })
+            .await;
+
+        assert!(matches!(result, Err(Error::RecordNotFound)));
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_delay() {
+        let retry = retry();
+        assert_eq!(retry.delay(0), Duration::from_millis(1));
+        assert_eq!(retry.delay(3), Duration::from_millis(8));
+    }
+}
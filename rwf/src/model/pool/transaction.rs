@@ -18,6 +18,7 @@ impl Transaction {
     pub async fn new(mut connection: ConnectionGuard) -> Result<Self, Error> {
         let start = Instant::now();
         connection.query_cached("BEGIN", &[]).await?;
+        connection.transaction = true;
 
         if get_config().general.log_queries {
             info!("BEGIN ({:.3} ms)", start.elapsed().as_secs_f64() * 1000.0);
@@ -36,6 +37,7 @@ impl Transaction {
 
         let start = Instant::now();
         self.connection.query_cached("COMMIT", &[]).await?;
+        self.connection.transaction = false;
 
         if get_config().general.log_queries {
             info!("COMMIT ({:.3} ms)", start.elapsed().as_secs_f64() * 1000.0);
@@ -51,6 +53,7 @@ impl Transaction {
 
         let start = Instant::now();
         self.connection.query_cached("ROLLBACK", &[]).await?;
+        self.connection.transaction = false;
 
         if get_config().general.log_queries {
             info!(
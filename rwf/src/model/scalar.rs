@@ -0,0 +1,95 @@
+//! Map rows into scalars and tuples.
+//!
+//! Useful for ad-hoc queries which don't return a model, e.g. counts or aggregates:
+//!
+//! ```ignore
+//! let count = Query::<i64>::raw("SELECT COUNT(*) FROM users", &[])
+//!     .fetch(&mut conn)
+//!     .await?;
+//! ```
+//!
+//! Scalars are read from the first column of the row; tuple elements from the columns
+//! in the same position.
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tokio_postgres::types::FromSql;
+use uuid::Uuid;
+
+use super::{Error, FromRow};
+
+macro_rules! scalar {
+    ($($ty:ty),+) => {
+        $(
+            impl FromRow for $ty {
+                fn from_row(row: tokio_postgres::Row) -> Result<Self, Error> {
+                    Ok(row.try_get(0)?)
+                }
+            }
+        )+
+    };
+}
+
+scalar!(
+    i64,
+    i32,
+    i16,
+    f64,
+    f32,
+    bool,
+    String,
+    Uuid,
+    OffsetDateTime,
+    PrimitiveDateTime,
+    serde_json::Value
+);
+
+macro_rules! tuple {
+    ($($name:ident: $index:tt),+) => {
+        impl<$($name),+> FromRow for ($($name,)+)
+        where
+            $($name: for<'a> FromSql<'a> + Clone + Send),+
+        {
+            fn from_row(row: tokio_postgres::Row) -> Result<Self, Error> {
+                Ok(($(row.try_get::<_, $name>($index)?,)+))
+            }
+        }
+    };
+}
+
+tuple!(A: 0);
+tuple!(A: 0, B: 1);
+tuple!(A: 0, B: 1, C: 2);
+tuple!(A: 0, B: 1, C: 2, D: 3);
+tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+
+#[cfg(test)]
+mod test {
+    use crate::model::{Pool, Query};
+
+    #[tokio::test]
+    async fn test_scalar_and_tuple() {
+        let pool = Pool::from_env();
+        let mut conn = pool.get().await.unwrap();
+
+        let count = Query::<i64>::raw("SELECT COUNT(*) FROM (VALUES (1), (2)) t", &[])
+            .fetch(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let (id,) = Query::<(i64,)>::raw("SELECT 5::BIGINT", &[])
+            .fetch(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(id, 5);
+
+        let rows = Query::<(i64, String)>::raw(
+            "SELECT * FROM (VALUES (1::BIGINT, 'one'), (2::BIGINT, 'two')) t ORDER BY 1",
+            &[],
+        )
+        .fetch_all(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(rows, vec![(1, "one".to_string()), (2, "two".to_string())]);
+    }
+}
@@ -1,12 +1,14 @@
 //! Implements the `SELECT` query.
+use super::Error;
 use crate::model::{
     column::ToColumn,
     filter::{Filter, JoinOp},
-    Column, Columns, Escape, FromRow, Join, Joins, Limit, Lock, OrderBy, Placeholders, ToSql,
-    ToValue, Value, WhereClause,
+    Column, Columns, EscapeIdentifier, FromRow, Join, Joins, Limit, Lock, OrderBy, Placeholders,
+    Row, ToSql, ToValue, Value, WhereClause,
 };
 
 use std::marker::PhantomData;
+use std::time::Duration;
 
 #[derive(PartialEq, Debug)]
 enum Op {
@@ -16,6 +18,8 @@ enum Op {
     GreaterThan,
     GreaterEqualThan,
     LesserEqualThan,
+    Like,
+    ILike,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -28,8 +32,11 @@ pub struct Select<T: FromRow + ?Sized> {
     pub placeholders: Placeholders,
     pub where_clause: WhereClause,
     pub joins: Joins,
+    ctes: Vec<(String, Select<Row>)>,
+    unions: Vec<(bool, Select<Row>)>,
     lock: Lock,
     group: bool,
+    select_exists: bool,
     _phantom: PhantomData<T>,
 }
 
@@ -45,15 +52,149 @@ impl<T: FromRow> Select<T> {
             placeholders: Placeholders::default(),
             where_clause: WhereClause::default(),
             joins: Joins::default(),
+            ctes: vec![],
+            unions: vec![],
             lock: Lock::default(),
             group: false,
+            select_exists: false,
             _phantom: PhantomData,
         }
     }
 
+    /// Add a common table expression, i.e. `WITH name AS (subquery)`, which the query can
+    /// reference as a table.
+    ///
+    /// Placeholders of the subquery are renumbered to follow the placeholders already
+    /// in this query, so values are bound correctly in both.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Select, Row, ToSql};
+    /// let recent = Select::<Row>::new("orders", "id").filter_gt("created_at", "2024-01-01");
+    /// let query = Select::<Row>::new("recent_orders", "id")
+    ///     .filter_and("user_id", 5)
+    ///     .with("recent_orders", recent);
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"WITH "recent_orders" AS (SELECT * FROM "orders" WHERE "orders"."created_at" > $2) SELECT * FROM "recent_orders" WHERE "recent_orders"."user_id" = $1"#
+    /// );
+    /// ```
+    pub fn with<S: FromRow>(mut self, name: &str, subquery: Select<S>) -> Self {
+        let subquery = self.bind_subquery(subquery);
+        self.ctes.push((name.to_string(), subquery));
+        self
+    }
+
+    /// Move the placeholders of a subquery after the ones already in this query.
+    fn bind_subquery<S: FromRow>(&mut self, subquery: Select<S>) -> Select<Row> {
+        let mut subquery = subquery.erase();
+        subquery.shift_placeholders(self.placeholders.len() as i32);
+        self.placeholders
+            .append(std::mem::take(&mut subquery.placeholders));
+        subquery
+    }
+
+    /// Renumber all placeholders used anywhere in the query, including its subqueries, by the offset.
+    fn shift_placeholders(&mut self, offset: i32) {
+        self.where_clause.shift_placeholders(offset);
+        self.joins.shift_placeholders(offset);
+
+        for (_, cte) in self.ctes.iter_mut() {
+            cte.shift_placeholders(offset);
+        }
+
+        for (_, union) in self.unions.iter_mut() {
+            union.shift_placeholders(offset);
+        }
+    }
+
+    /// Forget the type of the rows returned by the query, so it can be used as a subquery.
+    fn erase(self) -> Select<Row> {
+        Select {
+            table_name: self.table_name,
+            primary_key: self.primary_key,
+            columns: self.columns,
+            order_by: self.order_by,
+            limit: self.limit,
+            placeholders: self.placeholders,
+            where_clause: self.where_clause,
+            joins: self.joins,
+            ctes: self.ctes,
+            unions: self.unions,
+            lock: self.lock,
+            group: self.group,
+            select_exists: self.select_exists,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Combine the rows returned by this query and another query with `UNION`, removing duplicates.
+    ///
+    /// Both queries must return compatible columns, i.e. the same number of columns with the same types.
+    /// Placeholders of the other query are renumbered to follow the placeholders already in this query.
+    /// The ordering and limit of this query apply to the combined result.
+    ///
+    /// Postgres doesn't allow `FOR UPDATE` with `UNION`, so locking the combined query returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Select, Row, ToSql};
+    /// let admins = Select::<Row>::new("admins", "id").filter_and("active", "yes");
+    /// let query = Select::<Row>::new("users", "id")
+    ///     .filter_and("team_id", 5)
+    ///     .union(admins)
+    ///     .limit(10);
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"(SELECT * FROM "users" WHERE "users"."team_id" = $1) UNION (SELECT * FROM "admins" WHERE "admins"."active" = $2) LIMIT 10"#
+    /// );
+    /// ```
+    pub fn union<S: FromRow>(self, other: Select<S>) -> Self {
+        self.add_union(other, false)
+    }
+
+    /// Combine the rows returned by this query and another query with `UNION ALL`, keeping duplicates.
+    /// See [`Self::union`].
+    pub fn union_all<S: FromRow>(self, other: Select<S>) -> Self {
+        self.add_union(other, true)
+    }
+
+    fn add_union<S: FromRow>(mut self, other: Select<S>, all: bool) -> Self {
+        let other = self.bind_subquery(other);
+        self.unions.push((all, other));
+        self
+    }
+
+    /// Bind identical filter values only once, referencing the same placeholder
+    /// in every predicate which uses them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Select, Row, ToSql};
+    /// let query = Select::<Row>::new("users", "id")
+    ///     .intern_placeholders()
+    ///     .filter_and("created_by", 5)
+    ///     .filter_or("updated_by", 5);
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE ("users"."created_by" = $1) OR ("users"."updated_by" = $1)"#
+    /// );
+    /// assert_eq!(query.placeholders.len(), 1);
+    /// ```
+    pub fn intern_placeholders(mut self) -> Self {
+        self.placeholders = self.placeholders.intern();
+        self
+    }
+
     /// Add a LIMIT to the query.
     pub fn limit(mut self, limit: i64) -> Self {
-        self.limit = Limit::new(limit);
+        self.limit = self.limit.limit(limit);
         self
     }
 
@@ -79,11 +220,35 @@ impl<T: FromRow> Select<T> {
         self
     }
 
+    /// Lock the selected rows with `FOR UPDATE`, waiting at most `timeout` for rows locked by other transactions.
+    ///
+    /// The timeout is set with `SET LOCAL`, so the query must run inside a transaction.
+    /// If it expires, [`super::Error::LockTimeout`] is returned.
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock = self.lock.timeout(timeout);
+        self
+    }
+
+    /// The locking clause of the query.
+    pub fn locking(&self) -> &Lock {
+        &self.lock
+    }
+
     pub fn exists(mut self) -> Self {
         self.columns = self.columns.exists();
         self
     }
 
+    /// Check if any rows match the query, i.e. `SELECT EXISTS(SELECT 1 FROM ... WHERE ...)`.
+    ///
+    /// The filters and placeholders are preserved, while the selected columns,
+    /// ordering and limit are ignored. Unlike [`Select::exists`], which counts
+    /// all matching rows, the database can stop at the first match.
+    pub fn select_exists(mut self) -> Self {
+        self.select_exists = true;
+        self
+    }
+
     fn filter(
         mut self,
         column: impl ToColumn,
@@ -112,6 +277,13 @@ impl<T: FromRow> Select<T> {
                     Value::Record(Box::new(placeholder))
                 }
 
+                Value::Record(list) => Value::Record(Box::new(self.placeholders.add(&list))),
+
+                Value::Range((low, high)) => Value::Range((
+                    Box::new(self.placeholders.add(&low)),
+                    Box::new(self.placeholders.add(&high)),
+                )),
+
                 Value::Column(ref _column) => value,
                 Value::Function(ref _function) => value,
 
@@ -128,6 +300,8 @@ impl<T: FromRow> Select<T> {
             Op::GreaterThan => filter.gt(column, value),
             Op::GreaterEqualThan => filter.gte(column, value),
             Op::LesserEqualThan => filter.lte(column, value),
+            Op::Like => filter.like(column, value),
+            Op::ILike => filter.ilike(column, value),
         }
 
         match join_op {
@@ -138,6 +312,10 @@ impl<T: FromRow> Select<T> {
         self
     }
 
+    /// Add an equality filter, using the AND operator.
+    ///
+    /// Both a [`Value::List`] and a [`Value::Record`] are bound as a single array placeholder
+    /// and check membership, i.e. `= ANY($1)`.
     pub fn filter_and(mut self, column: impl ToColumn, value: impl ToValue) -> Self {
         self = self.filter(column, value, JoinOp::And, Op::Equals);
         self
@@ -178,6 +356,129 @@ impl<T: FromRow> Select<T> {
         self
     }
 
+    /// Filter rows matching a `LIKE` pattern, e.g. `%foo%`. The pattern is sent as a
+    /// placeholder, so `%` and `_` supplied by the user remain wildcards.
+    pub fn filter_like(mut self, column: impl ToColumn, pattern: impl ToValue) -> Self {
+        self = self.filter(column, pattern, JoinOp::And, Op::Like);
+        self
+    }
+
+    /// Filter rows matching a case-insensitive `ILIKE` pattern.
+    pub fn filter_ilike(mut self, column: impl ToColumn, pattern: impl ToValue) -> Self {
+        self = self.filter(column, pattern, JoinOp::And, Op::ILike);
+        self
+    }
+
+    /// Add a row constructor comparison, e.g. `("created_at", "id") > ($1, $2)`, using
+    /// the AND operator. Use it for keyset pagination over composite keys.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Select, Row, ToSql};
+    /// let query = Select::<Row>::new("posts", "id")
+    ///     .filter_row_gt(&[("created_at", "2024-01-01"), ("id", "5")]);
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "posts" WHERE ("posts"."created_at", "posts"."id") > ($1, $2)"#
+    /// );
+    /// ```
+    pub fn filter_row_gt(self, row: &[(impl ToColumn, impl ToValue)]) -> Self {
+        self.filter_row(row, |filter, row| filter.row_gt(row))
+    }
+
+    /// Add a row constructor comparison using `<`, see [`Self::filter_row_gt`].
+    pub fn filter_row_lt(self, row: &[(impl ToColumn, impl ToValue)]) -> Self {
+        self.filter_row(row, |filter, row| filter.row_lt(row))
+    }
+
+    fn filter_row(
+        mut self,
+        row: &[(impl ToColumn, impl ToValue)],
+        op: impl FnOnce(&mut Filter, &[(Column, Value)]),
+    ) -> Self {
+        let row = row
+            .iter()
+            .map(|(column, value)| {
+                (
+                    self.qualify(column.to_column()),
+                    self.placeholders.add(&value.to_value()),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut filter = Filter::default();
+        op(&mut filter, &row);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Filter rows where the JSONB column has the top-level key, e.g. `"data" ? $1`.
+    pub fn filter_json_has_key(mut self, column: impl ToColumn, key: impl ToValue) -> Self {
+        let column = self.qualify(column);
+        let key = self.placeholders.add(&key.to_value());
+
+        let mut filter = Filter::default();
+        filter.json_has_key(column, key);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Filter rows where the value of the key in the JSONB column, extracted as text, is equal
+    /// to the value, e.g. `"data" ->> $1 = $2`.
+    pub fn filter_json_path_eq(
+        mut self,
+        column: impl ToColumn,
+        path: impl ToValue,
+        value: impl ToValue,
+    ) -> Self {
+        let column = self.qualify(column);
+        let path = self.placeholders.add(&path.to_value());
+        let value = self.placeholders.add(&value.to_value());
+
+        let mut filter = Filter::default();
+        filter.json_path_eq(column, path, value);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Filter rows where the column is equal to the value cast to a Postgres type,
+    /// e.g. `"tasks"."due_on" = $1::text::date`. See [`Filter::add_typed`] for the supported types and values.
+    pub fn filter_typed(
+        mut self,
+        column: impl ToColumn,
+        value: impl ToValue,
+        cast: &str,
+    ) -> Result<Self, Error> {
+        let column = self.qualify(column);
+        let value = Filter::typed_value(value.to_value())?;
+        // Null is handled by the filter.
+        let value = if value.is_null() {
+            value
+        } else {
+            self.placeholders.add(&value)
+        };
+
+        let mut filter = Filter::default();
+        filter.add_typed(column, value, cast)?;
+        self.where_clause.concat(filter);
+
+        Ok(self)
+    }
+
+    fn qualify(&self, column: impl ToColumn) -> Column {
+        let column = column.to_column();
+        if !column.qualified() {
+            column.qualify(&self.table_name)
+        } else {
+            column
+        }
+    }
+
     pub fn join(mut self, join: Join) -> Self {
         self.joins = self.joins.add(join);
         self.columns = self.columns.table_name(&self.table_name);
@@ -249,21 +550,310 @@ impl<T: FromRow> Select<T> {
 
 impl<T: FromRow> ToSql for Select<T> {
     fn to_sql(&self) -> String {
+        let with = if self.ctes.is_empty() {
+            "".to_string()
+        } else {
+            format!(
+                "WITH {} ",
+                self.ctes
+                    .iter()
+                    .map(|(name, query)| format!(
+                        r#""{}" AS ({})"#,
+                        name.escape_identifier(),
+                        query.to_sql()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+
+        if self.select_exists && self.unions.is_empty() {
+            return format!(
+                r#"{}SELECT EXISTS(SELECT 1 FROM "{}"{}{}) AS "exists""#,
+                with,
+                self.table_name.escape_identifier(),
+                self.joins.to_sql(),
+                self.where_clause.to_sql(),
+            );
+        }
+
         let group = if self.group {
             format!("GROUP BY {} ", self.columns.to_sql())
         } else {
             "".to_string()
         };
-        format!(
-            r#"SELECT {} FROM "{}"{}{}{}{}{}{}"#,
+        let select = format!(
+            r#"SELECT {} FROM "{}"{}{}{}"#,
             self.columns.to_sql(),
-            self.table_name.escape(),
+            self.table_name.escape_identifier(),
             self.joins.to_sql(),
             self.where_clause.to_sql(),
             group,
+        );
+
+        if self.unions.is_empty() {
+            return format!(
+                "{}{}{}{}{}",
+                with,
+                select,
+                self.order_by.to_sql(),
+                self.limit.to_sql(),
+                self.lock.to_sql(),
+            );
+        }
+
+        let unions = self
+            .unions
+            .iter()
+            .map(|(all, query)| {
+                format!(
+                    " UNION {}({})",
+                    if *all { "ALL " } else { "" },
+                    query.to_sql()
+                )
+            })
+            .collect::<String>();
+
+        // Any row of the combined result will do.
+        if self.select_exists {
+            return format!(
+                r#"{}SELECT EXISTS(({}){}) AS "exists""#,
+                with,
+                select.trim_end(),
+                unions,
+            );
+        }
+
+        // Ordering, limit and locking apply to the combined result.
+        format!(
+            "{}({}){}{}{}{}",
+            with,
+            select.trim_end(),
+            unions,
             self.order_by.to_sql(),
             self.limit.to_sql(),
             self.lock.to_sql(),
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{Row, ToOrderBy};
+
+    #[test]
+    fn test_with() {
+        let active = Select::<Row>::new("users", "id")
+            .filter_and("active", "yes")
+            .filter_gt("created_at", "2024-01-01");
+        let query = Select::<Row>::new("active_users", "id")
+            .with("active_users", active)
+            .filter_and("team_id", 5);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"WITH "active_users" AS (SELECT * FROM "users" WHERE "users"."active" = $1 AND "users"."created_at" > $2) SELECT * FROM "active_users" WHERE "active_users"."team_id" = $3"#
+        );
+
+        let values = (1..=3)
+            .map(|id| query.placeholders().get(id).unwrap().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                Value::String("yes".into()),
+                Value::String("2024-01-01".into()),
+                Value::Int(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_with_nested() {
+        let teams = Select::<Row>::new("teams", "id").filter_and("region", "eu");
+        let members = Select::<Row>::new("team_users", "id")
+            .filter_gt("joined_at", "2024-01-01")
+            .with("eu_teams", teams);
+        let orders = Select::<Row>::new("orders", "id").filter_gt("total", 100);
+
+        let query = Select::<Row>::new("members", "id")
+            .filter_and("active", "yes")
+            .with("members", members)
+            .with("big_orders", orders)
+            .filter_lt("id", 50);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"WITH "members" AS (WITH "eu_teams" AS (SELECT * FROM "teams" WHERE "teams"."region" = $3) SELECT * FROM "team_users" WHERE "team_users"."joined_at" > $2), "big_orders" AS (SELECT * FROM "orders" WHERE "orders"."total" > $4) SELECT * FROM "members" WHERE "members"."active" = $1 AND "members"."id" < $5"#
+        );
+
+        let values = (1..=5)
+            .map(|id| query.placeholders().get(id).unwrap().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                Value::String("yes".into()),
+                Value::String("2024-01-01".into()),
+                Value::String("eu".into()),
+                Value::Int(100),
+                Value::Int(50),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_union_all() {
+        let archived = Select::<Row>::new("archived_posts", "id")
+            .filter_and("author_id", 7)
+            .filter_gt("created_at", "2024-01-01");
+        let query = Select::<Row>::new("posts", "id")
+            .filter_and("author_id", 7)
+            .union_all(archived)
+            .union(Select::<Row>::new("drafts", "id").filter_lt("id", 100))
+            .filter_and("published", "yes")
+            .order_by(("created_at", "DESC").to_order_by())
+            .limit(25);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"(SELECT * FROM "posts" WHERE "posts"."author_id" = $1 AND "posts"."published" = $5) UNION ALL (SELECT * FROM "archived_posts" WHERE "archived_posts"."author_id" = $2 AND "archived_posts"."created_at" > $3) UNION (SELECT * FROM "drafts" WHERE "drafts"."id" < $4) ORDER BY "created_at" DESC LIMIT 25"#
+        );
+
+        let values = (1..=5)
+            .map(|id| query.placeholders().get(id).unwrap().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                Value::Int(7),
+                Value::Int(7),
+                Value::String("2024-01-01".into()),
+                Value::Int(100),
+                Value::String("yes".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_union_nested() {
+        let drafts = Select::<Row>::new("drafts", "id")
+            .filter_and("author_id", 1)
+            .union(Select::<Row>::new("archived", "id").filter_and("author_id", 2));
+        let recent = Select::<Row>::new("imported", "id")
+            .filter_gt("created_at", "2024-01-01")
+            .with(
+                "authors",
+                Select::<Row>::new("users", "id").filter_and("active", "yes"),
+            );
+        let query = Select::<Row>::new("posts", "id")
+            .filter_and("author_id", 3)
+            .union(drafts)
+            .union_all(recent);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"(SELECT * FROM "posts" WHERE "posts"."author_id" = $1) UNION ((SELECT * FROM "drafts" WHERE "drafts"."author_id" = $2) UNION (SELECT * FROM "archived" WHERE "archived"."author_id" = $3)) UNION ALL (WITH "authors" AS (SELECT * FROM "users" WHERE "users"."active" = $5) SELECT * FROM "imported" WHERE "imported"."created_at" > $4)"#
+        );
+
+        let values = (1..=5)
+            .map(|id| query.placeholders().get(id).unwrap().clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                Value::Int(3),
+                Value::Int(1),
+                Value::Int(2),
+                Value::String("2024-01-01".into()),
+                Value::String("yes".into()),
+            ]
+        );
+
+        // Neither the unions nor the lock are dropped.
+        let exists = query.clone().select_exists();
+        assert!(exists.to_sql().starts_with(
+            r#"SELECT EXISTS((SELECT * FROM "posts" WHERE "posts"."author_id" = $1) UNION (("#
+        ));
+        assert!(exists.to_sql().ends_with(r#") AS "exists""#));
+        assert!(query.lock().to_sql().ends_with(" FOR UPDATE"));
+    }
+
+    #[test]
+    fn test_filter_list_and_record() {
+        let ids = Value::new(&[1_i64, 2][..]);
+        let query = Select::<Row>::new("users", "id")
+            .filter_and("id", ids.clone())
+            .filter_not("team_id", ids.clone().into_record());
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = ANY($1) AND "users"."team_id" <> ALL($2)"#
+        );
+        assert_eq!(query.placeholders().get(1), Some(&ids));
+        assert_eq!(query.placeholders().get(2), Some(&ids));
+    }
+
+    #[test]
+    fn test_filter_between() {
+        let query = Select::<Row>::new("users", "id")
+            .filter_and("age", 18_i64..=65)
+            .filter_and("name", "bob");
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."age" BETWEEN $1 AND $2 AND "users"."name" = $3"#
+        );
+        assert_eq!(query.placeholders().get(2), Some(&Value::Integer(65)));
+    }
+
+    #[test]
+    fn test_filter_like() {
+        let query = Select::<Row>::new("users", "id")
+            .filter_like("name", "100%_done")
+            .filter_ilike("email", "%@test.com");
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."name" LIKE $1 AND "users"."email" ILIKE $2"#
+        );
+        assert_eq!(
+            query.placeholders().get(1),
+            Some(&Value::String("100%_done".into()))
+        );
+    }
+
+    #[test]
+    fn test_filter_row() {
+        let query = Select::<Row>::new("posts", "id")
+            .filter_and("author_id", 7)
+            .filter_row_lt(&[("created_at", "2024-01-01"), ("id", "100")])
+            .filter_gt("score", 3);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "posts" WHERE "posts"."author_id" = $1 AND ("posts"."created_at", "posts"."id") < ($2, $3) AND "posts"."score" > $4"#
+        );
+        assert_eq!(query.where_clause.placeholders(), 4);
+        assert_eq!(
+            query.placeholders().get(3),
+            Some(&Value::String("100".into()))
+        );
+    }
+
+    #[test]
+    fn test_intern_placeholders() {
+        let ids = vec![1_i64, 2, 3];
+        let query = Select::<Row>::new("messages", "id")
+            .intern_placeholders()
+            .filter_and("sender_id", ids.as_slice())
+            .filter_or("recipient_id", ids.as_slice());
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "messages" WHERE ("messages"."sender_id" = ANY($1)) OR ("messages"."recipient_id" = ANY($1))"#
+        );
+        assert_eq!(query.placeholders.len(), 1);
+    }
+}
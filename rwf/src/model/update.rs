@@ -1,16 +1,45 @@
 //! Implements the `UPDATE` statement.
 use super::{
-    Column, Escape, FromRow, Model, Placeholders, Select, ToColumn, ToSql, ToValue, WhereClause,
+    Column, EscapeIdentifier, FromRow, Model, Placeholders, Select, ToColumn, ToSql, ToValue,
+    Value, WhereClause,
 };
 use std::marker::PhantomData;
 
+/// `UPDATE` statement.
+///
+/// Values in the `SET` and `WHERE` clauses are bound to placeholders in the order
+/// they are added, so they can be mixed freely.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::model::{Update, ToSql};
+/// # use rwf::prelude::*;
+/// #[derive(Clone, macros::Model)]
+/// struct User {
+///     id: Option<i64>,
+///     email: String,
+///     admin: bool,
+/// }
+///
+/// let update = Update::<User>::empty()
+///     .set("email", "alice@example.com")
+///     .set("admin", true)
+///     .filter("id", 5);
+///
+/// assert_eq!(
+///     update.to_sql(),
+///     r#"UPDATE "users" SET "email" = $1, "admin" = $2 WHERE "users"."id" = $3 RETURNING *"#
+/// );
+/// ```
 #[derive(Debug, Clone)]
 pub struct Update<T> {
     table_name: String,
     primary_key: String,
     pub placeholders: Placeholders,
-    columns: Vec<Column>,
+    columns: Vec<(Column, Value)>,
     where_clause: WhereClause,
+    returning: bool,
     marker: PhantomData<T>,
 }
 
@@ -22,6 +51,7 @@ impl<T: Model> Update<T> {
             placeholders: Placeholders::new(),
             columns: vec![],
             where_clause: WhereClause::default(),
+            returning: true,
             marker: PhantomData,
         }
     }
@@ -51,11 +81,45 @@ impl<T: Model> Update<T> {
 
     pub fn columns(mut self, columns: &[impl ToColumn], values: &[impl ToValue]) -> Self {
         for (column, value) in columns.iter().zip(values.iter()) {
-            self.columns.push(column.to_column());
-            self.placeholders.add(&value.to_value());
+            self = self.set(column.to_column(), value.to_value());
         }
         self
     }
+
+    /// Set the column to the value. The value is bound to the next placeholder.
+    pub fn set(mut self, column: impl ToColumn, value: impl ToValue) -> Self {
+        let placeholder = self.placeholders.add(&value.to_value());
+        self.columns.push((column.to_column(), placeholder));
+        self
+    }
+
+    /// Only update rows where the column is equal to the value. The value is bound
+    /// to the next placeholder, unless it's `NULL`.
+    pub fn filter(mut self, column: impl ToColumn, value: impl ToValue) -> Self {
+        let column = column.to_column();
+        let column = if column.qualified() {
+            column
+        } else {
+            column.qualify(&self.table_name)
+        };
+
+        let value = value.to_value();
+        let value = if value.is_null() {
+            value
+        } else {
+            self.placeholders.add(&value)
+        };
+
+        self.where_clause.add(column, value);
+        self
+    }
+
+    /// Don't return the updated rows. Used when only the number
+    /// of affected rows is needed.
+    pub fn without_returning(mut self) -> Self {
+        self.returning = false;
+        self
+    }
 }
 
 impl<T: Model> From<Select<T>> for Update<T> {
@@ -70,22 +134,21 @@ impl<T: Model> From<Select<T>> for Update<T> {
 
 impl<T: FromRow> ToSql for Update<T> {
     fn to_sql(&self) -> String {
-        let where_placeholders = self.where_clause.placeholders();
         let sets = self
             .columns
             .iter()
-            .enumerate()
-            .map(|(idx, column)| {
-                format!(r#"{} = ${}"#, column.to_sql(), idx + where_placeholders + 1)
-            })
+            .map(|(column, value)| format!(r#"{} = {}"#, column.to_sql(), value.to_sql()))
             .collect::<Vec<_>>()
             .join(", ");
 
+        let returning = if self.returning { " RETURNING *" } else { "" };
+
         format!(
-            r#"UPDATE "{}" SET {}{} RETURNING *"#,
-            self.table_name.escape(),
+            r#"UPDATE "{}" SET {}{}{}"#,
+            self.table_name.escape_identifier(),
             sets,
             self.where_clause.to_sql(),
+            returning,
         )
     }
 }
@@ -1,12 +1,12 @@
 //! Handles conversions between database types and Rust types.
-use bytes::BytesMut;
-use time::{OffsetDateTime, PrimitiveDateTime};
+use bytes::{BufMut, BytesMut};
+use time::{Duration, OffsetDateTime, PrimitiveDateTime};
 use tokio_postgres::types::{to_sql_checked, IsNull, Type};
 use uuid::Uuid;
 
 use std::{net::IpAddr, ops::RangeInclusive};
 
-use super::{Column, Error, Escape, ToSql};
+use super::{Aggregate, Column, Error, Escape, ToSql, Window};
 
 /// A value that can be converted to and from the database.
 ///
@@ -35,6 +35,8 @@ pub enum Value {
     TimestampT(OffsetDateTime),
     /// `TIMESTAMP`
     Timestamp(PrimitiveDateTime),
+    /// `INTERVAL`, e.g. `INTERVAL '7 days'`.
+    Interval(Duration),
     /// `INET`
     IpAddr(IpAddr),
     /// `UUID`
@@ -55,6 +57,10 @@ pub enum Value {
     Optional(Box<Option<Value>>),
     /// A database function, e.g. `now()`
     Function((String, Vec<Value>)),
+    /// Window function, e.g. `ROW_NUMBER() OVER (PARTITION BY x)`.
+    Window(Box<Window>),
+    /// Aggregate function, e.g. `COUNT("users"."id")`.
+    Aggregate(Box<Aggregate>),
     /// `NULL`.
     Null,
 }
@@ -107,6 +113,63 @@ impl Value {
         Self::Function((name.to_string(), vec![]))
     }
 
+    /// The database's current time, i.e. `now()`. The value is computed by the database,
+    /// so it can be used in filters and inserts without a placeholder.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Value, ToSql};
+    /// let now = Value::now();
+    ///
+    /// assert_eq!(now.to_sql(), r#""now"()"#);
+    /// ```
+    pub fn now() -> Self {
+        Self::function("now")
+    }
+
+    /// Wrap the value into a record, which filters compare with `= ANY(...)`, i.e. the column
+    /// must be equal to one of the values. A record is returned unchanged.
+    ///
+    /// [`crate::model::Filter::add`] compares a [`Value::List`] to the column as a whole array,
+    /// so convert the list into a record to check membership instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Value, Filter, Column, ToSql};
+    /// let ids = Value::new(&[1_i64, 2, 3][..]);
+    ///
+    /// let mut filter = Filter::default();
+    /// filter.add(Column::name("id"), ids.into_record());
+    ///
+    /// assert_eq!(filter.to_sql(), r#""id" = ANY({1, 2, 3})"#);
+    /// ```
+    pub fn into_record(self) -> Value {
+        match self {
+            Value::Record(_) => self,
+            value => Value::Record(Box::new(value)),
+        }
+    }
+
+    /// Unwrap the values of a record, so filters compare the column to them as a whole,
+    /// e.g. `"tags" = {1, 2, 3}`. Other values are returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::Value;
+    /// let record = Value::new(&[1_i64, 2, 3][..]).into_record();
+    ///
+    /// assert_eq!(record.into_list(), Value::new(&[1_i64, 2, 3][..]));
+    /// ```
+    pub fn into_list(self) -> Value {
+        match self {
+            Value::Record(value) => *value,
+            value => value,
+        }
+    }
+
     /// Checks if this value is a placeholder, as opposed to an actual value.
     pub fn placeholder(&self) -> bool {
         match self {
@@ -337,6 +400,18 @@ impl ToValue for Option<OffsetDateTime> {
     }
 }
 
+impl ToValue for Duration {
+    fn to_value(&self) -> Value {
+        Value::Interval(*self)
+    }
+}
+
+impl ToValue for Option<Duration> {
+    fn to_value(&self) -> Value {
+        Value::Optional(Box::new(self.as_ref().map(|v| v.to_value())))
+    }
+}
+
 impl ToValue for bool {
     fn to_value(&self) -> Value {
         Value::Boolean(*self)
@@ -352,21 +427,42 @@ impl tokio_postgres::types::ToSql for Value {
         use std::ops::Deref;
         match self {
             Value::String(string) => string.to_sql(ty, out),
-            Value::Integer(integer) => integer.to_sql(ty, out),
+            // Integers are cast to the column type, e.g. when binding
+            // elements of an INTEGER[] array.
+            Value::Integer(integer) | Value::BigInt(integer) => match *ty {
+                Type::INT4 => i32::try_from(*integer)?.to_sql(ty, out),
+                Type::INT2 => i16::try_from(*integer)?.to_sql(ty, out),
+                _ => integer.to_sql(ty, out),
+            },
 
             // Rust default number is an i32.
             // If the field is a bigint, this will automatically cast it.
-            Value::Int(integer) => match ty {
-                &Type::INT8 => (*integer as i64).to_sql(ty, out),
+            Value::Int(integer) => match *ty {
+                Type::INT8 => (*integer as i64).to_sql(ty, out),
+                Type::INT2 => i16::try_from(*integer)?.to_sql(ty, out),
+                _ => integer.to_sql(ty, out),
+            },
+            Value::SmallInt(integer) => match *ty {
+                Type::INT8 => (*integer as i64).to_sql(ty, out),
+                Type::INT4 => (*integer as i32).to_sql(ty, out),
                 _ => integer.to_sql(ty, out),
             },
-            Value::BigInt(integer) => integer.to_sql(ty, out),
-            Value::SmallInt(integer) => integer.to_sql(ty, out),
-            Value::Float(float) => float.to_sql(ty, out),
+            Value::Float(float) => match *ty {
+                Type::FLOAT4 => (*float as f32).to_sql(ty, out),
+                _ => float.to_sql(ty, out),
+            },
             Value::Real(float) => float.to_sql(ty, out),
             Value::Boolean(b) => b.to_sql(ty, out),
             Value::TimestampT(timestamp) => timestamp.to_sql(ty, out),
             Value::Timestamp(timestamp) => timestamp.to_sql(ty, out),
+            Value::Interval(interval) => {
+                // Binary format: microseconds, days, months.
+                let micros = i64::try_from(interval.whole_microseconds())?;
+                out.put_i64(micros);
+                out.put_i32(0);
+                out.put_i32(0);
+                Ok(IsNull::No)
+            }
             Value::IpAddr(ip) => ip.to_sql(ty, out),
             Value::Uuid(uuid) => uuid.to_sql(ty, out),
             Value::List(values) => values.to_sql(ty, out),
@@ -409,6 +505,23 @@ impl<'a> tokio_postgres::types::FromSql<'a> for Value {
             &Type::TIMESTAMPTZ => Ok(Value::TimestampT(OffsetDateTime::from_sql(ty, raw)?)),
             &Type::TIMESTAMP => Ok(Value::Timestamp(PrimitiveDateTime::from_sql(ty, raw)?)),
             &Type::UUID => Ok(Value::Uuid(Uuid::from_sql(ty, raw)?)),
+            &Type::INTERVAL => {
+                if raw.len() != 16 {
+                    return Err("invalid interval length".into());
+                }
+
+                let micros = i64::from_be_bytes(raw[0..8].try_into()?);
+                let days = i32::from_be_bytes(raw[8..12].try_into()?);
+                let months = i32::from_be_bytes(raw[12..16].try_into()?);
+
+                // Months don't have a fixed length, so assume 30 days like Postgres does
+                // when comparing intervals.
+                Ok(Value::Interval(
+                    Duration::microseconds(micros)
+                        + Duration::days(days as i64)
+                        + Duration::days(months as i64 * 30),
+                ))
+            }
 
             ty => todo!("unimplemented conversion from {:?} to rust", ty),
         }
@@ -439,6 +552,10 @@ impl ToSql for Value {
             Real(float) => float.to_string(),
             IpAddr(ip) => ip.to_string(),
             Uuid(uuid) => uuid.to_string(),
+            Interval(interval) => match interval.subsec_microseconds() {
+                0 => format!("INTERVAL '{} seconds'", interval.whole_seconds()),
+                _ => format!("INTERVAL '{} microseconds'", interval.whole_microseconds()),
+            },
             Placeholder(number) => format!("${}", number),
             Range((a, b)) => format!("BETWEEN {} AND {}", a.to_sql(), b.to_sql()),
             List(values) => format!(
@@ -468,6 +585,8 @@ impl ToSql for Value {
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
+            Value::Window(window) => window.to_sql(),
+            Value::Aggregate(aggregate) => aggregate.to_sql(),
             Value::Null => "NULL".to_string(),
             value => todo!("to_sql not implemented for {:?}", value),
         }
@@ -525,6 +644,9 @@ impl From<Value> for serde_json::Value {
                 use time::format_description::well_known::Rfc2822;
                 serde_json::Value::String(timestamp.format(&Rfc2822).unwrap())
             }
+            Value::Interval(interval) => {
+                serde_json::Value::Number(Number::from_f64(interval.as_seconds_f64()).unwrap())
+            }
             Value::List(list) => {
                 let mut values = vec![];
                 for v in list {
@@ -535,6 +657,8 @@ impl From<Value> for serde_json::Value {
             Value::Record(_) => serde_json::Value::Null,
             Value::Column(_) => serde_json::Value::Null,
             Value::Function(_) => serde_json::Value::Null,
+            Value::Window(_) => serde_json::Value::Null,
+            Value::Aggregate(_) => serde_json::Value::Null,
             Value::Null => serde_json::Value::Null,
             Value::Placeholder(_) => serde_json::Value::Null,
             Value::Range(_) => serde_json::Value::Null,
@@ -552,6 +676,100 @@ mod test {
         assert_eq!(value.to_sql(), "BETWEEN 1 AND 25");
     }
 
+    #[test]
+    fn test_record_list_conversion() {
+        use crate::model::{Column, Filter};
+
+        let list = Value::new(&[1_i64, 2][..]);
+        let record = list.clone().into_record();
+        assert_eq!(record, Value::Record(Box::new(list.clone())));
+        assert_eq!(record.clone().into_record(), record);
+        assert_eq!(record.clone().into_list(), list);
+        assert_eq!(list.clone().into_list(), list);
+
+        // Records check membership, lists compare the whole array.
+        let mut filter = Filter::default();
+        filter.add(Column::name("id"), record.clone());
+        filter.add(Column::name("tags"), list.clone());
+        filter.add_not(Column::name("team_id"), record.clone());
+        assert_eq!(
+            filter.to_sql(),
+            r#""id" = ANY({1, 2}) AND "tags" = {1, 2} AND "team_id" <> ALL({1, 2})"#
+        );
+    }
+
+    #[test]
+    fn test_list_binds_as_array() {
+        use tokio_postgres::types::FromSql;
+
+        let list = Value::List(vec![
+            Value::Integer(1),
+            Value::Integer(2),
+            Value::Integer(3),
+        ]);
+
+        for ty in [Type::INT2_ARRAY, Type::INT4_ARRAY, Type::INT8_ARRAY] {
+            let mut out = BytesMut::new();
+            tokio_postgres::types::ToSql::to_sql(&list, &ty, &mut out).unwrap();
+
+            let values = match ty {
+                Type::INT2_ARRAY => Vec::<i16>::from_sql(&ty, &out)
+                    .unwrap()
+                    .into_iter()
+                    .map(|v| v as i64)
+                    .collect(),
+                Type::INT4_ARRAY => Vec::<i32>::from_sql(&ty, &out)
+                    .unwrap()
+                    .into_iter()
+                    .map(|v| v as i64)
+                    .collect(),
+                _ => Vec::<i64>::from_sql(&ty, &out).unwrap(),
+            };
+            assert_eq!(values, vec![1, 2, 3]);
+        }
+
+        let list = Value::List(vec![
+            Value::String("apple".into()),
+            Value::String("banana".into()),
+        ]);
+        let mut out = BytesMut::new();
+        tokio_postgres::types::ToSql::to_sql(&list, &Type::TEXT_ARRAY, &mut out).unwrap();
+        assert_eq!(
+            Vec::<String>::from_sql(&Type::TEXT_ARRAY, &out).unwrap(),
+            vec!["apple", "banana"]
+        );
+
+        // Out of range for the column type.
+        let list = Value::List(vec![Value::Integer(i64::MAX)]);
+        let mut out = BytesMut::new();
+        assert!(tokio_postgres::types::ToSql::to_sql(&list, &Type::INT4_ARRAY, &mut out).is_err());
+    }
+
+    #[test]
+    fn test_interval() {
+        use tokio_postgres::types::FromSql;
+
+        let value = Value::new(Duration::days(7));
+        assert_eq!(value, Value::Interval(Duration::days(7)));
+        assert_eq!(ToSql::to_sql(&value), "INTERVAL '604800 seconds'");
+
+        let value = Value::new(Duration::milliseconds(1500));
+        assert_eq!(ToSql::to_sql(&value), "INTERVAL '1500000 microseconds'");
+
+        let mut out = BytesMut::new();
+        tokio_postgres::types::ToSql::to_sql(
+            &Value::new(Duration::seconds(1)),
+            &Type::INTERVAL,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(&out[..8], &1_000_000_i64.to_be_bytes());
+        assert_eq!(&out[8..], &[0u8; 8]);
+
+        let value = Value::from_sql(&Type::INTERVAL, &out).unwrap();
+        assert_eq!(value, Value::Interval(Duration::seconds(1)));
+    }
+
     #[test]
     fn test_function_args() {
         let value = Value::Function(("lower".into(), vec!["my string".to_value()]));
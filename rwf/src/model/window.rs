@@ -0,0 +1,148 @@
+//! Implements window functions, e.g. `ROW_NUMBER() OVER (PARTITION BY x ORDER BY y)`.
+use super::{Column, Escape, OrderBy, ToColumn, ToOrderBy, ToSql, ToValue, Value};
+
+/// Window function, computed over a set of rows related to the current row.
+///
+/// Add it to the selected columns with [`crate::model::Query::column`], using an alias.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::model::{Window, ToSql};
+/// let rank = Window::row_number()
+///     .partition_by("team_id")
+///     .order_by(("score", "DESC"))
+///     .alias("rank");
+///
+/// assert_eq!(
+///     rank.to_sql(),
+///     r#"ROW_NUMBER() OVER (PARTITION BY "team_id" ORDER BY "score" DESC) AS "rank""#
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    function: String,
+    args: Vec<Value>,
+    partition_by: Vec<Column>,
+    order_by: OrderBy,
+}
+
+impl Window {
+    /// Create a window function with the given name and arguments, e.g. `SUM("amount")`.
+    pub fn new(function: impl ToString, args: &[impl ToValue]) -> Self {
+        Self {
+            function: function.to_string(),
+            args: args.iter().map(|arg| arg.to_value()).collect(),
+            partition_by: vec![],
+            order_by: OrderBy::default(),
+        }
+    }
+
+    /// `ROW_NUMBER()`, the number of the current row within its partition, starting at 1.
+    pub fn row_number() -> Self {
+        Self::new("ROW_NUMBER", &[] as &[Value])
+    }
+
+    /// `RANK()`, rank of the current row with gaps.
+    pub fn rank() -> Self {
+        Self::new("RANK", &[] as &[Value])
+    }
+
+    /// `DENSE_RANK()`, rank of the current row without gaps.
+    pub fn dense_rank() -> Self {
+        Self::new("DENSE_RANK", &[] as &[Value])
+    }
+
+    /// `SUM(column)`, e.g. for running totals when combined with [`Self::order_by`].
+    pub fn sum(column: impl ToColumn) -> Self {
+        Self::new("SUM", &[Value::Column(column.to_column())])
+    }
+
+    /// Add a column to the `PARTITION BY` clause.
+    pub fn partition_by(mut self, column: impl ToColumn) -> Self {
+        self.partition_by.push(column.to_column());
+        self
+    }
+
+    /// Add columns to the `ORDER BY` clause.
+    pub fn order_by(mut self, order_by: impl ToOrderBy) -> Self {
+        self.order_by = self.order_by + order_by.to_order_by();
+        self
+    }
+
+    /// Name the result of the window function, so it can be selected as a column.
+    pub fn alias(self, name: impl ToString) -> Column {
+        Column::name(name).as_value(self)
+    }
+}
+
+impl ToSql for Window {
+    fn to_sql(&self) -> String {
+        let mut over = vec![];
+
+        if !self.partition_by.is_empty() {
+            over.push(format!(
+                "PARTITION BY {}",
+                self.partition_by
+                    .iter()
+                    .map(|column| column.to_sql())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        if !self.order_by.is_empty() {
+            over.push(self.order_by.to_sql().trim().to_string());
+        }
+
+        format!(
+            "{}({}) OVER ({})",
+            self.function.escape().to_uppercase(),
+            self.args
+                .iter()
+                .map(|arg| arg.to_sql())
+                .collect::<Vec<_>>()
+                .join(", "),
+            over.join(" ")
+        )
+    }
+}
+
+impl ToValue for Window {
+    fn to_value(&self) -> Value {
+        Value::Window(Box::new(self.clone()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{OrderBy, Row, Select};
+
+    #[test]
+    fn test_row_number() {
+        let window = Window::row_number()
+            .partition_by(Column::new("users", "team_id"))
+            .order_by(OrderBy::desc(Column::new("users", "score")))
+            .order_by(OrderBy::asc(Column::new("users", "id")));
+
+        assert_eq!(
+            window.to_sql(),
+            r#"ROW_NUMBER() OVER (PARTITION BY "users"."team_id" ORDER BY "users"."score" DESC, "users"."id" ASC)"#
+        );
+
+        let select = Select::<Row>::new("users", "id").select_additional(window.alias("rank"));
+        assert_eq!(
+            select.to_sql(),
+            r#"SELECT *, ROW_NUMBER() OVER (PARTITION BY "users"."team_id" ORDER BY "users"."score" DESC, "users"."id" ASC) AS "rank" FROM "users""#
+        );
+
+        let total = Window::sum("amount").order_by("created_at").alias("total");
+        assert_eq!(
+            total.to_sql(),
+            r#"SUM("amount") OVER (ORDER BY created_at) AS "total""#
+        );
+
+        assert_eq!(Window::rank().to_sql(), "RANK() OVER ()");
+    }
+}
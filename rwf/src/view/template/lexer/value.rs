@@ -619,6 +619,7 @@ impl ToTemplateValue for crate::model::Value {
                 timestamp.format(&Rfc2822)?.to_template_value()
             }
             ModelValue::IpAddr(addr) => Ok(Value::String(addr.to_string())),
+            ModelValue::Interval(interval) => interval.as_seconds_f64().to_template_value(),
             ModelValue::Uuid(uuid) => Ok(Value::String(uuid.to_string())),
             ModelValue::List(list) => {
                 let mut new_list = vec![];
@@ -631,7 +632,9 @@ impl ToTemplateValue for crate::model::Value {
             | ModelValue::Placeholder(_)
             | ModelValue::Column(_)
             | ModelValue::Range(_)
-            | ModelValue::Function(_) => Ok(Value::Null), // value => todo!("model value {:?} to template value", value),
+            | ModelValue::Function(_)
+            | ModelValue::Window(_)
+            | ModelValue::Aggregate(_) => Ok(Value::Null), // value => todo!("model value {:?} to template value", value),
         }
     }
 }